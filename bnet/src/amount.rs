@@ -0,0 +1,326 @@
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, Neg, Sub};
+use std::str::FromStr;
+
+/// Number of decimal places every `Amount` carries. This is the one knob
+/// for token and cash precision; amounts persist as decimal strings, so
+/// stored states stay readable if it changes.
+pub const DECIMALS: u32 = 8;
+const SCALE: i128 = 10_i128.pow(DECIMALS);
+
+/// Fixed-point token or cash quantity held as integer base units
+/// (`10^-DECIMALS`). Arithmetic is exact; the `checked_*` methods report
+/// overflow and the operator impls panic on it, like debug-mode integers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Amount(i128);
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+
+    pub const fn from_units(units: i128) -> Self {
+        Amount(units)
+    }
+
+    pub const fn units(self) -> i128 {
+        self.0
+    }
+
+    pub fn whole(n: i64) -> Self {
+        Amount(n as i128 * SCALE)
+    }
+
+    /// Rounds to the nearest base unit; `None` for NaN or out-of-range input.
+    pub fn from_f64(value: f64) -> Option<Self> {
+        let units = (value * SCALE as f64).round();
+        if !units.is_finite() || units.abs() >= i128::MAX as f64 {
+            return None;
+        }
+        Some(Amount(units as i128))
+    }
+
+    /// Lossy; for ratios and display-only math such as vote weights.
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / SCALE as f64
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn checked_add(self, other: Amount) -> Option<Amount> {
+        self.0.checked_add(other.0).map(Amount)
+    }
+
+    pub fn checked_sub(self, other: Amount) -> Option<Amount> {
+        self.0.checked_sub(other.0).map(Amount)
+    }
+
+    /// `self * numerator / denominator`, rounded toward zero.
+    pub fn checked_mul_div(self, numerator: i128, denominator: i128) -> Option<Amount> {
+        if denominator == 0 {
+            return None;
+        }
+        self.0
+            .checked_mul(numerator)
+            .map(|n| Amount(n / denominator))
+    }
+
+    /// Scales by a rate such as `0.2`, with the rate itself fixed to
+    /// `DECIMALS` places first so the product is exact and rounds down.
+    pub fn checked_mul_rate(self, rate: f64) -> Option<Amount> {
+        let rate = Amount::from_f64(rate)?;
+        self.checked_mul_div(rate.0, SCALE)
+    }
+
+    pub fn max(self, other: Amount) -> Amount {
+        if self >= other { self } else { other }
+    }
+
+    pub fn min(self, other: Amount) -> Amount {
+        if self <= other { self } else { other }
+    }
+}
+
+/// Splits `total` in proportion to `weights`, rounding every share down.
+/// The shares never sum to more than `total`; the caller decides where the
+/// remainder goes. `None` if the weights are unusable or the math overflows.
+pub fn split_pro_rata(total: Amount, weights: &[f64]) -> Option<Vec<Amount>> {
+    let units: Vec<i128> = weights
+        .iter()
+        .map(|w| Amount::from_f64(*w).map(|a| a.0))
+        .collect::<Option<_>>()?;
+    if units.iter().any(|u| *u < 0) {
+        return None;
+    }
+    let total_weight = units
+        .iter()
+        .try_fold(0_i128, |acc, u| acc.checked_add(*u))?;
+    if total_weight == 0 {
+        return None;
+    }
+    units
+        .into_iter()
+        .map(|u| total.checked_mul_div(u, total_weight))
+        .collect()
+}
+
+impl Add for Amount {
+    type Output = Amount;
+    fn add(self, other: Amount) -> Amount {
+        self.checked_add(other).expect("amount overflow")
+    }
+}
+
+impl Sub for Amount {
+    type Output = Amount;
+    fn sub(self, other: Amount) -> Amount {
+        self.checked_sub(other).expect("amount overflow")
+    }
+}
+
+impl Neg for Amount {
+    type Output = Amount;
+    fn neg(self) -> Amount {
+        Amount(self.0.checked_neg().expect("amount overflow"))
+    }
+}
+
+impl Sum for Amount {
+    fn sum<I: Iterator<Item = Amount>>(iter: I) -> Amount {
+        iter.fold(Amount::ZERO, Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Amount> for Amount {
+    fn sum<I: Iterator<Item = &'a Amount>>(iter: I) -> Amount {
+        iter.copied().sum()
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = match (self.0 < 0, f.sign_plus()) {
+            (true, _) => "-",
+            (false, true) => "+",
+            (false, false) => "",
+        };
+        let abs = self.0.unsigned_abs();
+        let scale = SCALE as u128;
+        let whole = abs / scale;
+        let frac = abs % scale;
+        if frac == 0 {
+            return write!(f, "{}{}", sign, whole);
+        }
+        let digits = format!("{:0width$}", frac, width = DECIMALS as usize);
+        write!(f, "{}{}.{}", sign, whole, digits.trim_end_matches('0'))
+    }
+}
+
+impl FromStr for Amount {
+    type Err = String;
+    /// Parses a plain decimal such as `12`, `-0.5` or `1000.00000001`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid amount: {}", s);
+        let (negative, digits) = match s.trim().strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.trim()),
+        };
+        let (whole, frac) = digits.split_once('.').unwrap_or((digits, ""));
+        if (whole.is_empty() && frac.is_empty())
+            || !whole.chars().all(|c| c.is_ascii_digit())
+            || !frac.chars().all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        if frac.len() > DECIMALS as usize {
+            return Err(format!(
+                "Amount {} has more than {} decimal places",
+                s, DECIMALS
+            ));
+        }
+        let whole: i128 = if whole.is_empty() {
+            0
+        } else {
+            whole.parse().map_err(|_| invalid())?
+        };
+        let frac_units: i128 = if frac.is_empty() {
+            0
+        } else {
+            let padded = format!("{:0<width$}", frac, width = DECIMALS as usize);
+            padded.parse().map_err(|_| invalid())?
+        };
+        let units = whole
+            .checked_mul(SCALE)
+            .and_then(|w| w.checked_add(frac_units))
+            .ok_or_else(invalid)?;
+        Ok(Amount(if negative { -units } else { units }))
+    }
+}
+
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// Accepts decimal strings and, for states written before amounts were
+/// fixed-point, plain JSON numbers.
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct AmountVisitor;
+
+        impl Visitor<'_> for AmountVisitor {
+            type Value = Amount;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a decimal string or number")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Amount, E> {
+                v.parse().map_err(E::custom)
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Amount, E> {
+                Ok(Amount::whole(v))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Amount, E> {
+                (v as i128)
+                    .checked_mul(SCALE)
+                    .map(Amount)
+                    .ok_or_else(|| E::custom("amount out of range"))
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Amount, E> {
+                Amount::from_f64(v).ok_or_else(|| E::custom("amount out of range"))
+            }
+        }
+
+        deserializer.deserialize_any(AmountVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amount(s: &str) -> Amount {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_and_format_round_trip() {
+        for s in ["0", "12", "-0.5", "1000.00000001", "0.1", "-42.125"] {
+            assert_eq!(amount(s).to_string(), s);
+        }
+        assert_eq!(amount("1.50").to_string(), "1.5");
+        assert_eq!(amount(".25"), amount("0.25"));
+        assert_eq!(amount("3"), Amount::whole(3));
+        assert_eq!(format!("{:+}", amount("2.5")), "+2.5");
+    }
+
+    #[test]
+    fn parse_rejects_malformed_and_overprecise_input() {
+        for s in ["", ".", "abc", "1.2.3", "--1", "1e5", "0.000000001"] {
+            assert!(s.parse::<Amount>().is_err(), "{s:?} parsed");
+        }
+    }
+
+    #[test]
+    fn serde_round_trips_and_reads_legacy_numbers() {
+        let a = amount("7.00000003");
+        let json = serde_json::to_string(&a).unwrap();
+        assert_eq!(json, "\"7.00000003\"");
+        assert_eq!(serde_json::from_str::<Amount>(&json).unwrap(), a);
+        assert_eq!(
+            serde_json::from_str::<Amount>("5").unwrap(),
+            Amount::whole(5)
+        );
+        assert_eq!(
+            serde_json::from_str::<Amount>("0.25").unwrap(),
+            amount("0.25")
+        );
+    }
+
+    #[test]
+    fn split_pro_rata_rounds_down_and_leaves_the_remainder() {
+        let total = Amount::from_units(10);
+        let shares = split_pro_rata(total, &[1.0, 1.0, 1.0]).unwrap();
+        assert_eq!(shares, vec![Amount::from_units(3); 3]);
+        let paid: Amount = shares.iter().sum();
+        assert_eq!(total - paid, Amount::from_units(1));
+
+        let shares = split_pro_rata(Amount::whole(100), &[3.0, 1.0]).unwrap();
+        assert_eq!(shares, vec![Amount::whole(75), Amount::whole(25)]);
+
+        let shares = split_pro_rata(Amount::whole(1), &[0.0, 2.0]).unwrap();
+        assert_eq!(shares, vec![Amount::ZERO, Amount::whole(1)]);
+    }
+
+    #[test]
+    fn split_pro_rata_rejects_unusable_weights() {
+        assert_eq!(split_pro_rata(Amount::whole(1), &[]), None);
+        assert_eq!(split_pro_rata(Amount::whole(1), &[0.0, 0.0]), None);
+        assert_eq!(split_pro_rata(Amount::whole(1), &[1.0, -1.0]), None);
+        assert_eq!(split_pro_rata(Amount::whole(1), &[f64::NAN]), None);
+    }
+
+    #[test]
+    fn mul_rate_rounds_down() {
+        assert_eq!(
+            Amount::from_units(7).checked_mul_rate(0.5),
+            Some(Amount::from_units(3))
+        );
+        assert_eq!(
+            Amount::whole(100).checked_mul_rate(0.2),
+            Some(Amount::whole(20))
+        );
+    }
+}
@@ -1,24 +1,164 @@
+use crate::amount::{Amount, split_pro_rata};
+use crate::error::{
+    EngineError, EntityKind, Guardrail, PromotionGap, TransferRestriction, Violation,
+};
+use crate::ledger::{balance, post, reconcile, transfer};
 use crate::model::*;
-use chrono::{DateTime, Duration, Utc};
-use std::collections::HashMap;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc, Weekday};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
 
-pub fn net_revenue(event: &RevenueEvent) -> f64 {
-    (event.gross_revenue - event.refund_amount).max(0.0)
+pub fn new_company(employees: usize, now: DateTime<Utc>) -> CompanyState {
+    let mut state = CompanyState {
+        holders: HashMap::new(),
+        treasury_tokens: Amount::ZERO,
+        treasury_cash: Amount::ZERO,
+        positions: vec![],
+        employee_count: employees,
+        token_price_history: vec![],
+        emission_policy: EmissionPolicy {
+            weekly_payout_percent: 0.20,
+            halving_interval_days: 365,
+            genesis: now,
+        },
+        governance_policy: GovernancePolicy {
+            vote_threshold: 2.0 / 3.0,
+            value_drop_trigger: 0.20,
+            value_window_days: 30,
+            voting_power: VotingPowerPolicy::default(),
+            allow_vote_changes: true,
+            quorum: 0.5,
+            voting_period_days: 7,
+            eligibility: vec![],
+            accountable_roles: vec![RoleTier::CEO, RoleTier::CSuite],
+            value_drop_cooldown_days: 7,
+            price_average: PriceAverage::default(),
+        },
+        votes: HashMap::new(),
+        marketplace: vec![],
+        tasks: HashMap::new(),
+        tokenomics: None,
+        onboarding_policy: OnboardingPolicy {
+            early_joiner_limit: 0,
+            early_joiner_reward: Amount::ZERO,
+            early_joiner_vesting: None,
+        },
+        onboarding_count: 0,
+        event_seq: 0,
+        journal: vec![],
+        revenue_events: vec![],
+        weekly_emissions: vec![],
+        payout_runs: vec![],
+        vesting_schedules: vec![],
+        vesting_policy: VestingPolicy::default(),
+        transfer_policy: TransferPolicy::default(),
+        transfers: vec![],
+        stakes: HashMap::new(),
+        staking_policy: StakingPolicy::default(),
+        promotion_policy: PromotionPolicy::default(),
+        delegations: vec![],
+        value_drops: vec![],
+        auctions: HashMap::new(),
+        token_receipts: HashMap::new(),
+    };
+    ensure_positions(&mut state);
+    state
 }
 
-pub fn aggregate_weekly_revenue(events: &[RevenueEvent]) -> f64 {
-    events.iter().map(net_revenue).sum()
+pub fn net_revenue(event: &RevenueEvent) -> Amount {
+    let gross = event.gross_revenue.max(Amount::ZERO);
+    let refund = event.refund_amount.max(Amount::ZERO).min(gross);
+    gross - refund
 }
 
-pub fn halving_factor(policy: &EmissionPolicy, now: DateTime<Utc>) -> f64 {
+pub fn aggregate_weekly_revenue(events: &[RevenueEvent]) -> Result<Amount, EngineError> {
+    events
+        .iter()
+        .try_fold(Amount::ZERO, |acc, e| acc.checked_add(net_revenue(e)))
+        .ok_or(EngineError::Overflow)
+}
+
+pub fn ingest_revenue(
+    state: &mut CompanyState,
+    event: &RevenueEvent,
+) -> Result<Amount, EngineError> {
+    let at = event.timestamp;
+    let gross = event.gross_revenue.max(Amount::ZERO);
+    // Refunds beyond gross are not clawed back from the treasury.
+    let refund = event.refund_amount.max(Amount::ZERO).min(gross);
+    transfer(
+        state,
+        at,
+        Asset::Cash,
+        "revenue",
+        Account::Revenue,
+        Account::Treasury,
+        gross,
+    )?;
+    transfer(
+        state,
+        at,
+        Asset::Cash,
+        "refund",
+        Account::Treasury,
+        Account::Refunds,
+        refund,
+    )?;
+    state.revenue_events.push(event.clone());
+    Ok(net_revenue(event))
+}
+
+/// ISO week id of `at`, e.g. `2026-W07`.
+pub fn iso_week_id(at: DateTime<Utc>) -> String {
+    let week = at.iso_week();
+    format!("{}-W{:02}", week.year(), week.week())
+}
+
+/// Monday 00:00 UTC of the ISO week containing `at`.
+pub fn iso_week_start(at: DateTime<Utc>) -> DateTime<Utc> {
+    let week = at.iso_week();
+    NaiveDate::from_isoywd_opt(week.year(), week.week(), Weekday::Mon)
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|d| d.and_utc())
+        .unwrap_or(at)
+}
+
+/// Net revenue ingested from the start of `as_of`'s ISO week up to `as_of`.
+pub fn week_net_revenue(state: &CompanyState, as_of: DateTime<Utc>) -> Result<Amount, EngineError> {
+    let start = iso_week_start(as_of);
+    let events: Vec<RevenueEvent> = state
+        .revenue_events
+        .iter()
+        .filter(|e| e.timestamp >= start && e.timestamp <= as_of)
+        .cloned()
+        .collect();
+    aggregate_weekly_revenue(&events)
+}
+
+pub fn halvings(policy: &EmissionPolicy, now: DateTime<Utc>) -> u32 {
     let days = (now - policy.genesis).num_days();
-    let halvings = days / policy.halving_interval_days;
-    0.5_f64.powi(halvings as i32)
+    (days / policy.halving_interval_days).clamp(0, u32::MAX as i64) as u32
+}
+
+pub fn halving_factor(policy: &EmissionPolicy, now: DateTime<Utc>) -> f64 {
+    0.5_f64.powi(halvings(policy, now) as i32)
 }
 
-pub fn weekly_emission(policy: &EmissionPolicy, week_revenue: f64, now: DateTime<Utc>) -> f64 {
-    let base = week_revenue * policy.weekly_payout_percent;
-    base * halving_factor(policy, now)
+/// Tokens to mint for a week's revenue, rounded down to a whole base unit.
+pub fn weekly_emission(
+    policy: &EmissionPolicy,
+    week_revenue: Amount,
+    now: DateTime<Utc>,
+) -> Result<Amount, EngineError> {
+    let base = week_revenue
+        .checked_mul_rate(policy.weekly_payout_percent)
+        .ok_or(EngineError::Overflow)?;
+    let halvings = halvings(policy, now);
+    if halvings >= 127 {
+        return Ok(Amount::ZERO);
+    }
+    base.checked_mul_div(1, 1_i128 << halvings)
+        .ok_or(EngineError::Overflow)
 }
 
 pub fn required_positions(employee_count: usize) -> HashMap<RoleTier, usize> {
@@ -41,7 +181,11 @@ pub fn required_positions(employee_count: usize) -> HashMap<RoleTier, usize> {
 }
 
 pub fn ensure_positions(state: &mut CompanyState) {
-    let required = required_positions(state.employee_count);
+    // In tier order, so replaying the log lays positions out the same way.
+    let mut required: Vec<(RoleTier, usize)> = required_positions(state.employee_count)
+        .into_iter()
+        .collect();
+    required.sort();
     for (tier, count) in required {
         let existing = state.positions.iter().filter(|p| p.tier == tier).count();
         if existing < count {
@@ -57,29 +201,403 @@ pub fn ensure_positions(state: &mut CompanyState) {
     }
 }
 
-pub fn run_weekly_emission(state: &mut CompanyState, week_revenue: f64, now: DateTime<Utc>) -> f64 {
-    let minted = weekly_emission(&state.emission_policy, week_revenue, now);
-    state.treasury_tokens += minted;
-    minted
+/// How much of a mint request was issued and how much the supply cap held back.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MintOutcome {
+    pub requested: Amount,
+    pub minted: Amount,
+    pub withheld: Amount,
 }
 
-pub fn record_token_price(state: &mut CompanyState, price: f64, now: DateTime<Utc>) {
-    state.token_price_history.push((now, price));
-    // keep only 30 days of history
-    let cutoff = now - Duration::days(state.governance_policy.value_window_days);
-    state.token_price_history.retain(|(ts, _)| *ts >= cutoff);
+/// Supply still mintable under the tokenomics cap; `None` when uncapped.
+pub fn remaining_supply(state: &CompanyState) -> Option<Amount> {
+    state
+        .tokenomics
+        .as_ref()
+        .map(|t| (t.total_supply_cap - t.minted_supply).max(Amount::ZERO))
 }
 
-pub fn rolling_average_price(state: &CompanyState) -> Option<f64> {
-    if state.token_price_history.is_empty() {
-        return None;
+/// Mints new tokens into `to`, counting them against the supply cap. Past
+/// the cap the request is clamped, or rejected outright if `clamp` is false.
+fn mint(
+    state: &mut CompanyState,
+    to: Account,
+    requested: Amount,
+    clamp: bool,
+    memo: &str,
+    now: DateTime<Utc>,
+) -> Result<MintOutcome, EngineError> {
+    if requested.is_negative() {
+        return Err(EngineError::invalid("mint amount must be >= 0"));
+    }
+    let minted = match remaining_supply(state) {
+        Some(available) if requested > available && !clamp => {
+            return Err(EngineError::SupplyCapExceeded {
+                requested,
+                available,
+            });
+        }
+        Some(available) => requested.min(available),
+        None => requested,
+    };
+    transfer(state, now, Asset::Tokens, memo, Account::Mint, to, minted)?;
+    if let Some(t) = state.tokenomics.as_mut() {
+        t.minted_supply = t
+            .minted_supply
+            .checked_add(minted)
+            .ok_or(EngineError::Overflow)?;
+    }
+    Ok(MintOutcome {
+        requested,
+        minted,
+        withheld: requested - minted,
+    })
+}
+
+/// Emits for the ISO week containing `as_of`, from the revenue ingested in
+/// it up to `as_of`. Only for replaying logs written before emission
+/// waited for the week to end; see [`emit_completed_week`].
+pub fn emit_week(
+    state: &mut CompanyState,
+    as_of: DateTime<Utc>,
+) -> Result<WeeklyEmission, EngineError> {
+    let net_revenue = week_net_revenue(state, as_of)?;
+    record_week_emission(state, iso_week_id(as_of), net_revenue, as_of)
+}
+
+/// ISO week id of the last week that was over by `at`.
+pub fn last_completed_week(at: DateTime<Utc>) -> String {
+    iso_week_id(iso_week_start(at) - Duration::seconds(1))
+}
+
+/// Emits for `week` from the revenue ingested in it. The week must have
+/// ended by `now`, so revenue that arrives late in the week is not left
+/// out, and each week can be emitted only once.
+pub fn emit_completed_week(
+    state: &mut CompanyState,
+    week: &str,
+    now: DateTime<Utc>,
+) -> Result<WeeklyEmission, EngineError> {
+    let (start, end) = parse_iso_week(week)?;
+    let week = iso_week_id(start);
+    if now < end {
+        return Err(EngineError::invalid(format!(
+            "week {} is still open until {}",
+            week, end
+        )));
+    }
+    let events: Vec<RevenueEvent> = state
+        .revenue_events
+        .iter()
+        .filter(|e| e.timestamp >= start && e.timestamp < end)
+        .cloned()
+        .collect();
+    let net_revenue = aggregate_weekly_revenue(&events)?;
+    record_week_emission(state, week, net_revenue, end - Duration::seconds(1))
+}
+
+fn record_week_emission(
+    state: &mut CompanyState,
+    week: String,
+    net_revenue: Amount,
+    as_of: DateTime<Utc>,
+) -> Result<WeeklyEmission, EngineError> {
+    if state.weekly_emissions.iter().any(|e| e.week == week) {
+        return Err(EngineError::WeekAlreadyEmitted { week });
+    }
+    let outcome = run_weekly_emission(state, net_revenue, as_of)?;
+    let emission = WeeklyEmission {
+        week,
+        as_of,
+        net_revenue,
+        minted: outcome.minted,
+        withheld: outcome.withheld,
+    };
+    state.weekly_emissions.push(emission.clone());
+    Ok(emission)
+}
+
+/// Start (inclusive) and end (exclusive) of an ISO week id like `2026-W07`.
+pub fn parse_iso_week(week: &str) -> Result<(DateTime<Utc>, DateTime<Utc>), EngineError> {
+    let invalid =
+        || EngineError::invalid(format!("bad ISO week {:?}, expected e.g. 2026-W07", week));
+    let (year, num) = week.split_once("-W").ok_or_else(invalid)?;
+    let year: i32 = year.parse().map_err(|_| invalid())?;
+    let num: u32 = num.parse().map_err(|_| invalid())?;
+    let start = NaiveDate::from_isoywd_opt(year, num, Weekday::Mon)
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .ok_or_else(invalid)?
+        .and_utc();
+    Ok((start, start + Duration::days(7)))
+}
+
+/// The canonical id of an ISO week, so `2026-W7` and `2026-W007` both
+/// name `2026-W07`.
+pub fn canonical_iso_week(week: &str) -> Result<String, EngineError> {
+    let (start, _) = parse_iso_week(week)?;
+    Ok(iso_week_id(start))
+}
+
+/// Whether two week ids name the same ISO week; runs logged before ids
+/// were canonicalized may be stored in another spelling.
+pub fn same_week(a: &str, b: &str) -> bool {
+    a == b || canonical_iso_week(a).is_ok_and(|a| canonical_iso_week(b).is_ok_and(|b| a == b))
+}
+
+/// Allocations for a payout week: one unit of weight per task finalized in
+/// `[start, end)`, split evenly between its assignees.
+pub fn completed_task_allocations(
+    state: &CompanyState,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Vec<WorkAllocation> {
+    let mut tasks: Vec<&Task> = state
+        .tasks
+        .values()
+        .filter(|t| matches!(t.status, TaskStatus::Done))
+        .filter(|t| t.updated_at >= start && t.updated_at < end && !t.assigned.is_empty())
+        .collect();
+    tasks.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut allocations: Vec<WorkAllocation> = vec![];
+    for task in tasks {
+        let weight = 1.0 / task.assigned.len() as f64;
+        for assignment in &task.assigned {
+            match allocations
+                .iter_mut()
+                .find(|a| a.holder_id == assignment.assignee_id)
+            {
+                Some(a) => a.weight += weight,
+                None => allocations.push(WorkAllocation {
+                    holder_id: assignment.assignee_id.clone(),
+                    weight,
+                }),
+            }
+        }
+    }
+    allocations
+}
+
+/// The live (unreversed) payout run for `week`, if any.
+pub fn payout_run<'a>(state: &'a CompanyState, week: &str) -> Option<&'a PayoutRun> {
+    state
+        .payout_runs
+        .iter()
+        .find(|r| same_week(&r.week, week) && r.reversed_at.is_none())
+}
+
+/// Emits for `week` unless that already happened, then distributes the
+/// week's emission by `source`. A week with a live run is rejected.
+pub fn run_payout(
+    state: &mut CompanyState,
+    week: &str,
+    source: &PayoutSource,
+    now: DateTime<Utc>,
+) -> Result<PayoutRun, EngineError> {
+    let canonical = canonical_iso_week(week)?;
+    let week = canonical.as_str();
+    if payout_run(state, week).is_some() {
+        return Err(EngineError::AlreadyExists {
+            kind: EntityKind::PayoutRun,
+            id: week.to_string(),
+        });
+    }
+    let (start, end) = parse_iso_week(week)?;
+    let allocations = match source {
+        PayoutSource::Tasks => completed_task_allocations(state, start, end),
+        PayoutSource::Template { name } => allocation_template(name)
+            .ok_or_else(|| EngineError::invalid(format!("unknown template: {}", name)))?,
+    };
+    if allocations.is_empty() {
+        return Err(EngineError::invalid(format!(
+            "no tasks completed in {}; use a template instead",
+            week
+        )));
+    }
+
+    let existing = state.weekly_emissions.iter().find(|e| e.week == week);
+    let (emitted, minted) = match existing {
+        Some(emission) => (false, emission.minted),
+        None => (true, emit_week(state, end - Duration::seconds(1))?.minted),
+    };
+    let shares = distribute_shares(
+        state,
+        &allocations,
+        minted,
+        &format!("payout run {}", week),
+        now,
+    )?;
+    let run = PayoutRun {
+        week: week.to_string(),
+        run_at: now,
+        source: source.clone(),
+        emitted,
+        minted,
+        distributed: shares.iter().map(|(share, _)| *share).sum(),
+        shares: allocations
+            .into_iter()
+            .zip(shares)
+            .map(|(a, (tokens, vesting_schedule))| PayoutShare {
+                holder_id: a.holder_id,
+                weight: a.weight,
+                tokens,
+                vesting_schedule,
+            })
+            .collect(),
+        reversed_at: None,
+    };
+    state.payout_runs.push(run.clone());
+    Ok(run)
+}
+
+/// Claws a run's shares back into the treasury and, if the run did the
+/// emission itself, burns it again so the week can be run afresh.
+pub fn reverse_payout(
+    state: &mut CompanyState,
+    week: &str,
+    now: DateTime<Utc>,
+) -> Result<(), EngineError> {
+    let canonical = canonical_iso_week(week)?;
+    let week = canonical.as_str();
+    let idx = state
+        .payout_runs
+        .iter()
+        .position(|r| same_week(&r.week, week) && r.reversed_at.is_none())
+        .ok_or_else(|| EngineError::not_found(EntityKind::PayoutRun, week))?;
+    let run = state.payout_runs[idx].clone();
+    let memo = format!("reversal of payout run {}", week);
+
+    if !run.distributed.is_zero() {
+        let mut postings = vec![Posting {
+            account: Account::Treasury,
+            amount: run.distributed,
+        }];
+        for share in run.shares.iter().filter(|s| !s.tokens.is_zero()) {
+            // Vesting shares come back out of the pool as far as they are
+            // still unvested, and out of the holder's balance beyond that.
+            let mut from_holder = share.tokens;
+            if let Some(id) = share.vesting_schedule
+                && let Some(schedule) = state.vesting_schedules.iter_mut().find(|s| s.id == id)
+            {
+                let from_pool = schedule.unvested().min(share.tokens);
+                schedule.forfeited = schedule.forfeited + from_pool;
+                from_holder = from_holder - from_pool;
+                postings.push(Posting {
+                    account: Account::Vesting,
+                    amount: -from_pool,
+                });
+            }
+            postings.push(Posting {
+                account: Account::Holder(share.holder_id.clone()),
+                amount: -from_holder,
+            });
+        }
+        post(state, now, Asset::Tokens, &memo, postings)?;
+    }
+    if run.emitted {
+        transfer(
+            state,
+            now,
+            Asset::Tokens,
+            &memo,
+            Account::Treasury,
+            Account::Mint,
+            run.minted,
+        )?;
+        if let Some(t) = state.tokenomics.as_mut() {
+            t.minted_supply = (t.minted_supply - run.minted).max(Amount::ZERO);
+        }
+        state.weekly_emissions.retain(|e| !same_week(&e.week, week));
     }
-    let sum: f64 = state.token_price_history.iter().map(|(_, p)| p).sum();
-    Some(sum / state.token_price_history.len() as f64)
+    state.payout_runs[idx].reversed_at = Some(now);
+    Ok(())
+}
+
+/// Mints the week's emission into the treasury, clamped to the supply cap.
+pub fn run_weekly_emission(
+    state: &mut CompanyState,
+    week_revenue: Amount,
+    now: DateTime<Utc>,
+) -> Result<MintOutcome, EngineError> {
+    let requested = weekly_emission(&state.emission_policy, week_revenue, now)?;
+    mint(
+        state,
+        Account::Treasury,
+        requested,
+        true,
+        "weekly emission",
+        now,
+    )
+}
+
+/// Adds a price sample, keeping the history in time order even when a feed
+/// backfills older ticks. The full history is kept; averages apply the
+/// value window when they are queried.
+pub fn record_token_price(state: &mut CompanyState, price: f64, now: DateTime<Utc>) {
+    let idx = state
+        .token_price_history
+        .partition_point(|(ts, _)| *ts <= now);
+    state.token_price_history.insert(idx, (now, price));
+}
+
+/// Average token price over the value window ending at `now`, by the
+/// governance policy's averaging method. `None` if no price was recorded
+/// in the window. The time-weighted methods also count the last price
+/// before the window, as standing from the window's start until the first
+/// sample inside it.
+pub fn rolling_average_price(state: &CompanyState, now: DateTime<Utc>) -> Option<f64> {
+    let start = now - Duration::days(state.governance_policy.value_window_days);
+    let history = &state.token_price_history;
+    let first = history.partition_point(|(ts, _)| *ts < start);
+    let end = history.partition_point(|(ts, _)| *ts <= now);
+    let window = history.get(first..end).unwrap_or_default();
+    let (_, last) = *window.last()?;
+    let carried = first.checked_sub(1).map(|idx| (start, history[idx].1));
+    let timeline: Vec<(DateTime<Utc>, f64)> =
+        carried.into_iter().chain(window.iter().copied()).collect();
+    let average = match state.governance_policy.price_average {
+        PriceAverage::Mean => window.iter().map(|(_, p)| p).sum::<f64>() / window.len() as f64,
+        PriceAverage::Median => {
+            let mut prices: Vec<f64> = window.iter().map(|(_, p)| *p).collect();
+            prices.sort_by(f64::total_cmp);
+            let mid = prices.len() / 2;
+            if prices.len().is_multiple_of(2) {
+                (prices[mid - 1] + prices[mid]) / 2.0
+            } else {
+                prices[mid]
+            }
+        }
+        PriceAverage::TimeWeighted => {
+            let mut weighted = 0.0;
+            let mut total = 0.0;
+            for (idx, (ts, price)) in timeline.iter().enumerate() {
+                let until = timeline.get(idx + 1).map_or(now, |(next, _)| *next);
+                let secs = (until - *ts).num_seconds() as f64;
+                weighted += price * secs;
+                total += secs;
+            }
+            // Every sample taken at `now`: nothing has stood yet.
+            if total > 0.0 { weighted / total } else { last }
+        }
+        PriceAverage::Exponential { half_life_days } => {
+            let half_life = half_life_days * 86_400.0;
+            let (mut at, mut average) = timeline[0];
+            for (ts, price) in &timeline[1..] {
+                let elapsed = (*ts - at).num_seconds() as f64;
+                let alpha = 1.0 - 0.5_f64.powf(elapsed / half_life);
+                average += alpha * (price - average);
+                at = *ts;
+            }
+            average
+        }
+    };
+    Some(average)
 }
 
-pub fn value_drop_triggered(state: &CompanyState, current_price: f64) -> bool {
-    if let Some(avg) = rolling_average_price(state) {
+/// Whether `current_price` sits far enough below the rolling average at
+/// `now` to count as a value drop.
+pub fn value_drop_triggered(state: &CompanyState, current_price: f64, now: DateTime<Utc>) -> bool {
+    if let Some(avg) = rolling_average_price(state, now) {
         let drop = (avg - current_price) / avg;
         drop >= state.governance_policy.value_drop_trigger
     } else {
@@ -87,69 +605,395 @@ pub fn value_drop_triggered(state: &CompanyState, current_price: f64) -> bool {
     }
 }
 
-pub fn apply_promotion_bid(state: &mut CompanyState, bid: PromotionBid) -> Result<(), String> {
-    let bidder = state
-        .holders
-        .get_mut(&bid.bidder_id)
-        .ok_or("Bidder not found")?;
-    if bidder.cash < bid.bid_amount {
-        return Err("Insufficient cash for bid".into());
+pub fn apply_promotion_bid(state: &mut CompanyState, bid: PromotionBid) -> Result<(), EngineError> {
+    if !state.holders.contains_key(&bid.bidder_id) {
+        return Err(EngineError::not_found(EntityKind::Holder, &bid.bidder_id));
     }
+    check_promotion(state, &bid.bidder_id, bid.target_role, bid.timestamp)?;
+    let slot =
+        free_position(state, bid.target_role, None).ok_or(EngineError::NoAvailablePosition {
+            role: bid.target_role,
+        })?;
 
-    let position = state
-        .positions
-        .iter_mut()
-        .find(|p| p.tier == bid.target_role && p.holder_id.is_none())
-        .ok_or("No available position for this role")?;
-
-    bidder.cash -= bid.bid_amount;
-    state.treasury_cash += bid.bid_amount;
+    transfer(
+        state,
+        bid.timestamp,
+        Asset::Cash,
+        &format!("promotion bid for {}", bid.target_role),
+        Account::Holder(bid.bidder_id.clone()),
+        Account::Treasury,
+        bid.bid_amount,
+    )?;
 
-    position.holder_id = Some(bidder.id.clone());
+    let position = &mut state.positions[slot];
+    position.holder_id = Some(bid.bidder_id.clone());
     position.acquired_at = Some(bid.timestamp);
     position.price_paid = Some(bid.bid_amount);
 
-    bidder.positions.push(bid.target_role);
+    if let Some(bidder) = state.holders.get_mut(&bid.bidder_id) {
+        bidder.positions.push(bid.target_role);
+    }
+    Ok(())
+}
+
+/// A vacant position of `role` that no running auction other than
+/// `auction_id` holds back.
+fn free_position(state: &CompanyState, role: RoleTier, auction_id: Option<&str>) -> Option<usize> {
+    let held_back = state
+        .auctions
+        .values()
+        .filter(|a| a.role == role && a.settlement.is_none())
+        .filter(|a| Some(a.id.as_str()) != auction_id)
+        .count();
+    state
+        .positions
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| p.tier == role && p.holder_id.is_none())
+        .nth(held_back)
+        .map(|(idx, _)| idx)
+}
+
+/// Puts a vacant `role` position up for auction until `ends_at`.
+#[allow(clippy::too_many_arguments)]
+pub fn open_auction(
+    state: &mut CompanyState,
+    id: &str,
+    role: RoleTier,
+    format: AuctionFormat,
+    pricing: AuctionPricing,
+    reserve_price: Amount,
+    ends_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<(), EngineError> {
+    if state.auctions.contains_key(id) {
+        return Err(EngineError::AlreadyExists {
+            kind: EntityKind::Auction,
+            id: id.to_string(),
+        });
+    }
+    if reserve_price.is_negative() {
+        return Err(EngineError::invalid("reserve price must be >= 0"));
+    }
+    if ends_at <= now {
+        return Err(EngineError::invalid("auction must end after it opens"));
+    }
+    if free_position(state, role, None).is_none() {
+        return Err(EngineError::NoAvailablePosition { role });
+    }
+    state.auctions.insert(
+        id.to_string(),
+        Auction {
+            id: id.to_string(),
+            role,
+            format,
+            pricing,
+            reserve_price,
+            opened_at: now,
+            ends_at,
+            bids: BTreeMap::new(),
+            settlement: None,
+        },
+    );
+    Ok(())
+}
+
+/// Places or raises `bidder_id`'s bid, moving the extra cash into escrow.
+/// In an ascending auction the bid must beat the leader, who is refunded.
+pub fn place_auction_bid(
+    state: &mut CompanyState,
+    auction_id: &str,
+    bidder_id: &str,
+    amount: Amount,
+    now: DateTime<Utc>,
+) -> Result<(), EngineError> {
+    if !state.holders.contains_key(bidder_id) {
+        return Err(EngineError::not_found(EntityKind::Holder, bidder_id));
+    }
+    let auction = state
+        .auctions
+        .get(auction_id)
+        .ok_or_else(|| EngineError::not_found(EntityKind::Auction, auction_id))?;
+    if auction.settlement.is_some() || now >= auction.ends_at {
+        return Err(EngineError::AuctionClosed {
+            auction_id: auction_id.to_string(),
+        });
+    }
+    check_promotion(state, bidder_id, auction.role, now)?;
+    let too_low = |minimum| EngineError::BidTooLow {
+        auction_id: auction_id.to_string(),
+        minimum,
+    };
+    if amount < auction.reserve_price {
+        return Err(too_low(auction.reserve_price));
+    }
+    let standing = auction.bids.get(bidder_id);
+    if let Some(standing) = standing
+        && amount <= standing.amount
+    {
+        return Err(EngineError::invalid(format!(
+            "bid must raise {}'s standing bid of {}",
+            bidder_id, standing.amount
+        )));
+    }
+    let mut outbid = None;
+    if auction.format == AuctionFormat::Ascending
+        && let Some((leader, lead)) = auction.leader()
+        && leader != bidder_id
+    {
+        if amount <= lead.amount {
+            return Err(too_low(lead.amount + Amount::from_units(1)));
+        }
+        outbid = Some((leader.clone(), lead.amount));
+    }
+    let top_up = match standing {
+        Some(standing) if standing.escrowed => amount - standing.amount,
+        _ => amount,
+    };
+
+    let memo = format!("bid in auction {}", auction_id);
+    transfer(
+        state,
+        now,
+        Asset::Cash,
+        &memo,
+        Account::Holder(bidder_id.to_string()),
+        Account::Escrow,
+        top_up,
+    )?;
+    if let Some((leader, lead_amount)) = &outbid {
+        transfer(
+            state,
+            now,
+            Asset::Cash,
+            &format!("outbid in auction {}", auction_id),
+            Account::Escrow,
+            Account::Holder(leader.clone()),
+            *lead_amount,
+        )?;
+    }
+    let Some(auction) = state.auctions.get_mut(auction_id) else {
+        return Ok(());
+    };
+    if let Some((leader, _)) = outbid
+        && let Some(bid) = auction.bids.get_mut(&leader)
+    {
+        bid.escrowed = false;
+    }
+    auction.bids.insert(
+        bidder_id.to_string(),
+        AuctionBid {
+            amount,
+            placed_at: now,
+            escrowed: true,
+        },
+    );
     Ok(())
 }
 
+/// Ends an auction whose time is up: the best bid from a holder still
+/// eligible for the role takes the position at the auction's price, and
+/// every other escrowed bid is refunded. Only a bid still in escrow can win,
+/// so an ascending auction whose leader is no longer eligible goes unsold.
+/// If nobody bid, or the position was filled some other way, everyone is
+/// refunded and the auction closes without a winner.
+pub fn settle_auction(
+    state: &mut CompanyState,
+    auction_id: &str,
+    now: DateTime<Utc>,
+) -> Result<AuctionSettlement, EngineError> {
+    let auction = state
+        .auctions
+        .get(auction_id)
+        .ok_or_else(|| EngineError::not_found(EntityKind::Auction, auction_id))?;
+    if auction.settlement.is_some() {
+        return Err(EngineError::invalid(format!(
+            "auction {} is already settled",
+            auction_id
+        )));
+    }
+    if now < auction.ends_at {
+        return Err(EngineError::invalid(format!(
+            "auction {} runs until {}",
+            auction_id, auction.ends_at
+        )));
+    }
+    let role = auction.role;
+    let slot = free_position(state, role, Some(auction_id));
+    // Bidders may have lost eligibility since bidding; their bids neither
+    // win nor set the price.
+    let eligible: Vec<&String> = auction
+        .bids
+        .keys()
+        .filter(|id| promotion_gaps(state, id, role, now).is_ok_and(|gaps| gaps.is_empty()))
+        .collect();
+    let leader = auction.leader_among(|id, bid| bid.escrowed && eligible.iter().any(|e| *e == id));
+    let winner = slot.and(leader).map(|(id, bid)| {
+        let price = match auction.pricing {
+            AuctionPricing::FirstPrice => bid.amount,
+            AuctionPricing::SecondPrice => auction
+                .bids
+                .iter()
+                .filter(|(other, _)| *other != id && eligible.contains(other))
+                .map(|(_, b)| b.amount)
+                .max()
+                .unwrap_or(Amount::ZERO)
+                .max(auction.reserve_price)
+                .min(bid.amount),
+        };
+        (id.clone(), price)
+    });
+    let escrowed: Vec<(String, Amount)> = auction
+        .bids
+        .iter()
+        .filter(|(_, b)| b.escrowed)
+        .map(|(id, b)| (id.clone(), b.amount))
+        .collect();
+
+    let memo = format!("auction {} for {}", auction_id, role);
+    for (bidder, held) in escrowed {
+        let mut refund = held;
+        if let Some((winner, price)) = &winner
+            && *winner == bidder
+        {
+            transfer(
+                state,
+                now,
+                Asset::Cash,
+                &memo,
+                Account::Escrow,
+                Account::Treasury,
+                *price,
+            )?;
+            refund = held - *price;
+        }
+        transfer(
+            state,
+            now,
+            Asset::Cash,
+            &format!("refund from auction {}", auction_id),
+            Account::Escrow,
+            Account::Holder(bidder),
+            refund,
+        )?;
+    }
+    if let (Some(slot), Some((winner, price))) = (slot, &winner) {
+        let position = &mut state.positions[slot];
+        position.holder_id = Some(winner.clone());
+        position.acquired_at = Some(now);
+        position.price_paid = Some(*price);
+        if let Some(holder) = state.holders.get_mut(winner) {
+            holder.positions.push(role);
+        }
+    }
+
+    let settlement = AuctionSettlement {
+        winner_id: winner.as_ref().map(|(id, _)| id.clone()),
+        price: winner.map(|(_, price)| price).unwrap_or_default(),
+        settled_at: now,
+    };
+    if let Some(auction) = state.auctions.get_mut(auction_id) {
+        for bid in auction.bids.values_mut() {
+            bid.escrowed = false;
+        }
+        auction.settlement = Some(settlement.clone());
+    }
+    Ok(settlement)
+}
+
 pub fn distribute_tokens(
     state: &mut CompanyState,
     allocations: &[WorkAllocation],
-    total_tokens: f64,
-) -> Result<(), String> {
+    total_tokens: Amount,
+    now: DateTime<Utc>,
+) -> Result<Amount, EngineError> {
+    let shares = distribute_shares(state, allocations, total_tokens, "work distribution", now)?;
+    Ok(shares.iter().map(|(share, _)| *share).sum())
+}
+
+/// Pays `total_tokens` out of the treasury pro rata and returns each share,
+/// with the vesting schedule it went into where the recipient's role vests.
+fn distribute_shares(
+    state: &mut CompanyState,
+    allocations: &[WorkAllocation],
+    total_tokens: Amount,
+    memo: &str,
+    now: DateTime<Utc>,
+) -> Result<Vec<(Amount, Option<u64>)>, EngineError> {
+    if allocations.iter().any(|a| a.weight < 0.0) {
+        return Err(EngineError::invalid("allocation weights must be >= 0"));
+    }
     let total_weight: f64 = allocations.iter().map(|a| a.weight).sum();
     if total_weight <= 0.0 {
-        return Err("Total allocation weight must be > 0".into());
+        return Err(EngineError::invalid("total allocation weight must be > 0"));
     }
-    if state.treasury_tokens < total_tokens {
-        return Err("Insufficient treasury tokens".into());
+    if total_tokens.is_negative() {
+        return Err(EngineError::invalid("total tokens must be >= 0"));
     }
 
-    for allocation in allocations {
-        let share = (allocation.weight / total_weight) * total_tokens;
-        let holder = state
-            .holders
-            .get_mut(&allocation.holder_id)
-            .ok_or("Holder not found")?;
-        holder.tokens += share;
+    // Shares round down; whatever they leave over stays in the treasury.
+    let weights: Vec<f64> = allocations.iter().map(|a| a.weight).collect();
+    let shares = split_pro_rata(total_tokens, &weights).ok_or(EngineError::Overflow)?;
+    let distributed: Amount = shares.iter().sum();
+    if distributed.is_zero() {
+        return Ok(shares.into_iter().map(|share| (share, None)).collect());
     }
-    state.treasury_tokens -= total_tokens;
-    Ok(())
+    let mut postings = vec![Posting {
+        account: Account::Treasury,
+        amount: -distributed,
+    }];
+    let mut vesting = vec![];
+    for (allocation, share) in allocations.iter().zip(&shares) {
+        let terms = role_vesting_terms(state, &allocation.holder_id);
+        let account = match terms {
+            Some(_) if !share.is_zero() => Account::Vesting,
+            _ => Account::Holder(allocation.holder_id.clone()),
+        };
+        vesting.push(terms.filter(|_| !share.is_zero()));
+        postings.push(Posting {
+            account,
+            amount: *share,
+        });
+    }
+    post(state, now, Asset::Tokens, memo, postings)?;
+
+    let mut out = vec![];
+    for ((allocation, share), terms) in allocations.iter().zip(shares).zip(vesting) {
+        let schedule = match terms {
+            Some((role, terms)) => Some(start_vesting(
+                state,
+                &allocation.holder_id,
+                share,
+                terms,
+                Some(role),
+                now,
+            )?),
+            None => None,
+        };
+        out.push((share, schedule));
+    }
+    Ok(out)
 }
 
-pub fn submit_vote(record: &mut VoteRecord, vote: Vote) {
-    record.votes.push(vote);
+/// Records `vote` as its voter's standing ballot, moving any ballot it
+/// replaces into the vote's history.
+pub fn submit_vote(record: &mut VoteRecord, vote: Vote, now: DateTime<Utc>) {
+    if let Some(previous) = record.votes.insert(vote.voter_id.clone(), vote) {
+        record.ballot_history.push(SupersededBallot {
+            ballot: previous,
+            superseded_at: now,
+        });
+    }
 }
 
 pub fn vote_passed(record: &VoteRecord, threshold: f64) -> bool {
-    let total_weight: f64 = record.votes.iter().map(|v| v.weight).sum();
+    let total_weight: f64 = record.votes.values().map(|v| v.weight).sum();
     if total_weight == 0.0 {
         return false;
     }
     let approve_weight: f64 = record
         .votes
-        .iter()
+        .values()
         .filter(|v| v.approve)
         .map(|v| v.weight)
         .sum();
@@ -167,7 +1011,15 @@ pub fn remove_holder_from_role(state: &mut CompanyState, role: RoleTier, holder_
         pos.price_paid = None;
     }
     if let Some(holder) = state.holders.get_mut(holder_id) {
-        holder.positions.retain(|r| *r != role);
+        drop_role(holder, role);
+    }
+}
+
+/// Removes one occurrence of `role`; a holder may sit in several seats of
+/// the same tier.
+fn drop_role(holder: &mut Holder, role: RoleTier) {
+    if let Some(i) = holder.positions.iter().position(|r| *r == role) {
+        holder.positions.remove(i);
     }
 }
 
@@ -175,23 +1027,36 @@ pub fn onboard_holder(
     state: &mut CompanyState,
     id: &str,
     name: &str,
-    cash: f64,
-) -> Result<(), String> {
+    cash: Amount,
+    now: DateTime<Utc>,
+) -> Result<(), EngineError> {
     if state.holders.contains_key(id) {
-        return Err("Holder already exists".into());
+        return Err(EngineError::AlreadyExists {
+            kind: EntityKind::Holder,
+            id: id.to_string(),
+        });
     }
     state.holders.insert(
         id.to_string(),
         Holder {
             id: id.to_string(),
             display_name: name.to_string(),
-            tokens: 0.0,
-            cash,
+            tokens: Amount::ZERO,
+            cash: Amount::ZERO,
             positions: vec![RoleTier::Employee],
+            employee_since: Some(now),
         },
     );
     state.employee_count += 1;
-    Ok(())
+    transfer(
+        state,
+        now,
+        Asset::Cash,
+        "onboarding deposit",
+        Account::External,
+        Account::Holder(id.to_string()),
+        cash,
+    )
 }
 
 pub fn create_listing(
@@ -199,12 +1064,18 @@ pub fn create_listing(
     listing_id: &str,
     seller_id: &str,
     role: RoleTier,
-    price: f64,
+    price: Amount,
     now: DateTime<Utc>,
-) -> Result<(), String> {
-    let holder = state.holders.get(seller_id).ok_or("Seller not found")?;
+) -> Result<(), EngineError> {
+    let holder = state
+        .holders
+        .get(seller_id)
+        .ok_or_else(|| EngineError::not_found(EntityKind::Holder, seller_id))?;
     if !holder.positions.contains(&role) {
-        return Err("Seller does not hold that role".into());
+        return Err(EngineError::RoleNotHeld {
+            holder_id: seller_id.to_string(),
+            role,
+        });
     }
     state.marketplace.push(PositionListing {
         id: listing_id.to_string(),
@@ -221,33 +1092,34 @@ pub fn buy_listing(
     state: &mut CompanyState,
     listing_id: &str,
     buyer_id: &str,
-) -> Result<(), String> {
-    let listing = state
+    now: DateTime<Utc>,
+) -> Result<(), EngineError> {
+    let idx = state
         .marketplace
-        .iter_mut()
-        .find(|l| l.id == listing_id && l.active)
-        .ok_or("Listing not found")?;
+        .iter()
+        .position(|l| l.id == listing_id && l.active)
+        .ok_or_else(|| EngineError::not_found(EntityKind::Listing, listing_id))?;
+    let listing = &state.marketplace[idx];
 
     let price = listing.price;
     let seller_id = listing.seller_id.clone();
     let role = listing.role;
+    check_promotion(state, buyer_id, role, now)?;
 
-    {
-        let buyer = state.holders.get_mut(buyer_id).ok_or("Buyer not found")?;
-        if buyer.cash < price {
-            return Err("Buyer has insufficient cash".into());
-        }
-        buyer.cash -= price;
+    transfer(
+        state,
+        now,
+        Asset::Cash,
+        &format!("purchase of listing {}", listing_id),
+        Account::Holder(buyer_id.to_string()),
+        Account::Holder(seller_id.clone()),
+        price,
+    )?;
+    if let Some(buyer) = state.holders.get_mut(buyer_id) {
         buyer.positions.push(role);
     }
-
-    {
-        let seller = state
-            .holders
-            .get_mut(&seller_id)
-            .ok_or("Seller not found")?;
-        seller.cash += price;
-        seller.positions.retain(|r| *r != role);
+    if let Some(seller) = state.holders.get_mut(&seller_id) {
+        drop_role(seller, role);
     }
 
     if let Some(pos) = state
@@ -256,342 +1128,3009 @@ pub fn buy_listing(
         .find(|p| p.tier == role && p.holder_id.as_deref() == Some(&seller_id))
     {
         pos.holder_id = Some(buyer_id.to_string());
-        pos.acquired_at = Some(Utc::now());
+        pos.acquired_at = Some(now);
         pos.price_paid = Some(price);
     }
 
-    listing.active = false;
+    state.marketplace[idx].active = false;
     Ok(())
 }
 
+/// Terms for a vote opening at `now` under the current governance policy.
+pub fn vote_terms(state: &CompanyState, now: DateTime<Utc>) -> VoteTerms {
+    let policy = &state.governance_policy;
+    VoteTerms {
+        opens_at: now,
+        closes_at: now + Duration::days(policy.voting_period_days),
+        quorum: policy.quorum,
+        threshold: Some(policy.vote_threshold),
+        allow_vote_changes: Some(policy.allow_vote_changes),
+    }
+}
+
+/// The share of cast voting power `record` needs to pass.
+fn vote_threshold(state: &CompanyState, record: &VoteRecord) -> f64 {
+    record
+        .terms
+        .and_then(|t| t.threshold)
+        .unwrap_or(state.governance_policy.vote_threshold)
+}
+
+/// Whether voters on `record` may change their ballot.
+fn vote_changes_allowed(state: &CompanyState, record: &VoteRecord) -> bool {
+    record
+        .terms
+        .and_then(|t| t.allow_vote_changes)
+        .unwrap_or(state.governance_policy.allow_vote_changes)
+}
+
 pub fn create_vote(
     state: &mut CompanyState,
     id: &str,
-    role: RoleTier,
-    holder: &str,
+    proposal: Proposal,
     reason: &str,
+    terms: Option<VoteTerms>,
     now: DateTime<Utc>,
 ) {
     state.votes.insert(
         id.to_string(),
         VoteRecord {
             id: id.to_string(),
-            target_role: role,
-            target_holder: holder.to_string(),
+            proposal,
             reason: reason.to_string(),
             created_at: now,
-            votes: vec![],
+            votes: BTreeMap::new(),
             resolved: false,
+            ballot_history: vec![],
+            terms,
+            outcome: None,
+            effect_error: None,
         },
     );
 }
 
-pub fn cast_vote(state: &mut CompanyState, vote_id: &str, vote: Vote) -> Result<bool, String> {
-    let record = state.votes.get_mut(vote_id).ok_or("Vote not found")?;
-    if record.resolved {
-        return Err("Vote already resolved".into());
+/// Opens a vote on `proposal`, checking up front that it could be applied
+/// as things stand.
+pub fn propose(
+    state: &mut CompanyState,
+    id: &str,
+    proposal: Proposal,
+    reason: &str,
+    terms: VoteTerms,
+    now: DateTime<Utc>,
+) -> Result<(), EngineError> {
+    if state.votes.contains_key(id) {
+        return Err(EngineError::AlreadyExists {
+            kind: EntityKind::Vote,
+            id: id.to_string(),
+        });
     }
-    submit_vote(record, vote);
-    let passed = vote_passed(record, state.governance_policy.vote_threshold);
-    Ok(passed)
+    if terms.closes_at <= terms.opens_at {
+        return Err(EngineError::invalid("vote must close after it opens"));
+    }
+    if !(0.0..=1.0).contains(&terms.quorum) {
+        return Err(EngineError::invalid("quorum must be in [0, 1]"));
+    }
+    validate_proposal(state, &proposal)?;
+    create_vote(state, id, proposal, reason, Some(terms), now);
+    Ok(())
 }
 
-pub fn resolve_vote_if_passed(state: &mut CompanyState, vote_id: &str) -> Result<bool, String> {
-    let (role, holder, should_resolve) = {
-        let record = state.votes.get_mut(vote_id).ok_or("Vote not found")?;
-        if record.resolved {
-            return Ok(true);
+fn validate_proposal(state: &CompanyState, proposal: &Proposal) -> Result<(), EngineError> {
+    let require_holder = |id: &str| {
+        if state.holders.contains_key(id) {
+            Ok(())
+        } else {
+            Err(EngineError::not_found(EntityKind::Holder, id))
         }
-        let passed = vote_passed(record, state.governance_policy.vote_threshold);
-        (record.target_role, record.target_holder.clone(), passed)
     };
-
-    if should_resolve {
-        remove_holder_from_role(state, role, &holder);
-        if let Some(record) = state.votes.get_mut(vote_id) {
-            record.resolved = true;
+    let require_positive = |amount: Amount| {
+        if amount.is_negative() || amount.is_zero() {
+            Err(EngineError::invalid("proposal amount must be > 0"))
+        } else {
+            Ok(())
         }
-        return Ok(true);
+    };
+    match proposal {
+        Proposal::RemoveFromRole { target_holder, .. } => require_holder(target_holder),
+        Proposal::AppointToRole { holder_id, .. } => require_holder(holder_id),
+        Proposal::SetGovernancePolicy { policy } => validate_governance_policy(policy),
+        Proposal::SetEmissionPolicy { policy } => validate_emission_policy(policy),
+        Proposal::PatchGovernancePolicy { changes } => {
+            if changes.is_empty() {
+                return Err(EngineError::invalid("no governance policy changes given"));
+            }
+            validate_governance_policy(&changes.apply(state.governance_policy.clone()))
+        }
+        Proposal::PatchEmissionPolicy { changes } => {
+            if changes.is_empty() {
+                return Err(EngineError::invalid("no emission policy changes given"));
+            }
+            validate_emission_policy(&changes.apply(state.emission_policy.clone()))
+        }
+        Proposal::SpendTreasury { to, amount, .. } => {
+            match to {
+                Account::Holder(id) => require_holder(id)?,
+                Account::External => {}
+                _ => {
+                    return Err(EngineError::invalid(
+                        "treasury spending must go to a holder or external",
+                    ));
+                }
+            }
+            require_positive(*amount)
+        }
+        Proposal::MintTokens { amount } | Proposal::BurnTokens { amount } => {
+            require_positive(*amount)
+        }
+        Proposal::AmendAllocations { allocations } => validate_allocations(state, allocations),
     }
-    Ok(false)
 }
 
-pub fn auto_trigger_value_drop_vote(
+/// Carries out a passed proposal.
+pub fn apply_proposal(
     state: &mut CompanyState,
-    current_price: f64,
+    proposal: &Proposal,
     now: DateTime<Utc>,
-    vote_id: &str,
-    role: RoleTier,
-    holder: &str,
-) -> bool {
-    if value_drop_triggered(state, current_price) {
-        if !state.votes.contains_key(vote_id) {
-            create_vote(state, vote_id, role, holder, "value_drop_trigger", now);
+) -> Result<(), EngineError> {
+    match proposal {
+        Proposal::RemoveFromRole {
+            target_role,
+            target_holder,
+        } => {
+            remove_holder_from_role(state, *target_role, target_holder);
+            forfeit_unvested(state, target_holder, *target_role, now)?;
+        }
+        Proposal::AppointToRole { role, holder_id } => {
+            assign_role_to_holder(state, *role, holder_id, now)?
+        }
+        Proposal::SetGovernancePolicy { policy } => set_governance_policy(state, policy.clone())?,
+        Proposal::SetEmissionPolicy { policy } => set_emission_policy(state, policy.clone())?,
+        Proposal::PatchGovernancePolicy { changes } => {
+            let policy = changes.apply(state.governance_policy.clone());
+            set_governance_policy(state, policy)?
+        }
+        Proposal::PatchEmissionPolicy { changes } => {
+            let policy = changes.apply(state.emission_policy.clone());
+            set_emission_policy(state, policy)?
+        }
+        Proposal::SpendTreasury { to, asset, amount } => transfer(
+            state,
+            now,
+            *asset,
+            "governance spend",
+            Account::Treasury,
+            to.clone(),
+            *amount,
+        )?,
+        Proposal::MintTokens { amount } => {
+            mint(
+                state,
+                Account::Treasury,
+                *amount,
+                false,
+                "governance mint",
+                now,
+            )?;
+        }
+        Proposal::BurnTokens { amount } => transfer(
+            state,
+            now,
+            Asset::Tokens,
+            "governance burn",
+            Account::Treasury,
+            Account::Burn,
+            *amount,
+        )?,
+        Proposal::AmendAllocations { allocations } => {
+            amend_allocations(state, allocations.clone())?
         }
-        return true;
     }
-    false
+    Ok(())
 }
 
-pub fn allocation_template(name: &str) -> Option<Vec<WorkAllocation>> {
-    match name {
-        "marketing" => Some(vec![
-            WorkAllocation {
-                holder_id: "lead".into(),
-                weight: 5.0,
-            },
-            WorkAllocation {
-                holder_id: "writer".into(),
-                weight: 3.0,
-            },
-            WorkAllocation {
-                holder_id: "designer".into(),
-                weight: 2.0,
-            },
-        ]),
-        "engineering" => Some(vec![
-            WorkAllocation {
-                holder_id: "lead".into(),
-                weight: 4.0,
-            },
-            WorkAllocation {
-                holder_id: "backend".into(),
-                weight: 3.0,
-            },
-            WorkAllocation {
-                holder_id: "frontend".into(),
-                weight: 2.0,
-            },
-            WorkAllocation {
-                holder_id: "qa".into(),
-                weight: 1.0,
-            },
-        ]),
-        "ops" => Some(vec![
-            WorkAllocation {
-                holder_id: "ops_lead".into(),
-                weight: 6.0,
-            },
-            WorkAllocation {
-                holder_id: "finance".into(),
-                weight: 2.0,
-            },
-            WorkAllocation {
-                holder_id: "admin".into(),
-                weight: 2.0,
-            },
-        ]),
-        _ => None,
+/// Casts `voter_id`'s ballot with the voting power the engine computes for
+/// them, replacing their earlier ballot if the policy allows changes;
+/// returns the weight recorded.
+pub fn cast_vote(
+    state: &mut CompanyState,
+    vote_id: &str,
+    voter_id: &str,
+    approve: bool,
+    now: DateTime<Utc>,
+) -> Result<f64, EngineError> {
+    if !state.holders.contains_key(voter_id) {
+        return Err(EngineError::not_found(EntityKind::Holder, voter_id));
+    }
+    let record = state
+        .votes
+        .get(vote_id)
+        .ok_or_else(|| EngineError::not_found(EntityKind::Vote, vote_id))?;
+    if !vote_changes_allowed(state, record) && record.votes.contains_key(voter_id) {
+        return Err(EngineError::BallotAlreadyCast {
+            vote_id: vote_id.to_string(),
+            voter_id: voter_id.to_string(),
+        });
+    }
+    if let Some(min_role) = required_role(&state.governance_policy, &record.proposal)
+        && !may_vote(state, voter_id, &record.proposal)
+    {
+        return Err(EngineError::NotEligibleToVote {
+            vote_id: vote_id.to_string(),
+            voter_id: voter_id.to_string(),
+            min_role,
+        });
+    }
+    let weight = voting_power(state, voter_id);
+    if weight <= 0.0 && !has_delegators(state, voter_id, record.proposal.kind()) {
+        return Err(EngineError::invalid(format!(
+            "{} has no voting power under the {} policy",
+            voter_id, state.governance_policy.voting_power
+        )));
     }
+    cast_weighted_vote(
+        state,
+        vote_id,
+        Vote {
+            voter_id: voter_id.to_string(),
+            weight,
+            approve,
+        },
+        now,
+    )?;
+    Ok(weight)
 }
 
-pub fn pm_create_task(
+/// Records a ballot with the weight it carries. Only for replaying logs
+/// written when callers supplied their own weight; use [`cast_vote`].
+/// Repeat ballots replace the voter's earlier one regardless of policy.
+pub fn cast_weighted_vote(
     state: &mut CompanyState,
-    id: &str,
-    title: &str,
-    summary: &str,
-    dod: Vec<String>,
-    deliverables: Vec<Deliverable>,
-    max_total_loc: usize,
-) {
-    let now = Utc::now();
-    state.tasks.insert(
-        id.to_string(),
-        Task {
-            id: id.to_string(),
-            title: title.to_string(),
-            summary: summary.to_string(),
-            definition_of_done: dod,
-            deliverables,
-            status: TaskStatus::Draft,
-            created_at: now,
-            updated_at: now,
-            assigned: vec![],
-            max_total_loc,
-            require_summary: true,
-            require_tests: true,
-        },
-    );
+    vote_id: &str,
+    vote: Vote,
+    now: DateTime<Utc>,
+) -> Result<bool, EngineError> {
+    let record = state
+        .votes
+        .get_mut(vote_id)
+        .ok_or_else(|| EngineError::not_found(EntityKind::Vote, vote_id))?;
+    if record.resolved {
+        return Err(EngineError::AlreadyResolved {
+            vote_id: vote_id.to_string(),
+        });
+    }
+    if let Some(terms) = &record.terms {
+        if now < terms.opens_at {
+            return Err(EngineError::invalid(format!(
+                "vote {} opens at {}",
+                vote_id,
+                terms.opens_at.to_rfc3339()
+            )));
+        }
+        if now >= terms.closes_at {
+            return Err(EngineError::VotingClosed {
+                vote_id: vote_id.to_string(),
+            });
+        }
+    }
+    submit_vote(record, vote, now);
+    let record = &state.votes[vote_id];
+    Ok(vote_passed(record, vote_threshold(state, record)))
 }
 
-pub fn pm_ready_task(state: &mut CompanyState, id: &str) -> Result<(), String> {
-    let task = state.tasks.get_mut(id).ok_or("Task not found")?;
-    if task.definition_of_done.is_empty() {
-        return Err("Definition of done required".into());
+/// Who `holder_id` has handed their vote on `kind` proposals to: their
+/// delegation for that kind, or else their general one.
+pub fn delegate_of<'a>(
+    state: &'a CompanyState,
+    holder_id: &str,
+    kind: ProposalKind,
+) -> Option<&'a str> {
+    let mut general = None;
+    for d in state
+        .delegations
+        .iter()
+        .filter(|d| d.delegator_id == holder_id)
+    {
+        match d.kind {
+            Some(k) if k == kind => return Some(&d.delegate_id),
+            None => general = Some(d.delegate_id.as_str()),
+            Some(_) => {}
+        }
     }
-    if task.deliverables.is_empty() {
-        return Err("At least one deliverable required".into());
+    general
+}
+
+/// The ballot cast on `holder_id`'s behalf: the first one along their
+/// delegation chain, or `None` if nobody on it has voted.
+fn representative<'a>(
+    state: &CompanyState,
+    holder_id: &str,
+    kind: ProposalKind,
+    votes: &'a BTreeMap<String, Vote>,
+) -> Option<&'a Vote> {
+    let mut seen = vec![holder_id];
+    let mut current = holder_id;
+    while let Some(next) = delegate_of(state, current, kind) {
+        if seen.contains(&next) {
+            return None;
+        }
+        if let Some(vote) = votes.get(next) {
+            return Some(vote);
+        }
+        seen.push(next);
+        current = next;
     }
-    task.status = TaskStatus::Ready;
-    task.updated_at = Utc::now();
-    Ok(())
+    None
 }
 
-pub fn pm_assign_task(
-    state: &mut CompanyState,
-    id: &str,
-    assignee_id: &str,
-    role: &str,
-) -> Result<(), String> {
-    let task = state.tasks.get_mut(id).ok_or("Task not found")?;
-    task.assigned.push(TaskAssignment {
-        assignee_id: assignee_id.to_string(),
-        role: role.to_string(),
-        status: TaskStatus::InProgress,
-    });
-    task.status = TaskStatus::InProgress;
-    task.updated_at = Utc::now();
-    Ok(())
+/// Voting power `voter_id`'s ballot on `record` carries for the holders who
+/// delegated to them and have not voted themselves.
+pub fn delegated_power(state: &CompanyState, record: &VoteRecord, voter_id: &str) -> f64 {
+    let kind = record.proposal.kind();
+    state
+        .holders
+        .keys()
+        .filter(|id| !record.votes.contains_key(*id) && may_vote(state, id, &record.proposal))
+        .filter(|id| {
+            representative(state, id, kind, &record.votes).is_some_and(|v| v.voter_id == voter_id)
+        })
+        .map(|id| voting_power(state, id))
+        .sum()
 }
 
-pub fn pm_submit_for_review(state: &mut CompanyState, id: &str) -> Result<(), String> {
-    let task = state.tasks.get_mut(id).ok_or("Task not found")?;
-    task.status = TaskStatus::Review;
-    task.updated_at = Utc::now();
-    Ok(())
+/// Whether anyone's `kind` votes reach `holder_id` through delegation.
+fn has_delegators(state: &CompanyState, holder_id: &str, kind: ProposalKind) -> bool {
+    state.holders.keys().any(|id| {
+        let mut seen = vec![id.as_str()];
+        let mut current = id.as_str();
+        while let Some(next) = delegate_of(state, current, kind) {
+            if next == holder_id {
+                return true;
+            }
+            if seen.contains(&next) {
+                return false;
+            }
+            seen.push(next);
+            current = next;
+        }
+        false
+    })
 }
 
-pub fn pm_finalize_task(
+/// Delegates `delegator_id`'s voting power to `delegate_id`, for proposals
+/// of `kind` or, without one, for every kind not delegated separately.
+/// Replaces the delegator's existing delegation for the same scope.
+pub fn delegate_votes(
     state: &mut CompanyState,
-    id: &str,
-    loc_changed: usize,
-    tests_run: bool,
-) -> Result<(), String> {
-    let task = state.tasks.get_mut(id).ok_or("Task not found")?;
-    if loc_changed > task.max_total_loc {
-        return Err("Code bloat guardrail: LOC limit exceeded".into());
+    delegator_id: &str,
+    delegate_id: &str,
+    kind: Option<ProposalKind>,
+    now: DateTime<Utc>,
+) -> Result<(), EngineError> {
+    for id in [delegator_id, delegate_id] {
+        if !state.holders.contains_key(id) {
+            return Err(EngineError::not_found(EntityKind::Holder, id));
+        }
     }
-    if task.require_tests && !tests_run {
-        return Err("Tests required before finalization".into());
+    if delegator_id == delegate_id {
+        return Err(EngineError::invalid(
+            "a holder cannot delegate to themselves",
+        ));
+    }
+
+    let previous = state
+        .delegations
+        .iter()
+        .position(|d| d.delegator_id == delegator_id && d.kind == kind)
+        .map(|idx| state.delegations.remove(idx));
+    state.delegations.push(Delegation {
+        delegator_id: delegator_id.to_string(),
+        delegate_id: delegate_id.to_string(),
+        kind,
+        since: now,
+    });
+
+    let kinds = match kind {
+        Some(kind) => vec![kind],
+        None => ProposalKind::ALL.to_vec(),
+    };
+    let cycle = kinds
+        .into_iter()
+        .find_map(|kind| delegation_cycle(state, delegator_id, kind));
+    if let Some(chain) = cycle {
+        state.delegations.pop();
+        state.delegations.extend(previous);
+        return Err(EngineError::DelegationCycle { chain });
     }
-    task.status = TaskStatus::Done;
-    task.updated_at = Utc::now();
     Ok(())
 }
 
-pub fn ensure_holder(state: &mut CompanyState, id: &str, name: &str) {
-    state.holders.entry(id.to_string()).or_insert(Holder {
-        id: id.to_string(),
-        display_name: name.to_string(),
-        tokens: 0.0,
-        cash: 0.0,
-        positions: vec![],
-    });
+/// The chain that leads from `holder_id` back to itself, if their `kind`
+/// delegations form a loop.
+fn delegation_cycle(
+    state: &CompanyState,
+    holder_id: &str,
+    kind: ProposalKind,
+) -> Option<Vec<String>> {
+    let mut chain = vec![holder_id.to_string()];
+    let mut current = holder_id;
+    while let Some(next) = delegate_of(state, current, kind) {
+        chain.push(next.to_string());
+        if next == holder_id {
+            return Some(chain);
+        }
+        if chain[..chain.len() - 1].iter().any(|id| id == next) {
+            return None;
+        }
+        current = next;
+    }
+    None
 }
 
-pub fn assign_role_to_holder(
+pub fn revoke_delegation(
     state: &mut CompanyState,
-    role: RoleTier,
-    holder_id: &str,
-) -> Result<(), String> {
-    let holder = state.holders.get_mut(holder_id).ok_or("Holder not found")?;
-    if !holder.positions.contains(&role) {
-        holder.positions.push(role);
+    delegator_id: &str,
+    kind: Option<ProposalKind>,
+) -> Result<(), EngineError> {
+    let idx = state
+        .delegations
+        .iter()
+        .position(|d| d.delegator_id == delegator_id && d.kind == kind)
+        .ok_or_else(|| match kind {
+            Some(kind) => EngineError::invalid(format!(
+                "{} has no delegation for {} proposals",
+                delegator_id, kind
+            )),
+            None => EngineError::invalid(format!("{} has no general delegation", delegator_id)),
+        })?;
+    let removed = state.delegations.remove(idx);
+
+    // Dropping a delegation for one kind lets the general delegation take
+    // over for it, which can close a loop.
+    let kinds = match kind {
+        Some(kind) => vec![kind],
+        None => ProposalKind::ALL.to_vec(),
+    };
+    let cycle = kinds
+        .into_iter()
+        .find_map(|kind| delegation_cycle(state, delegator_id, kind));
+    if let Some(chain) = cycle {
+        state.delegations.insert(idx, removed);
+        return Err(EngineError::DelegationCycle { chain });
     }
+    Ok(())
+}
 
-    if role == RoleTier::Employee {
-        return Ok(());
+/// Ballot weight of `holder_id` under the governance voting power policy;
+/// zero for unknown holders.
+pub fn voting_power(state: &CompanyState, holder_id: &str) -> f64 {
+    let Some(holder) = state.holders.get(holder_id) else {
+        return 0.0;
+    };
+    match &state.governance_policy.voting_power {
+        VotingPowerPolicy::TokenWeighted => holder.tokens.to_f64(),
+        VotingPowerPolicy::StakeWeighted => {
+            let staked = state
+                .stakes
+                .get(holder_id)
+                .map(|s| s.staked.to_f64())
+                .unwrap_or_default();
+            staked * role_multiplier(state, holder_id)
+        }
+        VotingPowerPolicy::OneHolderOneVote => 1.0,
+        VotingPowerPolicy::RoleTierWeighted { weights } => holder
+            .positions
+            .iter()
+            .filter_map(|role| weights.get(role).copied())
+            .fold(0.0, f64::max),
+        VotingPowerPolicy::Quadratic => holder.tokens.to_f64().sqrt(),
     }
+}
 
-    if let Some(pos) = state
-        .positions
-        .iter_mut()
-        .find(|p| p.tier == role && p.holder_id.is_none())
+pub fn set_governance_policy(
+    state: &mut CompanyState,
+    policy: GovernancePolicy,
+) -> Result<(), EngineError> {
+    validate_governance_policy(&policy)?;
+    state.governance_policy = policy;
+    Ok(())
+}
+
+fn validate_governance_policy(policy: &GovernancePolicy) -> Result<(), EngineError> {
+    if !(policy.vote_threshold > 0.0 && policy.vote_threshold <= 1.0) {
+        return Err(EngineError::invalid("vote threshold must be in (0, 1]"));
+    }
+    if !(0.0..1.0).contains(&policy.value_drop_trigger) {
+        return Err(EngineError::invalid("value drop trigger must be in [0, 1)"));
+    }
+    if policy.value_window_days <= 0 {
+        return Err(EngineError::invalid(
+            "value window must be at least one day",
+        ));
+    }
+    if !(0.0..=1.0).contains(&policy.quorum) {
+        return Err(EngineError::invalid("quorum must be in [0, 1]"));
+    }
+    if policy.voting_period_days <= 0 {
+        return Err(EngineError::invalid(
+            "voting period must be at least one day",
+        ));
+    }
+    if policy.value_drop_cooldown_days < 0 {
+        return Err(EngineError::invalid("value drop cooldown must be >= 0"));
+    }
+    if let PriceAverage::Exponential { half_life_days } = policy.price_average
+        && !(half_life_days > 0.0 && half_life_days.is_finite())
     {
-        pos.holder_id = Some(holder_id.to_string());
-        pos.acquired_at = Some(Utc::now());
-        pos.price_paid = Some(0.0);
-        return Ok(());
+        return Err(EngineError::invalid("price half-life must be > 0"));
     }
-
-    // For fixed/special roles, create a position if missing
-    let special = matches!(
-        role,
-        RoleTier::CEO | RoleTier::President | RoleTier::CoPresident | RoleTier::BoardSeat
-    );
-    if special {
-        state.positions.push(RolePosition {
-            tier: role,
-            holder_id: Some(holder_id.to_string()),
-            acquired_at: Some(Utc::now()),
-            price_paid: Some(0.0),
-        });
-        return Ok(());
+    for (idx, rule) in policy.eligibility.iter().enumerate() {
+        let about_roles = matches!(
+            rule.kind,
+            ProposalKind::RemoveFromRole | ProposalKind::AppointToRole
+        );
+        if rule.target_role.is_some() && !about_roles {
+            return Err(EngineError::invalid(format!(
+                "{} proposals are not about a role",
+                rule.kind
+            )));
+        }
+        let scope = (rule.kind, rule.target_role);
+        if policy.eligibility[..idx]
+            .iter()
+            .any(|r| (r.kind, r.target_role) == scope)
+        {
+            return Err(EngineError::invalid(format!(
+                "more than one eligibility rule for {}",
+                rule.kind
+            )));
+        }
     }
+    validate_voting_power_policy(&policy.voting_power)
+}
 
-    Err("No available position for role".into())
+/// The lowest role that may vote on `proposal`, if any rule restricts it.
+pub fn required_role(policy: &GovernancePolicy, proposal: &Proposal) -> Option<RoleTier> {
+    let kind = proposal.kind();
+    let role = proposal.role();
+    let rules = || policy.eligibility.iter().filter(move |r| r.kind == kind);
+    rules()
+        .find(|r| r.target_role.is_some() && r.target_role == role)
+        .or_else(|| rules().find(|r| r.target_role.is_none()))
+        .map(|r| r.min_role)
 }
 
-pub fn seed_roles(state: &mut CompanyState, ceo_id: &str, ceo_name: &str) -> Result<(), String> {
-    ensure_holder(state, ceo_id, ceo_name);
-    ensure_positions(state);
+/// Whether `holder_id` holds a role senior enough to vote on `proposal`.
+pub fn may_vote(state: &CompanyState, holder_id: &str, proposal: &Proposal) -> bool {
+    let Some(holder) = state.holders.get(holder_id) else {
+        return false;
+    };
+    match required_role(&state.governance_policy, proposal) {
+        Some(min_role) => holder.positions.iter().any(|role| *role >= min_role),
+        None => true,
+    }
+}
+
+pub fn set_voting_power_policy(
+    state: &mut CompanyState,
+    policy: VotingPowerPolicy,
+) -> Result<(), EngineError> {
+    validate_voting_power_policy(&policy)?;
+    state.governance_policy.voting_power = policy;
+    Ok(())
+}
 
-    assign_role_to_holder(state, RoleTier::CEO, ceo_id)?;
-    assign_role_to_holder(state, RoleTier::BoardSeat, ceo_id)?;
+fn validate_voting_power_policy(policy: &VotingPowerPolicy) -> Result<(), EngineError> {
+    if let VotingPowerPolicy::RoleTierWeighted { weights } = policy
+        && weights.values().any(|w| w.is_nan() || *w < 0.0)
+    {
+        return Err(EngineError::invalid("role weights must be >= 0"));
+    }
+    Ok(())
+}
 
-    let defaults = vec![
-        ("president", "President", RoleTier::President),
-        ("co_president", "Co-President", RoleTier::CoPresident),
-        ("csuite", "C-Suite", RoleTier::CSuite),
-        ("director", "Director", RoleTier::Director),
-        ("senior_manager", "Senior Manager", RoleTier::SeniorManager),
-        ("manager", "Manager", RoleTier::Manager),
-        ("employee", "Employee", RoleTier::Employee),
-    ];
+pub fn set_emission_policy(
+    state: &mut CompanyState,
+    policy: EmissionPolicy,
+) -> Result<(), EngineError> {
+    validate_emission_policy(&policy)?;
+    state.emission_policy = policy;
+    Ok(())
+}
 
-    for (id, name, role) in defaults {
-        ensure_holder(state, id, name);
-        let _ = assign_role_to_holder(state, role, id);
+fn validate_emission_policy(policy: &EmissionPolicy) -> Result<(), EngineError> {
+    if !(0.0..=1.0).contains(&policy.weekly_payout_percent) {
+        return Err(EngineError::invalid(
+            "weekly payout share must be in [0, 1]",
+        ));
+    }
+    if policy.halving_interval_days <= 0 {
+        return Err(EngineError::invalid(
+            "halving interval must be at least one day",
+        ));
     }
     Ok(())
 }
 
-pub fn set_tokenomics(
+/// Replaces the tokenomics allocation table; the percentages may not
+/// exceed 100 in total.
+pub fn amend_allocations(
     state: &mut CompanyState,
-    total_supply_cap: f64,
-    minted_supply: f64,
     allocations: Vec<TokenAllocation>,
-) {
-    state.tokenomics = Some(Tokenomics {
-        total_supply_cap,
-        minted_supply,
-        allocations,
-    });
+) -> Result<(), EngineError> {
+    validate_allocations(state, &allocations)?;
+    if let Some(t) = state.tokenomics.as_mut() {
+        t.allocations = allocations;
+    }
+    Ok(())
 }
 
-pub fn tokenomics_report(state: &CompanyState) -> Option<serde_json::Value> {
-    let t = state.tokenomics.as_ref()?;
-    let allocated_total: f64 = t.allocations.iter().map(|a| a.percent).sum();
-    Some(serde_json::json!({
-        "total_supply_cap": t.total_supply_cap,
-        "minted_supply": t.minted_supply,
-        "remaining_supply": (t.total_supply_cap - t.minted_supply).max(0.0),
-        "allocations": t.allocations,
-        "allocations_total_percent": allocated_total
-    }))
+fn validate_allocations(
+    state: &CompanyState,
+    allocations: &[TokenAllocation],
+) -> Result<(), EngineError> {
+    if state.tokenomics.is_none() {
+        return Err(EngineError::invalid("tokenomics have not been set"));
+    }
+    if allocations
+        .iter()
+        .any(|a| a.percent.is_nan() || a.percent < 0.0)
+    {
+        return Err(EngineError::invalid("allocation percentages must be >= 0"));
+    }
+    let total: f64 = allocations.iter().map(|a| a.percent).sum();
+    if total > 100.0 {
+        return Err(EngineError::invalid(format!(
+            "allocations total {}%, over 100%",
+            total
+        )));
+    }
+    Ok(())
 }
 
-pub fn grant_tokens(state: &mut CompanyState, holder_id: &str, amount: f64) -> Result<(), String> {
-    let holder = state.holders.get_mut(holder_id).ok_or("Holder not found")?;
-    holder.tokens += amount;
-    Ok(())
+pub fn role_multiplier(state: &CompanyState, holder_id: &str) -> f64 {
+    let multipliers = &state.staking_policy.role_multipliers;
+    state
+        .holders
+        .get(holder_id)
+        .into_iter()
+        .flat_map(|h| &h.positions)
+        .filter_map(|role| multipliers.get(role).copied())
+        .fold(None, |best: Option<f64>, m| {
+            Some(best.map_or(m, |b| b.max(m)))
+        })
+        .unwrap_or(1.0)
 }
 
-pub fn auto_onboard(
+pub fn set_promotion_policy(
     state: &mut CompanyState,
-    id: &str,
-    name: &str,
-    cash: f64,
-) -> Result<bool, String> {
-    onboard_holder(state, id, name, cash)?;
-    state.onboarding_count += 1;
+    policy: PromotionPolicy,
+) -> Result<(), EngineError> {
+    for (role, rung) in &policy.rungs {
+        if rung.requires.is_some_and(|required| required >= *role) {
+            return Err(EngineError::invalid(format!(
+                "{} can only require a lower role",
+                role
+            )));
+        }
+        if rung.min_days_in_role < 0 {
+            return Err(EngineError::invalid("days in role must be >= 0"));
+        }
+        if rung.min_days_in_role > 0 && rung.requires.is_none() {
+            return Err(EngineError::invalid(format!(
+                "{} sets days in role without a required role",
+                role
+            )));
+        }
+        if rung.min_stake.is_negative() {
+            return Err(EngineError::invalid("minimum stake must be >= 0"));
+        }
+    }
+    state.promotion_policy = policy;
+    Ok(())
+}
 
-    let mut rewarded = false;
-    if state.onboarding_count <= state.onboarding_policy.early_joiner_limit
-        && state.onboarding_policy.early_joiner_reward > 0.0
-    {
-        grant_tokens(state, id, state.onboarding_policy.early_joiner_reward)?;
-        rewarded = true;
+/// Fills in when each employee started, for states that predate tracking
+/// it, from the first journal entry on their account. Employees without
+/// one are left unknown.
+pub fn backfill_employee_since(state: &mut CompanyState) {
+    let mut first_entry: HashMap<&str, DateTime<Utc>> = HashMap::new();
+    for entry in &state.journal {
+        for p in &entry.postings {
+            if let Account::Holder(id) = &p.account {
+                first_entry.entry(id.as_str()).or_insert(entry.at);
+            }
+        }
+    }
+    let backfill: Vec<(String, DateTime<Utc>)> = state
+        .holders
+        .values()
+        .filter(|h| h.employee_since.is_none() && h.positions.contains(&RoleTier::Employee))
+        .filter_map(|h| first_entry.get(h.id.as_str()).map(|at| (h.id.clone(), *at)))
+        .collect();
+    for (id, at) in backfill {
+        if let Some(holder) = state.holders.get_mut(&id) {
+            holder.employee_since = Some(at);
+        }
+    }
+}
+
+/// Finished tasks `holder_id` was assigned to.
+pub fn completed_tasks(state: &CompanyState, holder_id: &str) -> usize {
+    state
+        .tasks
+        .values()
+        .filter(|t| matches!(t.status, TaskStatus::Done))
+        .filter(|t| t.assigned.iter().any(|a| a.assignee_id == holder_id))
+        .count()
+}
+
+/// When `holder` took `role`, or their earliest seat if they hold several;
+/// `Some(None)` if they hold it but it is not known since when.
+fn held_since(
+    state: &CompanyState,
+    holder: &Holder,
+    role: RoleTier,
+) -> Option<Option<DateTime<Utc>>> {
+    if !holder.positions.contains(&role) {
+        return None;
+    }
+    if role == RoleTier::Employee {
+        return Some(holder.employee_since);
+    }
+    Some(
+        state
+            .positions
+            .iter()
+            .filter(|p| p.tier == role && p.holder_id.as_deref() == Some(&holder.id))
+            .filter_map(|p| p.acquired_at)
+            .min(),
+    )
+}
+
+/// Requirements for `role` that `holder_id` does not meet at `now`; empty
+/// if they may take it.
+pub fn promotion_gaps(
+    state: &CompanyState,
+    holder_id: &str,
+    role: RoleTier,
+    now: DateTime<Utc>,
+) -> Result<Vec<PromotionGap>, EngineError> {
+    let holder = state
+        .holders
+        .get(holder_id)
+        .ok_or_else(|| EngineError::not_found(EntityKind::Holder, holder_id))?;
+    let Some(rung) = state.promotion_policy.rungs.get(&role) else {
+        return Ok(vec![]);
+    };
+    let mut gaps = vec![];
+    if let Some(required) = rung.requires {
+        match held_since(state, holder, required) {
+            None => gaps.push(PromotionGap::RoleRequired { role: required }),
+            // A start date lost to an old state is given the benefit of the
+            // doubt rather than barring the holder for good.
+            Some(None) => {}
+            Some(Some(since)) => {
+                let days_held = (now - since).num_days();
+                if days_held < rung.min_days_in_role {
+                    gaps.push(PromotionGap::TenureTooShort {
+                        role: required,
+                        days_held,
+                        days_required: rung.min_days_in_role,
+                    });
+                }
+            }
+        }
+    }
+    let completed = completed_tasks(state, holder_id);
+    if completed < rung.min_completed_tasks {
+        gaps.push(PromotionGap::TooFewCompletedTasks {
+            completed,
+            required: rung.min_completed_tasks,
+        });
+    }
+    let staked = state
+        .stakes
+        .get(holder_id)
+        .map(|s| s.staked)
+        .unwrap_or_default();
+    if staked < rung.min_stake {
+        gaps.push(PromotionGap::StakeTooLow {
+            staked,
+            required: rung.min_stake,
+        });
+    }
+    Ok(gaps)
+}
+
+fn check_promotion(
+    state: &CompanyState,
+    holder_id: &str,
+    role: RoleTier,
+    now: DateTime<Utc>,
+) -> Result<(), EngineError> {
+    let missing = promotion_gaps(state, holder_id, role, now)?;
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(EngineError::PromotionBlocked {
+        holder_id: holder_id.to_string(),
+        role,
+        missing,
+    })
+}
+
+pub fn set_staking_policy(
+    state: &mut CompanyState,
+    policy: StakingPolicy,
+) -> Result<(), EngineError> {
+    if policy.unbonding_days < 0 {
+        return Err(EngineError::invalid("unbonding period must be >= 0"));
+    }
+    if policy
+        .role_multipliers
+        .values()
+        .any(|m| m.is_nan() || *m < 0.0)
+    {
+        return Err(EngineError::invalid("role multipliers must be >= 0"));
+    }
+    state.staking_policy = policy;
+    Ok(())
+}
+
+/// Moves tokens from a holder's balance into the staking pool.
+pub fn stake_tokens(
+    state: &mut CompanyState,
+    holder_id: &str,
+    amount: Amount,
+    now: DateTime<Utc>,
+) -> Result<(), EngineError> {
+    if amount <= Amount::ZERO {
+        return Err(EngineError::invalid("stake amount must be > 0"));
+    }
+    transfer(
+        state,
+        now,
+        Asset::Tokens,
+        "stake",
+        Account::Holder(holder_id.to_string()),
+        Account::Staking,
+        amount,
+    )?;
+    let stake = state
+        .stakes
+        .entry(holder_id.to_string())
+        .or_insert_with(|| Stake {
+            holder_id: holder_id.to_string(),
+            staked: Amount::ZERO,
+            unbonding: vec![],
+        });
+    stake.staked = stake
+        .staked
+        .checked_add(amount)
+        .ok_or(EngineError::Overflow)?;
+    Ok(())
+}
+
+/// Starts unbonding staked tokens: they stop counting for votes at once and
+/// can be claimed back after the unbonding period.
+pub fn unstake_tokens(
+    state: &mut CompanyState,
+    holder_id: &str,
+    amount: Amount,
+    now: DateTime<Utc>,
+) -> Result<DateTime<Utc>, EngineError> {
+    if amount <= Amount::ZERO {
+        return Err(EngineError::invalid("unstake amount must be > 0"));
+    }
+    let release_at = now + Duration::days(state.staking_policy.unbonding_days);
+    let staked = state
+        .stakes
+        .get(holder_id)
+        .map(|s| s.staked)
+        .unwrap_or_default();
+    if staked < amount {
+        return Err(EngineError::InsufficientFunds {
+            account: format!("staking:{}", holder_id),
+            asset: Asset::Tokens,
+            needed: amount,
+            available: staked,
+        });
+    }
+    let stake = state
+        .stakes
+        .get_mut(holder_id)
+        .ok_or_else(|| EngineError::not_found(EntityKind::Holder, holder_id))?;
+    stake.staked = stake.staked - amount;
+    stake.unbonding.push(Unbonding { amount, release_at });
+    Ok(release_at)
+}
+
+/// Returns unbonded tokens whose period is over to the holder.
+pub fn claim_unbonded(
+    state: &mut CompanyState,
+    holder_id: &str,
+    now: DateTime<Utc>,
+) -> Result<Amount, EngineError> {
+    let stake = state
+        .stakes
+        .get_mut(holder_id)
+        .ok_or_else(|| EngineError::not_found(EntityKind::Holder, holder_id))?;
+    let (ready, pending): (Vec<Unbonding>, Vec<Unbonding>) =
+        stake.unbonding.drain(..).partition(|u| u.release_at <= now);
+    stake.unbonding = pending;
+    let claimed: Amount = ready.iter().map(|u| u.amount).sum();
+    transfer(
+        state,
+        now,
+        Asset::Tokens,
+        "unbonded stake",
+        Account::Staking,
+        Account::Holder(holder_id.to_string()),
+        claimed,
+    )?;
+    Ok(claimed)
+}
+
+/// Voting power behind a vote: what has been cast, and what every holder
+/// could cast. A voter's ballot counts at the weight it was cast with;
+/// holders who have not voted count at their current voting power, cast
+/// with the first voter along their delegation chain if there is one.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct VoteTally {
+    pub eligible: f64,
+    pub cast: f64,
+    pub approve: f64,
+    /// The part of `cast` and `approve` that delegates carry for others.
+    pub delegated: f64,
+    pub delegated_approve: f64,
+}
+
+pub fn tally_vote(state: &CompanyState, record: &VoteRecord) -> VoteTally {
+    let kind = record.proposal.kind();
+    let mut tally = VoteTally {
+        eligible: 0.0,
+        cast: 0.0,
+        approve: 0.0,
+        delegated: 0.0,
+        delegated_approve: 0.0,
+    };
+    for vote in record.votes.values() {
+        tally.cast += vote.weight;
+        if vote.approve {
+            tally.approve += vote.weight;
+        }
+    }
+    tally.eligible = tally.cast;
+    for id in state.holders.keys() {
+        if record.votes.contains_key(id) || !may_vote(state, id, &record.proposal) {
+            continue;
+        }
+        let power = voting_power(state, id);
+        tally.eligible += power;
+        let Some(ballot) = representative(state, id, kind, &record.votes) else {
+            continue;
+        };
+        tally.cast += power;
+        tally.delegated += power;
+        if ballot.approve {
+            tally.approve += power;
+            tally.delegated_approve += power;
+        }
+    }
+    tally
+}
+
+/// The outcome of an open vote as of `now`, or `None` while it is still
+/// undecided. After the close it passes only with quorum and the threshold
+/// met; before it, only once no remaining ballots could change the result.
+pub fn vote_outcome(
+    state: &CompanyState,
+    record: &VoteRecord,
+    now: DateTime<Utc>,
+) -> Option<VoteOutcome> {
+    let threshold = vote_threshold(state, record);
+    let Some(terms) = &record.terms else {
+        return vote_passed(record, threshold).then_some(VoteOutcome::Passed);
+    };
+    if now < terms.opens_at {
+        return None;
+    }
+    let tally = tally_vote(state, record);
+    if now >= terms.closes_at {
+        let quorate = tally.cast > 0.0 && tally.cast >= terms.quorum * tally.eligible;
+        let passed = quorate && tally.approve / tally.cast >= threshold;
+        return Some(if passed {
+            VoteOutcome::Passed
+        } else {
+            VoteOutcome::Failed
+        });
+    }
+    if tally.eligible <= 0.0 {
+        return None;
+    }
+    // Direct ballots are fixed unless voters may still change them; anyone
+    // else, delegators included, may yet vote either way.
+    let direct_cast = tally.cast - tally.delegated;
+    let direct_approve = tally.approve - tally.delegated_approve;
+    let undecided = tally.eligible - direct_cast;
+    let (least_approve, most_approve) = if vote_changes_allowed(state, record) {
+        (0.0, tally.eligible)
+    } else {
+        (direct_approve, direct_approve + undecided)
+    };
+    let quorate = direct_cast >= terms.quorum * tally.eligible;
+    if quorate && least_approve / tally.eligible >= threshold {
+        Some(VoteOutcome::Passed)
+    } else if most_approve / tally.eligible < threshold {
+        Some(VoteOutcome::Failed)
+    } else {
+        None
+    }
+}
+
+/// Decides the vote if its outcome is settled as of `now`, applying its
+/// proposal if it passed; returns whether it has passed. A passed proposal
+/// that can no longer be applied, say for want of treasury funds, still
+/// closes the vote, with the reason kept on the record.
+pub fn resolve_vote_if_passed(
+    state: &mut CompanyState,
+    vote_id: &str,
+    now: DateTime<Utc>,
+) -> Result<bool, EngineError> {
+    let record = state
+        .votes
+        .get(vote_id)
+        .ok_or_else(|| EngineError::not_found(EntityKind::Vote, vote_id))?;
+    if record.resolved {
+        return Ok(record.passed());
+    }
+    let Some(outcome) = vote_outcome(state, record, now) else {
+        return Ok(false);
+    };
+    let proposal = record.proposal.clone();
+
+    let effect_error = match outcome {
+        VoteOutcome::Passed => apply_proposal(state, &proposal, now)
+            .err()
+            .map(|err| err.to_string()),
+        VoteOutcome::Failed => None,
+    };
+    if let Some(record) = state.votes.get_mut(vote_id) {
+        record.resolved = true;
+        record.outcome = Some(outcome);
+        record.effect_error = effect_error;
+    }
+    Ok(outcome == VoteOutcome::Passed)
+}
+
+/// Decides every open vote whose closing time has passed; returns the
+/// ids and outcomes of the votes it closed.
+pub fn close_expired_votes(
+    state: &mut CompanyState,
+    now: DateTime<Utc>,
+) -> Result<Vec<(String, VoteOutcome)>, EngineError> {
+    let mut expired: Vec<String> = state
+        .votes
+        .values()
+        .filter(|v| !v.resolved && v.terms.is_some_and(|t| t.closes_at <= now))
+        .map(|v| v.id.clone())
+        .collect();
+    expired.sort();
+    let mut closed = vec![];
+    for vote_id in expired {
+        resolve_vote_if_passed(state, &vote_id, now)?;
+        if let Some(outcome) = state.votes[&vote_id].outcome {
+            closed.push((vote_id, outcome));
+        }
+    }
+    Ok(closed)
+}
+
+/// Opens a removal vote against every holder of an accountable role if
+/// `current_price`, observed at `observed_at`, has dropped far enough below
+/// the rolling average at that moment. Votes open and the cooldown runs at
+/// `now`, when the drop is recorded, so a late tick cannot open a vote that
+/// is already closed. Further drops within the cooldown of the last one are
+/// ignored, as are holders who already face an open removal vote for the
+/// role. Returns the ids of the votes opened.
+pub fn monitor_value_drop(
+    state: &mut CompanyState,
+    current_price: f64,
+    observed_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Vec<String> {
+    let Some(average) = rolling_average_price(state, observed_at) else {
+        return vec![];
+    };
+    if !value_drop_triggered(state, current_price, observed_at) {
+        return vec![];
+    }
+    let policy = &state.governance_policy;
+    let cooldown = Duration::days(policy.value_drop_cooldown_days);
+    if state
+        .value_drops
+        .last()
+        .is_some_and(|last| now < last.at + cooldown)
+    {
+        return vec![];
+    }
+
+    let mut targets: Vec<(RoleTier, String)> = state
+        .positions
+        .iter()
+        .filter(|p| policy.accountable_roles.contains(&p.tier))
+        .filter_map(|p| p.holder_id.clone().map(|holder| (p.tier, holder)))
+        .collect();
+    targets.sort();
+    targets.dedup();
+    let terms = vote_terms(state, now);
+    let mut vote_ids = vec![];
+    for (role, holder) in targets {
+        let proposal = Proposal::RemoveFromRole {
+            target_role: role,
+            target_holder: holder.clone(),
+        };
+        let already_open = state
+            .votes
+            .values()
+            .any(|v| !v.resolved && v.proposal == proposal);
+        let vote_id = format!("value-drop-{}-{}-{}", role, holder, now.timestamp());
+        if already_open || state.votes.contains_key(&vote_id) {
+            continue;
+        }
+        create_vote(
+            state,
+            &vote_id,
+            proposal,
+            "value_drop_trigger",
+            Some(terms),
+            now,
+        );
+        vote_ids.push(vote_id);
+    }
+    state.value_drops.push(ValueDrop {
+        at: now,
+        price: current_price,
+        average,
+        vote_ids: vote_ids.clone(),
+    });
+    vote_ids
+}
+
+/// Opens a removal vote against a hand-picked holder on a value drop. Only
+/// for replaying logs written before drops were monitored; see
+/// [`monitor_value_drop`].
+pub fn auto_trigger_value_drop_vote(
+    state: &mut CompanyState,
+    current_price: f64,
+    now: DateTime<Utc>,
+    vote_id: &str,
+    role: RoleTier,
+    holder: &str,
+    terms: Option<VoteTerms>,
+) -> bool {
+    if value_drop_triggered(state, current_price, now) {
+        if !state.votes.contains_key(vote_id) {
+            let proposal = Proposal::RemoveFromRole {
+                target_role: role,
+                target_holder: holder.to_string(),
+            };
+            create_vote(state, vote_id, proposal, "value_drop_trigger", terms, now);
+        }
+        return true;
+    }
+    false
+}
+
+pub fn allocation_template(name: &str) -> Option<Vec<WorkAllocation>> {
+    match name {
+        "marketing" => Some(vec![
+            WorkAllocation {
+                holder_id: "lead".into(),
+                weight: 5.0,
+            },
+            WorkAllocation {
+                holder_id: "writer".into(),
+                weight: 3.0,
+            },
+            WorkAllocation {
+                holder_id: "designer".into(),
+                weight: 2.0,
+            },
+        ]),
+        "engineering" => Some(vec![
+            WorkAllocation {
+                holder_id: "lead".into(),
+                weight: 4.0,
+            },
+            WorkAllocation {
+                holder_id: "backend".into(),
+                weight: 3.0,
+            },
+            WorkAllocation {
+                holder_id: "frontend".into(),
+                weight: 2.0,
+            },
+            WorkAllocation {
+                holder_id: "qa".into(),
+                weight: 1.0,
+            },
+        ]),
+        "ops" => Some(vec![
+            WorkAllocation {
+                holder_id: "ops_lead".into(),
+                weight: 6.0,
+            },
+            WorkAllocation {
+                holder_id: "finance".into(),
+                weight: 2.0,
+            },
+            WorkAllocation {
+                holder_id: "admin".into(),
+                weight: 2.0,
+            },
+        ]),
+        _ => None,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn pm_create_task(
+    state: &mut CompanyState,
+    id: &str,
+    title: &str,
+    summary: &str,
+    dod: Vec<String>,
+    deliverables: Vec<Deliverable>,
+    max_total_loc: usize,
+    now: DateTime<Utc>,
+) {
+    state.tasks.insert(
+        id.to_string(),
+        Task {
+            id: id.to_string(),
+            title: title.to_string(),
+            summary: summary.to_string(),
+            definition_of_done: dod,
+            deliverables,
+            status: TaskStatus::Draft,
+            created_at: now,
+            updated_at: now,
+            assigned: vec![],
+            max_total_loc,
+            require_summary: true,
+            require_tests: true,
+        },
+    );
+}
+
+pub fn pm_ready_task(
+    state: &mut CompanyState,
+    id: &str,
+    now: DateTime<Utc>,
+) -> Result<(), EngineError> {
+    let task = state
+        .tasks
+        .get_mut(id)
+        .ok_or_else(|| EngineError::not_found(EntityKind::Task, id))?;
+    if task.definition_of_done.is_empty() {
+        return Err(EngineError::GuardrailViolation {
+            task_id: id.to_string(),
+            guardrail: Guardrail::DefinitionOfDoneRequired,
+        });
+    }
+    if task.deliverables.is_empty() {
+        return Err(EngineError::GuardrailViolation {
+            task_id: id.to_string(),
+            guardrail: Guardrail::DeliverableRequired,
+        });
+    }
+    task.status = TaskStatus::Ready;
+    task.updated_at = now;
+    Ok(())
+}
+
+pub fn pm_assign_task(
+    state: &mut CompanyState,
+    id: &str,
+    assignee_id: &str,
+    role: &str,
+    now: DateTime<Utc>,
+) -> Result<(), EngineError> {
+    let task = state
+        .tasks
+        .get_mut(id)
+        .ok_or_else(|| EngineError::not_found(EntityKind::Task, id))?;
+    task.assigned.push(TaskAssignment {
+        assignee_id: assignee_id.to_string(),
+        role: role.to_string(),
+        status: TaskStatus::InProgress,
+    });
+    task.status = TaskStatus::InProgress;
+    task.updated_at = now;
+    Ok(())
+}
+
+pub fn pm_submit_for_review(
+    state: &mut CompanyState,
+    id: &str,
+    now: DateTime<Utc>,
+) -> Result<(), EngineError> {
+    let task = state
+        .tasks
+        .get_mut(id)
+        .ok_or_else(|| EngineError::not_found(EntityKind::Task, id))?;
+    task.status = TaskStatus::Review;
+    task.updated_at = now;
+    Ok(())
+}
+
+pub fn pm_finalize_task(
+    state: &mut CompanyState,
+    id: &str,
+    loc_changed: usize,
+    tests_run: bool,
+    now: DateTime<Utc>,
+) -> Result<(), EngineError> {
+    let task = state
+        .tasks
+        .get_mut(id)
+        .ok_or_else(|| EngineError::not_found(EntityKind::Task, id))?;
+    if loc_changed > task.max_total_loc {
+        return Err(EngineError::GuardrailViolation {
+            task_id: id.to_string(),
+            guardrail: Guardrail::LocLimitExceeded {
+                limit: task.max_total_loc,
+                actual: loc_changed,
+            },
+        });
+    }
+    if task.require_tests && !tests_run {
+        return Err(EngineError::GuardrailViolation {
+            task_id: id.to_string(),
+            guardrail: Guardrail::TestsRequired,
+        });
+    }
+    task.status = TaskStatus::Done;
+    task.updated_at = now;
+    Ok(())
+}
+
+pub fn ensure_holder(state: &mut CompanyState, id: &str, name: &str) {
+    state.holders.entry(id.to_string()).or_insert(Holder {
+        id: id.to_string(),
+        display_name: name.to_string(),
+        tokens: Amount::ZERO,
+        cash: Amount::ZERO,
+        positions: vec![],
+        employee_since: None,
+    });
+}
+
+/// Appoints `holder_id` to `role` if they meet the promotion policy.
+pub fn assign_role_to_holder(
+    state: &mut CompanyState,
+    role: RoleTier,
+    holder_id: &str,
+    now: DateTime<Utc>,
+) -> Result<(), EngineError> {
+    let holds = state
+        .holders
+        .get(holder_id)
+        .ok_or_else(|| EngineError::not_found(EntityKind::Holder, holder_id))?
+        .positions
+        .contains(&role);
+    if !holds {
+        check_promotion(state, holder_id, role, now)?;
+    }
+    place_in_role(state, role, holder_id, now)
+}
+
+/// Puts `holder_id` in `role` without consulting the promotion policy.
+fn place_in_role(
+    state: &mut CompanyState,
+    role: RoleTier,
+    holder_id: &str,
+    now: DateTime<Utc>,
+) -> Result<(), EngineError> {
+    let holder = state
+        .holders
+        .get_mut(holder_id)
+        .ok_or_else(|| EngineError::not_found(EntityKind::Holder, holder_id))?;
+    if holder.positions.contains(&role) {
+        return Ok(());
+    }
+
+    if role == RoleTier::Employee {
+        holder.positions.push(role);
+        holder.employee_since = Some(now);
+        state.employee_count += 1;
+        return Ok(());
+    }
+
+    if let Some(slot) = free_position(state, role, None) {
+        let pos = &mut state.positions[slot];
+        pos.holder_id = Some(holder_id.to_string());
+        pos.acquired_at = Some(now);
+        pos.price_paid = Some(Amount::ZERO);
+        if let Some(holder) = state.holders.get_mut(holder_id) {
+            holder.positions.push(role);
+        }
+        return Ok(());
+    }
+
+    // For fixed/special roles, create a position if missing; one that is
+    // vacant but up for auction is not missing.
+    let special = matches!(
+        role,
+        RoleTier::CEO | RoleTier::President | RoleTier::CoPresident | RoleTier::BoardSeat
+    );
+    let vacant = state
+        .positions
+        .iter()
+        .any(|p| p.tier == role && p.holder_id.is_none());
+    if special && !vacant {
+        state.positions.push(RolePosition {
+            tier: role,
+            holder_id: Some(holder_id.to_string()),
+            acquired_at: Some(now),
+            price_paid: Some(Amount::ZERO),
+        });
+        if let Some(holder) = state.holders.get_mut(holder_id) {
+            holder.positions.push(role);
+        }
+        return Ok(());
+    }
+
+    Err(EngineError::NoAvailablePosition { role })
+}
+
+pub fn seed_roles(
+    state: &mut CompanyState,
+    ceo_id: &str,
+    ceo_name: &str,
+    now: DateTime<Utc>,
+) -> Result<(), EngineError> {
+    ensure_holder(state, ceo_id, ceo_name);
+    ensure_positions(state);
+
+    place_in_role(state, RoleTier::CEO, ceo_id, now)?;
+    place_in_role(state, RoleTier::BoardSeat, ceo_id, now)?;
+
+    let defaults = vec![
+        ("president", "President", RoleTier::President),
+        ("co_president", "Co-President", RoleTier::CoPresident),
+        ("csuite", "C-Suite", RoleTier::CSuite),
+        ("director", "Director", RoleTier::Director),
+        ("senior_manager", "Senior Manager", RoleTier::SeniorManager),
+        ("manager", "Manager", RoleTier::Manager),
+        ("employee", "Employee", RoleTier::Employee),
+    ];
+
+    for (id, name, role) in defaults {
+        ensure_holder(state, id, name);
+        let _ = place_in_role(state, role, id, now);
+    }
+    Ok(())
+}
+
+pub fn set_tokenomics(
+    state: &mut CompanyState,
+    total_supply_cap: Amount,
+    minted_supply: Amount,
+    allocations: Vec<TokenAllocation>,
+) {
+    state.tokenomics = Some(Tokenomics {
+        total_supply_cap,
+        minted_supply,
+        allocations,
+    });
+}
+
+pub fn tokenomics_report(state: &CompanyState) -> Option<serde_json::Value> {
+    let t = state.tokenomics.as_ref()?;
+    let allocated_total: f64 = t.allocations.iter().map(|a| a.percent).sum();
+    Some(serde_json::json!({
+        "total_supply_cap": t.total_supply_cap,
+        "minted_supply": t.minted_supply,
+        "remaining_supply": remaining_supply(state),
+        "allocations": t.allocations,
+        "allocations_total_percent": allocated_total
+    }))
+}
+
+/// Mints `amount` to a holder, or onto a vesting schedule for them under
+/// `vesting`; rejected rather than clamped past the cap.
+pub fn grant_tokens(
+    state: &mut CompanyState,
+    holder_id: &str,
+    amount: Amount,
+    vesting: Option<VestingTerms>,
+    now: DateTime<Utc>,
+) -> Result<(), EngineError> {
+    mint_to_holder(state, holder_id, amount, vesting, false, "token grant", now)?;
+    Ok(())
+}
+
+fn mint_to_holder(
+    state: &mut CompanyState,
+    holder_id: &str,
+    amount: Amount,
+    vesting: Option<VestingTerms>,
+    clamp: bool,
+    memo: &str,
+    now: DateTime<Utc>,
+) -> Result<MintOutcome, EngineError> {
+    let Some(terms) = vesting else {
+        return mint(
+            state,
+            Account::Holder(holder_id.to_string()),
+            amount,
+            clamp,
+            memo,
+            now,
+        );
+    };
+    if !state.holders.contains_key(holder_id) {
+        return Err(EngineError::not_found(EntityKind::Holder, holder_id));
+    }
+    validate_vesting_terms(&terms)?;
+    let outcome = mint(state, Account::Vesting, amount, clamp, memo, now)?;
+    if !outcome.minted.is_zero() {
+        start_vesting(state, holder_id, outcome.minted, terms, None, now)?;
+    }
+    Ok(outcome)
+}
+
+fn validate_vesting_terms(terms: &VestingTerms) -> Result<(), EngineError> {
+    if terms.cliff_days < 0 || terms.duration_days < 0 {
+        return Err(EngineError::invalid("vesting periods must be >= 0"));
+    }
+    if terms.cliff_days > terms.duration_days {
+        return Err(EngineError::invalid(
+            "vesting cliff cannot be longer than the vesting duration",
+        ));
+    }
+    Ok(())
+}
+
+/// Opens a schedule for tokens the caller has already moved into the
+/// vesting pool, tied to `role` if they are that role's pay.
+fn start_vesting(
+    state: &mut CompanyState,
+    holder_id: &str,
+    total: Amount,
+    terms: VestingTerms,
+    role: Option<RoleTier>,
+    now: DateTime<Utc>,
+) -> Result<u64, EngineError> {
+    validate_vesting_terms(&terms)?;
+    let id = state.vesting_schedules.len() as u64 + 1;
+    state.vesting_schedules.push(VestingSchedule {
+        id,
+        holder_id: holder_id.to_string(),
+        total,
+        start: now,
+        terms,
+        role,
+        released: Amount::ZERO,
+        forfeited: Amount::ZERO,
+    });
+    Ok(id)
+}
+
+/// The highest role `holder_id` holds that the vesting policy covers, with
+/// its terms.
+pub fn role_vesting_terms(
+    state: &CompanyState,
+    holder_id: &str,
+) -> Option<(RoleTier, VestingTerms)> {
+    let holder = state.holders.get(holder_id)?;
+    holder
+        .positions
+        .iter()
+        .filter_map(|role| state.vesting_policy.roles.get(role).map(|t| (*role, *t)))
+        .max_by_key(|(role, _)| *role)
+}
+
+pub fn set_role_vesting(
+    state: &mut CompanyState,
+    role: RoleTier,
+    terms: Option<VestingTerms>,
+) -> Result<(), EngineError> {
+    match terms {
+        Some(terms) => {
+            validate_vesting_terms(&terms)?;
+            state.vesting_policy.roles.insert(role, terms);
+        }
+        None => {
+            state.vesting_policy.roles.remove(&role);
+        }
+    }
+    Ok(())
+}
+
+/// How much of the schedule has vested by `now`, forfeitures aside.
+pub fn vested_amount(schedule: &VestingSchedule, now: DateTime<Utc>) -> Amount {
+    let elapsed = (now - schedule.start).num_seconds();
+    let cliff = schedule.terms.cliff_days * 86_400;
+    let duration = schedule.terms.duration_days * 86_400;
+    if elapsed >= duration {
+        return schedule.total;
+    }
+    if elapsed < cliff {
+        return Amount::ZERO;
+    }
+    schedule
+        .total
+        .checked_mul_div(elapsed as i128, duration as i128)
+        .unwrap_or(schedule.total)
+}
+
+/// Releases everything vested by `now` to the holders; returns the total.
+pub fn accrue_vesting(state: &mut CompanyState, now: DateTime<Utc>) -> Result<Amount, EngineError> {
+    accrue_vesting_for(state, None, now)
+}
+
+fn accrue_vesting_for(
+    state: &mut CompanyState,
+    holder_id: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<Amount, EngineError> {
+    let mut released = Amount::ZERO;
+    for i in 0..state.vesting_schedules.len() {
+        let schedule = &state.vesting_schedules[i];
+        if holder_id.is_some_and(|id| id != schedule.holder_id) {
+            continue;
+        }
+        let vested = vested_amount(schedule, now).min(schedule.total - schedule.forfeited);
+        let due = vested - schedule.released;
+        if due <= Amount::ZERO {
+            continue;
+        }
+        let holder = Account::Holder(schedule.holder_id.clone());
+        transfer(
+            state,
+            now,
+            Asset::Tokens,
+            "vesting release",
+            Account::Vesting,
+            holder,
+            due,
+        )?;
+        state.vesting_schedules[i].released = vested;
+        released = released.checked_add(due).ok_or(EngineError::Overflow)?;
+    }
+    Ok(released)
+}
+
+/// Releases what `holder_id` has vested by `now`, then returns the rest of
+/// the vesting tokens `role` paid them to the treasury.
+pub fn forfeit_unvested(
+    state: &mut CompanyState,
+    holder_id: &str,
+    role: RoleTier,
+    now: DateTime<Utc>,
+) -> Result<Amount, EngineError> {
+    accrue_vesting_for(state, Some(holder_id), now)?;
+    let mut forfeited = Amount::ZERO;
+    for schedule in state
+        .vesting_schedules
+        .iter_mut()
+        .filter(|s| s.holder_id == holder_id && s.role == Some(role))
+    {
+        let unvested = schedule.unvested();
+        schedule.forfeited = schedule.forfeited + unvested;
+        forfeited = forfeited + unvested;
+    }
+    transfer(
+        state,
+        now,
+        Asset::Tokens,
+        &format!("unvested {} tokens forfeited by {}", role, holder_id),
+        Account::Vesting,
+        Account::Treasury,
+        forfeited,
+    )?;
+    Ok(forfeited)
+}
+
+/// Tokens granted to `holder_id` that have not vested (or been forfeited) yet.
+pub fn unvested_tokens(state: &CompanyState, holder_id: &str) -> Amount {
+    state
+        .vesting_schedules
+        .iter()
+        .filter(|s| s.holder_id == holder_id)
+        .map(VestingSchedule::unvested)
+        .sum()
+}
+
+pub fn set_onboarding_policy(
+    state: &mut CompanyState,
+    early_joiner_limit: Option<usize>,
+    early_joiner_reward: Option<Amount>,
+    early_joiner_vesting: Option<VestingTerms>,
+) -> Result<(), EngineError> {
+    if let Some(terms) = early_joiner_vesting {
+        validate_vesting_terms(&terms)?;
+        state.onboarding_policy.early_joiner_vesting = Some(terms);
+    }
+    if let Some(limit) = early_joiner_limit {
+        state.onboarding_policy.early_joiner_limit = limit;
+    }
+    if let Some(reward) = early_joiner_reward {
+        state.onboarding_policy.early_joiner_reward = reward;
+    }
+    Ok(())
+}
+
+pub fn auto_onboard(
+    state: &mut CompanyState,
+    id: &str,
+    name: &str,
+    cash: Amount,
+    now: DateTime<Utc>,
+) -> Result<bool, EngineError> {
+    onboard_holder(state, id, name, cash, now)?;
+    state.onboarding_count += 1;
+
+    let mut rewarded = false;
+    if state.onboarding_count <= state.onboarding_policy.early_joiner_limit
+        && state.onboarding_policy.early_joiner_reward > Amount::ZERO
+    {
+        // A reward the cap cannot cover shrinks rather than blocking the join.
+        let outcome = mint_to_holder(
+            state,
+            id,
+            state.onboarding_policy.early_joiner_reward,
+            state.onboarding_policy.early_joiner_vesting,
+            true,
+            "early joiner reward",
+            now,
+        )?;
+        rewarded = outcome.minted > Amount::ZERO;
     }
     Ok(rewarded)
 }
+
+/// Checks the cross-references and accounting invariants a consistent state
+/// must satisfy. An empty result means the state is sound.
+pub fn verify(state: &CompanyState) -> Vec<Violation> {
+    let mut violations = vec![];
+
+    let mut occupied: HashMap<(&str, RoleTier), usize> = HashMap::new();
+    for pos in &state.positions {
+        let Some(holder_id) = pos.holder_id.as_deref() else {
+            continue;
+        };
+        if !state.holders.contains_key(holder_id) {
+            violations.push(Violation::UnknownPositionHolder {
+                role: pos.tier,
+                holder_id: holder_id.to_string(),
+            });
+            continue;
+        }
+        *occupied.entry((holder_id, pos.tier)).or_default() += 1;
+    }
+    let mut holders: Vec<&Holder> = state.holders.values().collect();
+    holders.sort_by(|a, b| a.id.cmp(&b.id));
+    for holder in &holders {
+        let mut listed: Vec<(RoleTier, usize)> = vec![];
+        for role in &holder.positions {
+            match listed.iter_mut().find(|(r, _)| r == role) {
+                Some((_, n)) => *n += 1,
+                None => listed.push((*role, 1)),
+            }
+        }
+        // Employee is a status, not a seat, so it has no role position.
+        for (role, listed) in listed {
+            let occupied = occupied
+                .remove(&(holder.id.as_str(), role))
+                .unwrap_or_default();
+            if role != RoleTier::Employee && listed != occupied {
+                violations.push(Violation::RoleCountMismatch {
+                    holder_id: holder.id.clone(),
+                    role,
+                    listed,
+                    occupied,
+                });
+            }
+        }
+    }
+    let mut unlisted: Vec<_> = occupied.into_iter().collect();
+    unlisted.sort_by(|a, b| a.0.cmp(&b.0));
+    for ((holder_id, role), occupied) in unlisted {
+        violations.push(Violation::RoleCountMismatch {
+            holder_id: holder_id.to_string(),
+            role,
+            listed: 0,
+            occupied,
+        });
+    }
+
+    // Headcount may include staff who are not holders, so it can only be
+    // too low, never too high.
+    let employees = holders
+        .iter()
+        .filter(|h| h.positions.contains(&RoleTier::Employee))
+        .count();
+    if state.employee_count < employees {
+        violations.push(Violation::EmployeeCountTooLow {
+            recorded: state.employee_count,
+            holders: employees,
+        });
+    }
+
+    if let Some(t) = &state.tokenomics
+        && t.minted_supply > t.total_supply_cap
+    {
+        violations.push(Violation::SupplyCapExceeded {
+            cap: t.total_supply_cap,
+            minted: t.minted_supply,
+        });
+    }
+
+    let mut balances = vec![
+        (Account::Treasury, Asset::Tokens, state.treasury_tokens),
+        (Account::Treasury, Asset::Cash, state.treasury_cash),
+    ];
+    for h in &holders {
+        balances.push((Account::Holder(h.id.clone()), Asset::Tokens, h.tokens));
+        balances.push((Account::Holder(h.id.clone()), Asset::Cash, h.cash));
+    }
+    for (account, asset, balance) in balances {
+        if balance.is_negative() {
+            violations.push(Violation::NegativeBalance {
+                account: account.to_string(),
+                asset,
+                balance,
+            });
+        }
+    }
+
+    for entry in &state.journal {
+        let off_by = entry
+            .postings
+            .iter()
+            .try_fold(Amount::ZERO, |acc, p| acc.checked_add(p.amount));
+        if off_by != Some(Amount::ZERO) {
+            violations.push(Violation::UnbalancedJournalEntry {
+                entry_id: entry.id,
+                off_by: off_by.unwrap_or_default(),
+            });
+        }
+    }
+    // States that predate the event log have no journal to reconcile with
+    // until their first logged change imports them.
+    if state.event_seq > 0 {
+        for d in reconcile(state) {
+            violations.push(Violation::LedgerMismatch {
+                account: d.account.to_string(),
+                asset: d.asset,
+                cached: d.cached,
+                journal: d.derived,
+            });
+        }
+        let staked = balance(state, &Account::Staking, Asset::Tokens);
+        let stakes: Amount = state.stakes.values().map(Stake::total).sum();
+        if staked != stakes {
+            violations.push(Violation::StakingPoolMismatch {
+                pool: staked,
+                stakes,
+            });
+        }
+        let pool = balance(state, &Account::Escrow, Asset::Cash);
+        let bids: Amount = state.auctions.values().map(Auction::escrowed).sum();
+        if pool != bids {
+            violations.push(Violation::EscrowMismatch { pool, bids });
+        }
+        let pool = balance(state, &Account::Vesting, Asset::Tokens);
+        let unvested: Amount = state
+            .vesting_schedules
+            .iter()
+            .map(VestingSchedule::unvested)
+            .sum();
+        if pool != unvested {
+            violations.push(Violation::VestingPoolMismatch {
+                pool,
+                schedules: unvested,
+            });
+        }
+    }
+
+    for listing in state.marketplace.iter().filter(|l| l.active) {
+        let held = state
+            .holders
+            .get(&listing.seller_id)
+            .is_some_and(|h| h.positions.contains(&listing.role));
+        if !held {
+            violations.push(Violation::StaleListing {
+                listing_id: listing.id.clone(),
+                seller_id: listing.seller_id.clone(),
+                role: listing.role,
+            });
+        }
+    }
+
+    violations
+}
+
+pub fn set_transfer_policy(
+    state: &mut CompanyState,
+    policy: TransferPolicy,
+) -> Result<(), EngineError> {
+    if policy.lockup_days < 0 {
+        return Err(EngineError::invalid("lock-up period must be >= 0"));
+    }
+    if policy.max_per_day.is_some_and(|m| m.is_negative()) {
+        return Err(EngineError::invalid("daily transfer limit must be >= 0"));
+    }
+    state.transfer_policy = policy;
+    Ok(())
+}
+
+/// Tokens another holder, the treasury or the mint sent `holder_id` within
+/// the lock-up window before `now`.
+pub fn locked_tokens(state: &CompanyState, holder_id: &str, now: DateTime<Utc>) -> Amount {
+    let lockup_days = state.transfer_policy.lockup_days;
+    if lockup_days == 0 {
+        return Amount::ZERO;
+    }
+    let since = now - Duration::days(lockup_days);
+    state
+        .token_receipts
+        .get(holder_id)
+        .into_iter()
+        .flatten()
+        .filter(|r| r.at > since)
+        .map(|r| r.amount)
+        .sum()
+}
+
+/// Checks `holder_id` may send `amount` at `now` under the transfer policy.
+pub fn check_transfer_allowed(
+    state: &CompanyState,
+    holder_id: &str,
+    amount: Amount,
+    now: DateTime<Utc>,
+) -> Result<(), EngineError> {
+    let policy = &state.transfer_policy;
+    let refuse = |restriction| EngineError::TransferRestricted {
+        holder_id: holder_id.to_string(),
+        restriction,
+    };
+
+    if policy.block_during_removal_vote {
+        let mut open: Vec<&VoteRecord> = state
+            .votes
+            .values()
+            .filter(|v| !v.resolved && v.proposal.removal_target() == Some(holder_id))
+            .collect();
+        open.sort_by(|a, b| a.id.cmp(&b.id));
+        if let Some(vote) = open.first() {
+            return Err(refuse(TransferRestriction::RemovalVoteOpen {
+                vote_id: vote.id.clone(),
+            }));
+        }
+    }
+
+    let balance = state
+        .holders
+        .get(holder_id)
+        .map(|h| h.tokens)
+        .unwrap_or_default();
+    let locked = locked_tokens(state, holder_id, now).min(balance);
+    let transferable = balance - locked;
+    if amount > transferable && !locked.is_zero() {
+        return Err(refuse(TransferRestriction::LockedUp {
+            locked,
+            transferable,
+        }));
+    }
+
+    if let Some(limit) = policy.max_per_day {
+        let today = now.date_naive();
+        let already_sent: Amount = state
+            .transfers
+            .iter()
+            .filter(|t| t.from == holder_id && t.at.date_naive() == today)
+            .map(|t| t.amount)
+            .sum();
+        let total = already_sent
+            .checked_add(amount)
+            .ok_or(EngineError::Overflow)?;
+        if total > limit {
+            return Err(refuse(TransferRestriction::DailyLimitExceeded {
+                limit,
+                already_sent,
+            }));
+        }
+    }
+    Ok(())
+}
+
+/// Sends tokens between holders, subject to the transfer policy.
+pub fn transfer_tokens(
+    state: &mut CompanyState,
+    from: &str,
+    to: &str,
+    amount: Amount,
+    memo: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<(), EngineError> {
+    if amount <= Amount::ZERO {
+        return Err(EngineError::invalid("transfer amount must be > 0"));
+    }
+    if from == to {
+        return Err(EngineError::invalid("cannot transfer to yourself"));
+    }
+    for id in [from, to] {
+        if !state.holders.contains_key(id) {
+            return Err(EngineError::not_found(EntityKind::Holder, id));
+        }
+    }
+    check_transfer_allowed(state, from, amount, now)?;
+    transfer(
+        state,
+        now,
+        Asset::Tokens,
+        memo.unwrap_or("holder transfer"),
+        Account::Holder(from.to_string()),
+        Account::Holder(to.to_string()),
+        amount,
+    )?;
+    state.transfers.push(TokenTransfer {
+        from: from.to_string(),
+        to: to.to_string(),
+        amount,
+        at: now,
+        memo: memo.map(str::to_string),
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Monday 2025-01-06, the first moment of ISO week 2025-W02.
+    fn monday() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 6, 0, 0, 0).unwrap()
+    }
+
+    fn company(holders: &[(&str, i64)]) -> CompanyState {
+        let mut state = new_company(0, monday());
+        for (id, cash) in holders {
+            onboard_holder(&mut state, id, id, Amount::whole(*cash), monday()).unwrap();
+        }
+        state
+    }
+
+    fn revenue(state: &mut CompanyState, at: DateTime<Utc>, gross: i64) {
+        let event = RevenueEvent {
+            timestamp: at,
+            gross_revenue: Amount::whole(gross),
+            refund_amount: Amount::ZERO,
+        };
+        ingest_revenue(state, &event).unwrap();
+    }
+
+    #[test]
+    fn last_completed_week_is_the_one_before_now() {
+        assert_eq!(last_completed_week(monday()), "2025-W01");
+        assert_eq!(
+            last_completed_week(monday() + Duration::days(6)),
+            "2025-W01"
+        );
+        assert_eq!(
+            last_completed_week(monday() + Duration::days(7)),
+            "2025-W02"
+        );
+    }
+
+    #[test]
+    fn completed_week_emission_waits_for_the_week_to_end() {
+        let mut state = company(&[]);
+        revenue(&mut state, monday() + Duration::days(1), 1_000);
+        revenue(&mut state, monday() + Duration::days(8), 500);
+
+        let err =
+            emit_completed_week(&mut state, "2025-W02", monday() + Duration::days(6)).unwrap_err();
+        assert!(matches!(err, EngineError::InvalidInput { .. }), "{err:?}");
+        assert!(state.weekly_emissions.is_empty());
+
+        let emission =
+            emit_completed_week(&mut state, "2025-W2", monday() + Duration::days(7)).unwrap();
+        assert_eq!(emission.week, "2025-W02");
+        assert_eq!(emission.net_revenue, Amount::whole(1_000));
+        assert_eq!(emission.minted, Amount::whole(200));
+        assert_eq!(
+            emission.as_of,
+            monday() + Duration::days(7) - Duration::seconds(1)
+        );
+
+        let err =
+            emit_completed_week(&mut state, "2025-W002", monday() + Duration::days(9)).unwrap_err();
+        assert!(
+            matches!(err, EngineError::WeekAlreadyEmitted { .. }),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn week_ids_are_canonical() {
+        assert_eq!(canonical_iso_week("2025-W2").unwrap(), "2025-W02");
+        assert_eq!(canonical_iso_week("2025-W002").unwrap(), "2025-W02");
+        assert!(canonical_iso_week("2025-W54").is_err());
+        assert!(canonical_iso_week("2025-02").is_err());
+        assert!(same_week("2025-W2", "2025-W02"));
+        assert!(!same_week("2025-W2", "2025-W03"));
+        assert!(!same_week("junk", "2025-W02"));
+    }
+
+    #[test]
+    fn payout_runs_match_any_spelling_of_the_week() {
+        let mut state = company(&[("ops_lead", 0), ("finance", 0), ("admin", 0)]);
+        revenue(&mut state, monday() + Duration::days(2), 1_000);
+        let ops = PayoutSource::Template { name: "ops".into() };
+        let now = monday() + Duration::days(8);
+
+        let run = run_payout(&mut state, "2025-W2", &ops, now).unwrap();
+        assert_eq!(run.week, "2025-W02");
+        assert!(run.emitted);
+        assert_eq!(run.distributed, Amount::whole(200));
+        assert_eq!(state.weekly_emissions[0].week, "2025-W02");
+        assert!(payout_run(&state, "2025-W002").is_some());
+
+        let err = run_payout(&mut state, "2025-W02", &ops, now).unwrap_err();
+        assert!(matches!(err, EngineError::AlreadyExists { .. }), "{err:?}");
+
+        reverse_payout(&mut state, "2025-W002", now).unwrap();
+        assert!(payout_run(&state, "2025-W02").is_none());
+        let rerun = run_payout(&mut state, "2025-W02", &ops, now).unwrap();
+        assert_eq!(rerun.distributed, Amount::whole(200));
+        assert!(verify(&state).is_empty());
+    }
+
+    #[test]
+    fn emission_is_clamped_at_the_supply_cap_and_reports_what_it_withheld() {
+        let mut state = company(&[("alice", 0)]);
+        set_tokenomics(&mut state, Amount::whole(1_000), Amount::whole(900), vec![]);
+        revenue(&mut state, monday() + Duration::days(1), 1_000);
+
+        let emission =
+            emit_completed_week(&mut state, "2025-W02", monday() + Duration::days(7)).unwrap();
+        assert_eq!(emission.minted, Amount::whole(100));
+        assert_eq!(emission.withheld, Amount::whole(100));
+        assert_eq!(remaining_supply(&state), Some(Amount::ZERO));
+        assert_eq!(state.weekly_emissions[0].withheld, Amount::whole(100));
+    }
+
+    #[test]
+    fn grants_past_the_supply_cap_are_rejected_whole() {
+        let mut state = company(&[("alice", 0)]);
+        set_tokenomics(&mut state, Amount::whole(1_000), Amount::whole(950), vec![]);
+        let before = state.holders["alice"].tokens;
+
+        let err = grant_tokens(&mut state, "alice", Amount::whole(60), None, monday()).unwrap_err();
+        assert!(
+            matches!(err, EngineError::SupplyCapExceeded { requested, available }
+                if requested == Amount::whole(60) && available == Amount::whole(50)),
+            "{err:?}"
+        );
+        assert_eq!(state.holders["alice"].tokens, before);
+        assert_eq!(remaining_supply(&state), Some(Amount::whole(50)));
+
+        grant_tokens(&mut state, "alice", Amount::whole(50), None, monday()).unwrap();
+        assert_eq!(state.holders["alice"].tokens, before + Amount::whole(50));
+        assert_eq!(remaining_supply(&state), Some(Amount::ZERO));
+    }
+
+    fn schedule(cliff_days: i64, duration_days: i64) -> VestingSchedule {
+        VestingSchedule {
+            id: 1,
+            holder_id: "alice".into(),
+            total: Amount::whole(100),
+            start: monday(),
+            terms: VestingTerms {
+                cliff_days,
+                duration_days,
+            },
+            role: None,
+            released: Amount::ZERO,
+            forfeited: Amount::ZERO,
+        }
+    }
+
+    #[test]
+    fn vesting_waits_for_the_cliff_then_releases_linearly() {
+        let schedule = schedule(10, 40);
+        let vested = |days: i64| vested_amount(&schedule, monday() + Duration::days(days));
+        assert_eq!(vested(0), Amount::ZERO);
+        assert_eq!(vested(9), Amount::ZERO);
+        assert_eq!(vested(10), Amount::whole(25));
+        assert_eq!(vested(20), Amount::whole(50));
+        assert_eq!(vested(40), Amount::whole(100));
+        assert_eq!(vested(400), Amount::whole(100));
+        assert_eq!(
+            vested_amount(&self::schedule(0, 0), monday()),
+            Amount::whole(100)
+        );
+    }
+
+    #[test]
+    fn accrual_moves_vested_tokens_to_the_holder() {
+        let mut state = company(&[("alice", 0)]);
+        let terms = VestingTerms {
+            cliff_days: 10,
+            duration_days: 40,
+        };
+        grant_tokens(
+            &mut state,
+            "alice",
+            Amount::whole(100),
+            Some(terms),
+            monday(),
+        )
+        .unwrap();
+
+        accrue_vesting(&mut state, monday() + Duration::days(5)).unwrap();
+        assert_eq!(state.holders["alice"].tokens, Amount::ZERO);
+        let released = accrue_vesting(&mut state, monday() + Duration::days(20)).unwrap();
+        assert_eq!(released, Amount::whole(50));
+        assert_eq!(state.holders["alice"].tokens, Amount::whole(50));
+        accrue_vesting(&mut state, monday() + Duration::days(60)).unwrap();
+        assert_eq!(state.holders["alice"].tokens, Amount::whole(100));
+        assert_eq!(unvested_tokens(&state, "alice"), Amount::ZERO);
+        assert!(verify(&state).is_empty());
+    }
+
+    #[test]
+    fn removal_forfeits_only_the_vesting_tied_to_the_role() {
+        let mut state = company(&[("alice", 0)]);
+        let terms = VestingTerms {
+            cliff_days: 0,
+            duration_days: 40,
+        };
+        mint(
+            &mut state,
+            Account::Treasury,
+            Amount::whole(1_000),
+            false,
+            "seed",
+            monday(),
+        )
+        .unwrap();
+        place_in_role(&mut state, RoleTier::CEO, "alice", monday()).unwrap();
+        set_role_vesting(&mut state, RoleTier::CEO, Some(terms)).unwrap();
+        let work = [WorkAllocation {
+            holder_id: "alice".into(),
+            weight: 1.0,
+        }];
+        distribute_tokens(&mut state, &work, Amount::whole(100), monday()).unwrap();
+        grant_tokens(
+            &mut state,
+            "alice",
+            Amount::whole(40),
+            Some(terms),
+            monday(),
+        )
+        .unwrap();
+        assert_eq!(state.vesting_schedules[0].role, Some(RoleTier::CEO));
+        assert_eq!(state.vesting_schedules[1].role, None);
+
+        let halfway = monday() + Duration::days(20);
+        let removal = |role| Proposal::RemoveFromRole {
+            target_role: role,
+            target_holder: "alice".into(),
+        };
+        apply_proposal(&mut state, &removal(RoleTier::Employee), halfway).unwrap();
+        assert_eq!(unvested_tokens(&state, "alice"), Amount::whole(70));
+
+        apply_proposal(&mut state, &removal(RoleTier::CEO), halfway).unwrap();
+        assert!(!state.holders["alice"].positions.contains(&RoleTier::CEO));
+        assert_eq!(state.vesting_schedules[0].forfeited, Amount::whole(50));
+        assert_eq!(state.vesting_schedules[1].forfeited, Amount::ZERO);
+        assert_eq!(unvested_tokens(&state, "alice"), Amount::whole(20));
+        assert_eq!(state.holders["alice"].tokens, Amount::whole(70));
+        assert!(verify(&state).is_empty());
+    }
+
+    /// Three one-vote holders and an open mint proposal, two-thirds to
+    /// pass with half of them voting.
+    fn open_vote(allow_vote_changes: bool) -> CompanyState {
+        let mut state = company(&[("a", 0), ("b", 0), ("c", 0)]);
+        set_voting_power_policy(&mut state, VotingPowerPolicy::OneHolderOneVote).unwrap();
+        state.governance_policy.allow_vote_changes = allow_vote_changes;
+        let terms = vote_terms(&state, monday());
+        let proposal = Proposal::MintTokens {
+            amount: Amount::whole(1),
+        };
+        propose(&mut state, "v", proposal, "", terms, monday()).unwrap();
+        state
+    }
+
+    fn outcome(state: &CompanyState, days: i64) -> Option<VoteOutcome> {
+        vote_outcome(state, &state.votes["v"], monday() + Duration::days(days))
+    }
+
+    #[test]
+    fn vote_needs_quorum_once_closed() {
+        let mut state = open_vote(true);
+        cast_vote(&mut state, "v", "a", true, monday()).unwrap();
+        assert_eq!(outcome(&state, 1), None);
+        assert_eq!(outcome(&state, 7), Some(VoteOutcome::Failed));
+
+        cast_vote(&mut state, "v", "b", true, monday()).unwrap();
+        assert_eq!(outcome(&state, 7), Some(VoteOutcome::Passed));
+    }
+
+    #[test]
+    fn vote_closed_with_quorum_still_needs_the_threshold() {
+        let mut state = open_vote(true);
+        cast_vote(&mut state, "v", "a", true, monday()).unwrap();
+        cast_vote(&mut state, "v", "b", false, monday()).unwrap();
+        assert_eq!(outcome(&state, 7), Some(VoteOutcome::Failed));
+    }
+
+    #[test]
+    fn vote_is_not_decided_early_while_ballots_may_change() {
+        let mut state = open_vote(true);
+        for voter in ["a", "b", "c"] {
+            cast_vote(&mut state, "v", voter, true, monday()).unwrap();
+        }
+        assert_eq!(outcome(&state, 1), None);
+        assert_eq!(outcome(&state, 7), Some(VoteOutcome::Passed));
+    }
+
+    #[test]
+    fn vote_is_decided_early_once_the_rest_cannot_change_it() {
+        let mut state = open_vote(false);
+        cast_vote(&mut state, "v", "a", true, monday()).unwrap();
+        assert_eq!(outcome(&state, 1), None);
+        cast_vote(&mut state, "v", "b", true, monday()).unwrap();
+        assert_eq!(outcome(&state, 1), Some(VoteOutcome::Passed));
+
+        let mut state = open_vote(false);
+        cast_vote(&mut state, "v", "a", false, monday()).unwrap();
+        assert_eq!(outcome(&state, 1), None);
+        cast_vote(&mut state, "v", "b", false, monday()).unwrap();
+        assert_eq!(outcome(&state, 1), Some(VoteOutcome::Failed));
+    }
+
+    #[test]
+    fn second_ballot_is_refused_when_votes_cannot_change() {
+        let mut state = open_vote(false);
+        cast_vote(&mut state, "v", "a", true, monday()).unwrap();
+        let err = cast_vote(&mut state, "v", "a", false, monday()).unwrap_err();
+        assert!(
+            matches!(&err, EngineError::BallotAlreadyCast { vote_id, voter_id }
+                if vote_id == "v" && voter_id == "a"),
+            "{err:?}"
+        );
+        assert!(state.votes["v"].votes["a"].approve);
+        assert!(state.votes["v"].ballot_history.is_empty());
+    }
+
+    #[test]
+    fn changed_ballot_replaces_the_standing_one_and_is_kept_in_history() {
+        let mut state = open_vote(true);
+        cast_vote(&mut state, "v", "a", true, monday()).unwrap();
+        let later = monday() + Duration::hours(1);
+        cast_vote(&mut state, "v", "a", false, later).unwrap();
+        let record = &state.votes["v"];
+        assert!(!record.votes["a"].approve);
+        assert_eq!(record.ballot_history.len(), 1);
+        assert!(record.ballot_history[0].ballot.approve);
+        assert_eq!(record.ballot_history[0].superseded_at, later);
+    }
+
+    #[test]
+    fn vote_keeps_the_threshold_and_ballot_rule_it_opened_with() {
+        let mut state = open_vote(false);
+        state.governance_policy.vote_threshold = 1.0;
+        state.governance_policy.allow_vote_changes = true;
+        cast_vote(&mut state, "v", "a", true, monday()).unwrap();
+        let err = cast_vote(&mut state, "v", "a", false, monday()).unwrap_err();
+        assert!(
+            matches!(err, EngineError::BallotAlreadyCast { .. }),
+            "{err:?}"
+        );
+        cast_vote(&mut state, "v", "b", false, monday()).unwrap();
+        cast_vote(&mut state, "v", "c", true, monday()).unwrap();
+        assert_eq!(outcome(&state, 7), Some(VoteOutcome::Passed));
+
+        let terms = state.votes["v"].terms.unwrap();
+        assert_eq!(terms.threshold, Some(2.0 / 3.0));
+        assert_eq!(terms.allow_vote_changes, Some(false));
+    }
+
+    #[test]
+    fn vote_is_undecided_before_it_opens() {
+        let mut state = open_vote(false);
+        for voter in ["a", "b", "c"] {
+            cast_vote(&mut state, "v", voter, true, monday()).unwrap();
+        }
+        let before = monday() - Duration::seconds(1);
+        assert_eq!(vote_outcome(&state, &state.votes["v"], before), None);
+    }
+
+    #[test]
+    fn time_weighted_average_counts_the_price_standing_at_window_start() {
+        let mut state = company(&[]);
+        state.governance_policy.price_average = PriceAverage::TimeWeighted;
+        record_token_price(&mut state, 10.0, monday() - Duration::days(40));
+        record_token_price(&mut state, 4.0, monday() - Duration::days(10));
+        // 10 stood for the first 20 days of the 30-day window, 4 for the rest.
+        assert_eq!(rolling_average_price(&state, monday()), Some(8.0));
+    }
+
+    /// A sealed second-price auction for the vacant CEO seat with bids of
+    /// 50, 30 and 20 from holders who each start with 100 cash.
+    fn sealed_auction() -> CompanyState {
+        let mut state = company(&[("a", 100), ("b", 100), ("c", 100)]);
+        open_auction(
+            &mut state,
+            "ceo",
+            RoleTier::CEO,
+            AuctionFormat::Sealed,
+            AuctionPricing::SecondPrice,
+            Amount::whole(10),
+            monday() + Duration::days(1),
+            monday(),
+        )
+        .unwrap();
+        for (bidder, amount) in [("a", 50), ("b", 30), ("c", 20)] {
+            place_auction_bid(&mut state, "ceo", bidder, Amount::whole(amount), monday()).unwrap();
+        }
+        state
+    }
+
+    fn cash(state: &CompanyState, id: &str) -> Amount {
+        state.holders[id].cash
+    }
+
+    #[test]
+    fn second_price_auction_charges_the_runner_up_bid_and_refunds_the_rest() {
+        let mut state = sealed_auction();
+        assert_eq!(cash(&state, "a"), Amount::whole(50));
+        assert_eq!(
+            balance(&state, &Account::Escrow, Asset::Cash),
+            Amount::whole(100)
+        );
+
+        let early = settle_auction(&mut state, "ceo", monday()).unwrap_err();
+        assert!(
+            matches!(early, EngineError::InvalidInput { .. }),
+            "{early:?}"
+        );
+
+        let settlement = settle_auction(&mut state, "ceo", monday() + Duration::days(1)).unwrap();
+        assert_eq!(settlement.winner_id.as_deref(), Some("a"));
+        assert_eq!(settlement.price, Amount::whole(30));
+        assert_eq!(cash(&state, "a"), Amount::whole(70));
+        assert_eq!(cash(&state, "b"), Amount::whole(100));
+        assert_eq!(cash(&state, "c"), Amount::whole(100));
+        assert_eq!(state.treasury_cash, Amount::whole(30));
+        assert!(balance(&state, &Account::Escrow, Asset::Cash).is_zero());
+        assert!(state.holders["a"].positions.contains(&RoleTier::CEO));
+        assert!(verify(&state).is_empty());
+    }
+
+    #[test]
+    fn second_price_with_a_single_bid_charges_the_reserve() {
+        let mut state = company(&[("a", 100)]);
+        open_auction(
+            &mut state,
+            "ceo",
+            RoleTier::CEO,
+            AuctionFormat::Sealed,
+            AuctionPricing::SecondPrice,
+            Amount::whole(10),
+            monday() + Duration::days(1),
+            monday(),
+        )
+        .unwrap();
+        place_auction_bid(&mut state, "ceo", "a", Amount::whole(40), monday()).unwrap();
+        let settlement = settle_auction(&mut state, "ceo", monday() + Duration::days(1)).unwrap();
+        assert_eq!(settlement.price, Amount::whole(10));
+        assert_eq!(cash(&state, "a"), Amount::whole(90));
+    }
+
+    #[test]
+    fn auction_skips_and_refunds_bidders_no_longer_eligible() {
+        let mut state = company(&[("a", 100), ("b", 100), ("c", 100)]);
+        for id in ["a", "b", "c"] {
+            grant_tokens(&mut state, id, Amount::whole(5), None, monday()).unwrap();
+            stake_tokens(&mut state, id, Amount::whole(5), monday()).unwrap();
+        }
+        let rung = PromotionRung {
+            min_stake: Amount::whole(5),
+            ..PromotionRung::default()
+        };
+        let policy = PromotionPolicy {
+            rungs: BTreeMap::from([(RoleTier::CEO, rung)]),
+        };
+        set_promotion_policy(&mut state, policy).unwrap();
+        open_auction(
+            &mut state,
+            "ceo",
+            RoleTier::CEO,
+            AuctionFormat::Sealed,
+            AuctionPricing::SecondPrice,
+            Amount::whole(10),
+            monday() + Duration::days(1),
+            monday(),
+        )
+        .unwrap();
+        for (bidder, amount) in [("a", 50), ("b", 30), ("c", 20)] {
+            place_auction_bid(&mut state, "ceo", bidder, Amount::whole(amount), monday()).unwrap();
+        }
+        unstake_tokens(&mut state, "a", Amount::whole(5), monday()).unwrap();
+
+        let settlement = settle_auction(&mut state, "ceo", monday() + Duration::days(1)).unwrap();
+        assert_eq!(settlement.winner_id.as_deref(), Some("b"));
+        assert_eq!(settlement.price, Amount::whole(20));
+        assert_eq!(cash(&state, "a"), Amount::whole(100));
+        assert_eq!(cash(&state, "b"), Amount::whole(80));
+        assert_eq!(cash(&state, "c"), Amount::whole(100));
+        assert!(verify(&state).is_empty());
+    }
+
+    fn restriction(result: Result<(), EngineError>) -> TransferRestriction {
+        match result {
+            Err(EngineError::TransferRestricted { restriction, .. }) => restriction,
+            other => panic!("expected a transfer restriction, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn lockup_holds_back_only_tokens_sent_by_others() {
+        let mut state = company(&[("alice", 0), ("bob", 0)]);
+        state.transfer_policy.lockup_days = 3;
+        let day = |n| monday() + Duration::days(n);
+        let terms = VestingTerms {
+            cliff_days: 0,
+            duration_days: 10,
+        };
+        grant_tokens(&mut state, "alice", Amount::whole(100), None, day(0)).unwrap();
+        grant_tokens(&mut state, "alice", Amount::whole(20), Some(terms), day(0)).unwrap();
+        let send = |state: &mut CompanyState, amount, at| {
+            transfer_tokens(state, "alice", "bob", Amount::whole(amount), None, at)
+        };
+        assert!(matches!(
+            restriction(send(&mut state, 1, day(1))),
+            TransferRestriction::LockedUp { locked, .. } if locked == Amount::whole(100)
+        ));
+
+        stake_tokens(&mut state, "alice", Amount::whole(40), day(0)).unwrap();
+        unstake_tokens(&mut state, "alice", Amount::whole(40), day(0)).unwrap();
+        send(&mut state, 10, day(4)).unwrap();
+        assert_eq!(locked_tokens(&state, "bob", day(4)), Amount::whole(10));
+        assert!(
+            transfer_tokens(&mut state, "bob", "alice", Amount::whole(1), None, day(4)).is_err()
+        );
+
+        claim_unbonded(&mut state, "alice", day(7)).unwrap();
+        accrue_vesting(&mut state, day(7)).unwrap();
+        assert_eq!(state.holders["alice"].tokens, Amount::whole(104));
+        assert_eq!(locked_tokens(&state, "alice", day(7)), Amount::ZERO);
+        send(&mut state, 104, day(7)).unwrap();
+    }
+
+    #[test]
+    fn daily_limit_counts_what_was_sent_that_utc_day() {
+        let mut state = company(&[("alice", 0), ("bob", 0)]);
+        state.transfer_policy.max_per_day = Some(Amount::whole(50));
+        grant_tokens(&mut state, "alice", Amount::whole(100), None, monday()).unwrap();
+        let send = |state: &mut CompanyState, amount, at| {
+            transfer_tokens(state, "alice", "bob", Amount::whole(amount), None, at)
+        };
+        send(&mut state, 30, monday() + Duration::hours(9)).unwrap();
+        assert!(matches!(
+            restriction(send(&mut state, 30, monday() + Duration::hours(23))),
+            TransferRestriction::DailyLimitExceeded { already_sent, .. }
+                if already_sent == Amount::whole(30)
+        ));
+        send(&mut state, 20, monday() + Duration::hours(23)).unwrap();
+        send(&mut state, 30, monday() + Duration::hours(24)).unwrap();
+    }
+
+    #[test]
+    fn open_removal_vote_blocks_the_target_from_sending() {
+        let mut state = company(&[("alice", 0), ("bob", 0)]);
+        grant_tokens(&mut state, "alice", Amount::whole(100), None, monday()).unwrap();
+        place_in_role(&mut state, RoleTier::CEO, "alice", monday()).unwrap();
+        let terms = vote_terms(&state, monday());
+        let proposal = Proposal::RemoveFromRole {
+            target_role: RoleTier::CEO,
+            target_holder: "alice".into(),
+        };
+        propose(&mut state, "oust", proposal, "test", terms, monday()).unwrap();
+        let send = |state: &mut CompanyState| {
+            transfer_tokens(state, "alice", "bob", Amount::whole(1), None, monday())
+        };
+        assert!(matches!(
+            restriction(send(&mut state)),
+            TransferRestriction::RemovalVoteOpen { vote_id } if vote_id == "oust"
+        ));
+
+        state.votes.get_mut("oust").unwrap().resolved = true;
+        send(&mut state).unwrap();
+
+        state.votes.get_mut("oust").unwrap().resolved = false;
+        state.transfer_policy.block_during_removal_vote = false;
+        send(&mut state).unwrap();
+    }
+
+    #[test]
+    fn voting_power_follows_the_policy() {
+        let mut state = company(&[("a", 0), ("b", 0)]);
+        grant_tokens(&mut state, "a", Amount::whole(100), None, monday()).unwrap();
+        grant_tokens(&mut state, "b", Amount::whole(9), None, monday()).unwrap();
+        stake_tokens(&mut state, "b", Amount::whole(4), monday()).unwrap();
+        place_in_role(&mut state, RoleTier::CEO, "b", monday()).unwrap();
+        state
+            .staking_policy
+            .role_multipliers
+            .insert(RoleTier::CEO, 3.0);
+        let power = |state: &mut CompanyState, policy| {
+            set_voting_power_policy(state, policy).unwrap();
+            (voting_power(state, "a"), voting_power(state, "b"))
+        };
+
+        assert_eq!(
+            power(&mut state, VotingPowerPolicy::default()),
+            (100.0, 5.0)
+        );
+        assert_eq!(
+            power(&mut state, VotingPowerPolicy::StakeWeighted),
+            (0.0, 12.0)
+        );
+        assert_eq!(
+            power(&mut state, VotingPowerPolicy::OneHolderOneVote),
+            (1.0, 1.0)
+        );
+        let weights = BTreeMap::from([(RoleTier::CEO, 7.0)]);
+        let role_tier = VotingPowerPolicy::RoleTierWeighted { weights };
+        assert_eq!(power(&mut state, role_tier), (0.0, 7.0));
+        assert_eq!(
+            power(&mut state, VotingPowerPolicy::Quadratic),
+            (10.0, 5f64.sqrt())
+        );
+        assert_eq!(voting_power(&state, "ghost"), 0.0);
+    }
+
+    #[test]
+    fn cast_vote_rejects_unknown_and_powerless_voters() {
+        let mut state = company(&[("a", 0), ("b", 0)]);
+        grant_tokens(&mut state, "a", Amount::whole(100), None, monday()).unwrap();
+        let terms = vote_terms(&state, monday());
+        let proposal = Proposal::MintTokens {
+            amount: Amount::whole(1),
+        };
+        propose(&mut state, "v", proposal, "test", terms, monday()).unwrap();
+
+        let err = cast_vote(&mut state, "v", "ghost", true, monday()).unwrap_err();
+        assert!(matches!(err, EngineError::NotFound { .. }), "{err:?}");
+        let err = cast_vote(&mut state, "v", "b", true, monday()).unwrap_err();
+        assert!(matches!(err, EngineError::InvalidInput { .. }), "{err:?}");
+        assert_eq!(
+            cast_vote(&mut state, "v", "a", true, monday()).unwrap(),
+            100.0
+        );
+        assert!(state.votes["v"].votes.contains_key("a"));
+        assert!(!state.votes["v"].votes.contains_key("ghost"));
+    }
+
+    #[test]
+    fn unstaked_tokens_stop_voting_at_once_and_return_after_unbonding() {
+        let mut state = company(&[("a", 0)]);
+        set_voting_power_policy(&mut state, VotingPowerPolicy::StakeWeighted).unwrap();
+        grant_tokens(&mut state, "a", Amount::whole(50), None, monday()).unwrap();
+        let err = stake_tokens(&mut state, "a", Amount::whole(60), monday()).unwrap_err();
+        assert!(
+            matches!(err, EngineError::InsufficientFunds { .. }),
+            "{err:?}"
+        );
+        stake_tokens(&mut state, "a", Amount::whole(30), monday()).unwrap();
+        assert_eq!(state.holders["a"].tokens, Amount::whole(20));
+        assert_eq!(voting_power(&state, "a"), 30.0);
+
+        let release_at = unstake_tokens(&mut state, "a", Amount::whole(10), monday()).unwrap();
+        assert_eq!(release_at, monday() + Duration::days(7));
+        assert_eq!(voting_power(&state, "a"), 20.0);
+        let err = unstake_tokens(&mut state, "a", Amount::whole(25), monday()).unwrap_err();
+        assert!(
+            matches!(err, EngineError::InsufficientFunds { .. }),
+            "{err:?}"
+        );
+
+        let early = claim_unbonded(&mut state, "a", release_at - Duration::seconds(1)).unwrap();
+        assert_eq!(early, Amount::ZERO);
+        assert_eq!(state.holders["a"].tokens, Amount::whole(20));
+        assert_eq!(
+            claim_unbonded(&mut state, "a", release_at).unwrap(),
+            Amount::whole(10)
+        );
+        assert_eq!(state.holders["a"].tokens, Amount::whole(30));
+        assert!(state.stakes["a"].unbonding.is_empty());
+        assert!(verify(&state).is_empty());
+    }
+
+    #[test]
+    fn stake_weight_uses_the_best_multiplier_among_roles_held() {
+        let mut state = company(&[("a", 0), ("b", 0)]);
+        set_voting_power_policy(&mut state, VotingPowerPolicy::StakeWeighted).unwrap();
+        for id in ["a", "b"] {
+            grant_tokens(&mut state, id, Amount::whole(10), None, monday()).unwrap();
+            stake_tokens(&mut state, id, Amount::whole(10), monday()).unwrap();
+        }
+        place_in_role(&mut state, RoleTier::CEO, "a", monday()).unwrap();
+        let policy = StakingPolicy {
+            unbonding_days: 7,
+            role_multipliers: BTreeMap::from([(RoleTier::Manager, 5.0), (RoleTier::CEO, 3.0)]),
+        };
+        set_staking_policy(&mut state, policy).unwrap();
+        assert_eq!(role_multiplier(&state, "a"), 3.0);
+        assert_eq!(voting_power(&state, "a"), 30.0);
+        assert_eq!(role_multiplier(&state, "b"), 1.0);
+        assert_eq!(voting_power(&state, "b"), 10.0);
+
+        let negative = StakingPolicy {
+            unbonding_days: 7,
+            role_multipliers: BTreeMap::from([(RoleTier::CEO, -1.0)]),
+        };
+        assert!(set_staking_policy(&mut state, negative).is_err());
+        assert_eq!(voting_power(&state, "a"), 30.0);
+    }
+
+    #[test]
+    fn apply_proposal_carries_out_each_kind() {
+        let mut state = company(&[("a", 0), ("b", 0)]);
+        set_tokenomics(&mut state, Amount::whole(1_000), Amount::ZERO, vec![]);
+        let apply = |state: &mut CompanyState, proposal| apply_proposal(state, &proposal, monday());
+
+        place_in_role(&mut state, RoleTier::CEO, "a", monday()).unwrap();
+        apply(
+            &mut state,
+            Proposal::RemoveFromRole {
+                target_role: RoleTier::CEO,
+                target_holder: "a".into(),
+            },
+        )
+        .unwrap();
+        assert!(!state.holders["a"].positions.contains(&RoleTier::CEO));
+        apply(
+            &mut state,
+            Proposal::AppointToRole {
+                role: RoleTier::CEO,
+                holder_id: "b".into(),
+            },
+        )
+        .unwrap();
+        assert!(state.holders["b"].positions.contains(&RoleTier::CEO));
+
+        let mut governance = state.governance_policy.clone();
+        governance.quorum = 0.9;
+        apply(
+            &mut state,
+            Proposal::SetGovernancePolicy { policy: governance },
+        )
+        .unwrap();
+        assert_eq!(state.governance_policy.quorum, 0.9);
+        let changes = GovernancePolicyPatch {
+            quorum: Some(0.6),
+            ..Default::default()
+        };
+        apply(&mut state, Proposal::PatchGovernancePolicy { changes }).unwrap();
+        assert_eq!(state.governance_policy.quorum, 0.6);
+        assert_eq!(state.governance_policy.vote_threshold, 2.0 / 3.0);
+
+        let mut emission = state.emission_policy.clone();
+        emission.weekly_payout_percent = 0.1;
+        apply(&mut state, Proposal::SetEmissionPolicy { policy: emission }).unwrap();
+        assert_eq!(state.emission_policy.weekly_payout_percent, 0.1);
+        let changes = EmissionPolicyPatch {
+            halving_interval_days: Some(30),
+            ..Default::default()
+        };
+        apply(&mut state, Proposal::PatchEmissionPolicy { changes }).unwrap();
+        assert_eq!(state.emission_policy.halving_interval_days, 30);
+        assert_eq!(state.emission_policy.weekly_payout_percent, 0.1);
+
+        let whole = Amount::whole;
+        apply(&mut state, Proposal::MintTokens { amount: whole(100) }).unwrap();
+        assert_eq!(state.treasury_tokens, whole(100));
+        let spend = |amount| Proposal::SpendTreasury {
+            to: Account::Holder("a".into()),
+            asset: Asset::Tokens,
+            amount,
+        };
+        apply(&mut state, spend(whole(40))).unwrap();
+        assert_eq!(state.holders["a"].tokens, whole(40));
+        apply(&mut state, Proposal::BurnTokens { amount: whole(20) }).unwrap();
+        assert_eq!(state.treasury_tokens, whole(40));
+        assert_eq!(remaining_supply(&state), Some(whole(900)));
+        let err = apply(&mut state, spend(whole(41))).unwrap_err();
+        assert!(
+            matches!(err, EngineError::InsufficientFunds { .. }),
+            "{err:?}"
+        );
+
+        let allocations = vec![TokenAllocation {
+            name: "team".into(),
+            percent: 50.0,
+        }];
+        apply(&mut state, Proposal::AmendAllocations { allocations }).unwrap();
+        assert_eq!(
+            state.tokenomics.as_ref().unwrap().allocations[0].name,
+            "team"
+        );
+        assert!(verify(&state).is_empty());
+    }
+
+    #[test]
+    fn passed_vote_that_cannot_take_effect_closes_with_the_reason() {
+        let mut state = open_vote(false);
+        let proposal = Proposal::SpendTreasury {
+            to: Account::Holder("a".into()),
+            asset: Asset::Tokens,
+            amount: Amount::whole(10),
+        };
+        let terms = vote_terms(&state, monday());
+        create_vote(&mut state, "spend", proposal, "", Some(terms), monday());
+        for voter in ["a", "b"] {
+            cast_vote(&mut state, "spend", voter, true, monday()).unwrap();
+        }
+
+        assert!(resolve_vote_if_passed(&mut state, "spend", monday()).unwrap());
+        let record = &state.votes["spend"];
+        assert!(record.resolved);
+        assert_eq!(record.outcome, Some(VoteOutcome::Passed));
+        assert!(record.effect_error.is_some());
+        assert_eq!(state.holders["a"].tokens, Amount::ZERO);
+    }
+
+    #[test]
+    fn delegated_power_follows_the_delegate_until_the_delegator_votes() {
+        let mut state = open_vote(true);
+        delegate_votes(&mut state, "b", "a", None, monday()).unwrap();
+        delegate_votes(
+            &mut state,
+            "c",
+            "b",
+            Some(ProposalKind::MintTokens),
+            monday(),
+        )
+        .unwrap();
+        cast_vote(&mut state, "v", "a", true, monday()).unwrap();
+        let tally = tally_vote(&state, &state.votes["v"]);
+        assert_eq!(tally.eligible, 3.0);
+        assert_eq!((tally.cast, tally.approve), (3.0, 3.0));
+        assert_eq!((tally.delegated, tally.delegated_approve), (2.0, 2.0));
+
+        cast_vote(&mut state, "v", "b", false, monday()).unwrap();
+        let tally = tally_vote(&state, &state.votes["v"]);
+        assert_eq!((tally.cast, tally.approve), (3.0, 1.0));
+        assert_eq!((tally.delegated, tally.delegated_approve), (1.0, 0.0));
+    }
+
+    #[test]
+    fn delegations_may_not_loop() {
+        let mut state = company(&[("a", 0), ("b", 0), ("c", 0)]);
+        delegate_votes(&mut state, "a", "b", None, monday()).unwrap();
+        let err = delegate_votes(&mut state, "b", "a", None, monday()).unwrap_err();
+        assert!(
+            matches!(&err, EngineError::DelegationCycle { chain } if chain == &["b", "a", "b"]),
+            "{err:?}"
+        );
+        assert_eq!(state.delegations.len(), 1);
+
+        // Dropping a's minting delegation hands minting votes to a's general
+        // delegate b, who delegates them back to a.
+        let mint = Some(ProposalKind::MintTokens);
+        let mut state = company(&[("a", 0), ("b", 0), ("c", 0)]);
+        delegate_votes(&mut state, "a", "c", mint, monday()).unwrap();
+        delegate_votes(&mut state, "a", "b", None, monday()).unwrap();
+        delegate_votes(&mut state, "b", "a", mint, monday()).unwrap();
+        let err = revoke_delegation(&mut state, "a", mint).unwrap_err();
+        assert!(
+            matches!(&err, EngineError::DelegationCycle { chain } if chain == &["a", "b", "a"]),
+            "{err:?}"
+        );
+        assert_eq!(
+            delegate_of(&state, "a", ProposalKind::MintTokens),
+            Some("c")
+        );
+        revoke_delegation(&mut state, "b", mint).unwrap();
+        revoke_delegation(&mut state, "a", mint).unwrap();
+        assert_eq!(
+            delegate_of(&state, "a", ProposalKind::MintTokens),
+            Some("b")
+        );
+    }
+
+    #[test]
+    fn only_the_board_may_vote_to_remove_the_ceo() {
+        let mut state = company(&[("ceo", 0), ("board", 0), ("staff", 0)]);
+        set_voting_power_policy(&mut state, VotingPowerPolicy::OneHolderOneVote).unwrap();
+        place_in_role(&mut state, RoleTier::CEO, "ceo", monday()).unwrap();
+        place_in_role(&mut state, RoleTier::BoardSeat, "board", monday()).unwrap();
+        state.governance_policy.eligibility.push(EligibilityRule {
+            kind: ProposalKind::RemoveFromRole,
+            target_role: Some(RoleTier::CEO),
+            min_role: RoleTier::BoardSeat,
+        });
+        let removal = |role| Proposal::RemoveFromRole {
+            target_role: role,
+            target_holder: "ceo".into(),
+        };
+        assert!(may_vote(&state, "board", &removal(RoleTier::CEO)));
+        assert!(!may_vote(&state, "ceo", &removal(RoleTier::CEO)));
+        assert!(!may_vote(&state, "staff", &removal(RoleTier::CEO)));
+        assert!(may_vote(&state, "staff", &removal(RoleTier::Manager)));
+        assert!(!may_vote(&state, "ghost", &removal(RoleTier::Manager)));
+
+        let terms = vote_terms(&state, monday());
+        propose(
+            &mut state,
+            "oust",
+            removal(RoleTier::CEO),
+            "",
+            terms,
+            monday(),
+        )
+        .unwrap();
+        let err = cast_vote(&mut state, "oust", "staff", true, monday()).unwrap_err();
+        assert!(
+            matches!(&err, EngineError::NotEligibleToVote { voter_id, min_role, .. }
+                if voter_id == "staff" && *min_role == RoleTier::BoardSeat),
+            "{err:?}"
+        );
+        cast_vote(&mut state, "oust", "board", true, monday()).unwrap();
+        assert_eq!(tally_vote(&state, &state.votes["oust"]).eligible, 1.0);
+    }
+
+    #[test]
+    fn value_drop_opens_removal_votes_for_accountable_roles_once_per_cooldown() {
+        let mut state = company(&[("ceo", 0), ("president", 0)]);
+        place_in_role(&mut state, RoleTier::CEO, "ceo", monday()).unwrap();
+        place_in_role(&mut state, RoleTier::President, "president", monday()).unwrap();
+        state.governance_policy.accountable_roles = vec![RoleTier::CEO];
+        record_token_price(&mut state, 10.0, monday() - Duration::days(10));
+        let monitor = |state: &mut CompanyState, price, days| {
+            let at = monday() + Duration::days(days);
+            monitor_value_drop(state, price, at, at)
+        };
+
+        assert!(monitor(&mut state, 9.0, 0).is_empty());
+        assert!(state.value_drops.is_empty());
+
+        let opened = monitor(&mut state, 7.0, 0);
+        assert_eq!(opened.len(), 1);
+        assert_eq!(
+            state.votes[&opened[0]].proposal,
+            Proposal::RemoveFromRole {
+                target_role: RoleTier::CEO,
+                target_holder: "ceo".into(),
+            }
+        );
+
+        assert!(monitor(&mut state, 6.0, 6).is_empty());
+        assert_eq!(state.value_drops.len(), 1);
+
+        state.governance_policy.accountable_roles = vec![RoleTier::CEO, RoleTier::President];
+        let opened = monitor(&mut state, 6.0, 8);
+        assert_eq!(opened.len(), 1);
+        assert_eq!(
+            state.votes[&opened[0]].proposal.removal_target(),
+            Some("president")
+        );
+        assert_eq!(state.value_drops.len(), 2);
+        assert_eq!(state.value_drops[1].vote_ids, opened);
+        assert_eq!(state.votes.len(), 2);
+    }
+}
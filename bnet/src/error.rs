@@ -0,0 +1,605 @@
+use crate::amount::Amount;
+use crate::model::{Asset, RoleTier};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityKind {
+    Holder,
+    Listing,
+    Vote,
+    Task,
+    PayoutRun,
+    Auction,
+}
+
+impl fmt::Display for EntityKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            EntityKind::Holder => "holder",
+            EntityKind::Listing => "listing",
+            EntityKind::Vote => "vote",
+            EntityKind::Task => "task",
+            EntityKind::PayoutRun => "payout run",
+            EntityKind::Auction => "auction",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "guardrail", rename_all = "snake_case")]
+pub enum Guardrail {
+    LocLimitExceeded { limit: usize, actual: usize },
+    TestsRequired,
+    DefinitionOfDoneRequired,
+    DeliverableRequired,
+}
+
+impl fmt::Display for Guardrail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Guardrail::LocLimitExceeded { limit, actual } => write!(
+                f,
+                "code bloat guardrail: {} LOC changed exceeds limit of {}",
+                actual, limit
+            ),
+            Guardrail::TestsRequired => write!(f, "tests required before finalization"),
+            Guardrail::DefinitionOfDoneRequired => write!(f, "definition of done required"),
+            Guardrail::DeliverableRequired => write!(f, "at least one deliverable required"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "restriction", rename_all = "snake_case")]
+pub enum TransferRestriction {
+    LockedUp {
+        locked: Amount,
+        transferable: Amount,
+    },
+    DailyLimitExceeded {
+        limit: Amount,
+        already_sent: Amount,
+    },
+    RemovalVoteOpen {
+        vote_id: String,
+    },
+}
+
+impl fmt::Display for TransferRestriction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransferRestriction::LockedUp {
+                locked,
+                transferable,
+            } => write!(
+                f,
+                "{} tokens are still locked up; {} transferable",
+                locked, transferable
+            ),
+            TransferRestriction::DailyLimitExceeded {
+                limit,
+                already_sent,
+            } => write!(
+                f,
+                "daily limit of {} exceeded ({} already sent today)",
+                limit, already_sent
+            ),
+            TransferRestriction::RemovalVoteOpen { vote_id } => {
+                write!(f, "transfers frozen while removal vote {} is open", vote_id)
+            }
+        }
+    }
+}
+
+/// One promotion requirement a holder does not meet yet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "requirement", rename_all = "snake_case")]
+pub enum PromotionGap {
+    RoleRequired {
+        role: RoleTier,
+    },
+    TenureTooShort {
+        role: RoleTier,
+        days_held: i64,
+        days_required: i64,
+    },
+    TooFewCompletedTasks {
+        completed: usize,
+        required: usize,
+    },
+    StakeTooLow {
+        staked: Amount,
+        required: Amount,
+    },
+}
+
+impl fmt::Display for PromotionGap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PromotionGap::RoleRequired { role } => write!(f, "must hold {} first", role),
+            PromotionGap::TenureTooShort {
+                role,
+                days_held,
+                days_required,
+            } => write!(
+                f,
+                "must hold {} for {} days ({} so far)",
+                role, days_required, days_held
+            ),
+            PromotionGap::TooFewCompletedTasks {
+                completed,
+                required,
+            } => write!(f, "must complete {} tasks ({} so far)", required, completed),
+            PromotionGap::StakeTooLow { staked, required } => {
+                write!(f, "must stake {} tokens ({} staked)", required, staked)
+            }
+        }
+    }
+}
+
+/// A broken invariant found by [`crate::engine::verify`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "violation", rename_all = "snake_case")]
+pub enum Violation {
+    /// A role position names a holder that does not exist.
+    UnknownPositionHolder {
+        role: RoleTier,
+        holder_id: String,
+    },
+    /// A holder's role list and the role positions they occupy disagree.
+    RoleCountMismatch {
+        holder_id: String,
+        role: RoleTier,
+        listed: usize,
+        occupied: usize,
+    },
+    /// Fewer employees on record than holders with the employee role.
+    EmployeeCountTooLow {
+        recorded: usize,
+        holders: usize,
+    },
+    SupplyCapExceeded {
+        cap: Amount,
+        minted: Amount,
+    },
+    NegativeBalance {
+        account: String,
+        asset: Asset,
+        balance: Amount,
+    },
+    UnbalancedJournalEntry {
+        entry_id: u64,
+        off_by: Amount,
+    },
+    /// A cached balance disagrees with the journal.
+    LedgerMismatch {
+        account: String,
+        asset: Asset,
+        cached: Amount,
+        journal: Amount,
+    },
+    /// The vesting pool does not hold exactly the schedules' unvested tokens.
+    VestingPoolMismatch {
+        pool: Amount,
+        schedules: Amount,
+    },
+    /// The staking pool does not hold exactly the staked and unbonding tokens.
+    StakingPoolMismatch {
+        pool: Amount,
+        stakes: Amount,
+    },
+    /// The escrow account does not hold exactly the running auctions' bids.
+    EscrowMismatch {
+        pool: Amount,
+        bids: Amount,
+    },
+    /// An active listing whose seller no longer holds the role for sale.
+    StaleListing {
+        listing_id: String,
+        seller_id: String,
+        role: RoleTier,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::UnknownPositionHolder { role, holder_id } => {
+                write!(f, "{} position held by unknown holder {}", role, holder_id)
+            }
+            Violation::RoleCountMismatch {
+                holder_id,
+                role,
+                listed,
+                occupied,
+            } => write!(
+                f,
+                "{} lists role {} {} time(s) but occupies {} position(s)",
+                holder_id, role, listed, occupied
+            ),
+            Violation::EmployeeCountTooLow { recorded, holders } => write!(
+                f,
+                "employee count {} is below the {} holders with the employee role",
+                recorded, holders
+            ),
+            Violation::SupplyCapExceeded { cap, minted } => {
+                write!(f, "minted supply {} exceeds cap {}", minted, cap)
+            }
+            Violation::NegativeBalance {
+                account,
+                asset,
+                balance,
+            } => write!(f, "{} has negative {} balance {}", account, asset, balance),
+            Violation::UnbalancedJournalEntry { entry_id, off_by } => {
+                write!(f, "journal entry #{} is off by {}", entry_id, off_by)
+            }
+            Violation::LedgerMismatch {
+                account,
+                asset,
+                cached,
+                journal,
+            } => write!(
+                f,
+                "{} {} balance is {} but the journal says {}",
+                account, asset, cached, journal
+            ),
+            Violation::VestingPoolMismatch { pool, schedules } => write!(
+                f,
+                "vesting pool holds {} but schedules have {} unvested",
+                pool, schedules
+            ),
+            Violation::StakingPoolMismatch { pool, stakes } => write!(
+                f,
+                "staking pool holds {} but stakes add up to {}",
+                pool, stakes
+            ),
+            Violation::EscrowMismatch { pool, bids } => write!(
+                f,
+                "escrow holds {} but running auctions have {} in bids",
+                pool, bids
+            ),
+            Violation::StaleListing {
+                listing_id,
+                seller_id,
+                role,
+            } => write!(
+                f,
+                "active listing {} sells {} which {} no longer holds",
+                listing_id, role, seller_id
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub enum EngineError {
+    NotFound {
+        kind: EntityKind,
+        id: String,
+    },
+    AlreadyExists {
+        kind: EntityKind,
+        id: String,
+    },
+    InsufficientFunds {
+        account: String,
+        asset: Asset,
+        needed: Amount,
+        available: Amount,
+    },
+    NoAvailablePosition {
+        role: RoleTier,
+    },
+    SupplyCapExceeded {
+        requested: Amount,
+        available: Amount,
+    },
+    WeekAlreadyEmitted {
+        week: String,
+    },
+    TransferRestricted {
+        holder_id: String,
+        restriction: TransferRestriction,
+    },
+    RoleNotHeld {
+        holder_id: String,
+        role: RoleTier,
+    },
+    AlreadyResolved {
+        vote_id: String,
+    },
+    BallotAlreadyCast {
+        vote_id: String,
+        voter_id: String,
+    },
+    VotingClosed {
+        vote_id: String,
+    },
+    NotEligibleToVote {
+        vote_id: String,
+        voter_id: String,
+        min_role: RoleTier,
+    },
+    AuctionClosed {
+        auction_id: String,
+    },
+    BidTooLow {
+        auction_id: String,
+        minimum: Amount,
+    },
+    PromotionBlocked {
+        holder_id: String,
+        role: RoleTier,
+        missing: Vec<PromotionGap>,
+    },
+    /// Delegating would route a holder's vote back to themselves.
+    DelegationCycle {
+        chain: Vec<String>,
+    },
+    GuardrailViolation {
+        task_id: String,
+        guardrail: Guardrail,
+    },
+    InvalidInput {
+        reason: String,
+    },
+    /// An amount calculation left the representable range.
+    Overflow,
+}
+
+impl EngineError {
+    pub fn not_found(kind: EntityKind, id: &str) -> Self {
+        EngineError::NotFound {
+            kind,
+            id: id.to_string(),
+        }
+    }
+
+    pub fn invalid(reason: impl Into<String>) -> Self {
+        EngineError::InvalidInput {
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::NotFound { kind, id } => write!(f, "{} not found: {}", kind, id),
+            EngineError::AlreadyExists { kind, id } => {
+                write!(f, "{} already exists: {}", kind, id)
+            }
+            EngineError::InsufficientFunds {
+                account,
+                asset,
+                needed,
+                available,
+            } => write!(
+                f,
+                "insufficient {} in {}: needed {}, available {}",
+                asset, account, needed, available
+            ),
+            EngineError::NoAvailablePosition { role } => {
+                write!(f, "no available position for role {}", role)
+            }
+            EngineError::SupplyCapExceeded {
+                requested,
+                available,
+            } => write!(
+                f,
+                "minting {} tokens would exceed the supply cap ({} left)",
+                requested, available
+            ),
+            EngineError::WeekAlreadyEmitted { week } => {
+                write!(f, "emission already run for week {}", week)
+            }
+            EngineError::TransferRestricted {
+                holder_id,
+                restriction,
+            } => write!(f, "transfer from {} refused: {}", holder_id, restriction),
+            EngineError::RoleNotHeld { holder_id, role } => {
+                write!(f, "{} does not hold role {}", holder_id, role)
+            }
+            EngineError::AlreadyResolved { vote_id } => {
+                write!(f, "vote already resolved: {}", vote_id)
+            }
+            EngineError::BallotAlreadyCast { vote_id, voter_id } => {
+                write!(f, "{} already voted on {}", voter_id, vote_id)
+            }
+            EngineError::VotingClosed { vote_id } => {
+                write!(f, "voting has closed on {}", vote_id)
+            }
+            EngineError::NotEligibleToVote {
+                vote_id,
+                voter_id,
+                min_role,
+            } => write!(
+                f,
+                "{} may not vote on {}: requires {} or above",
+                voter_id, vote_id, min_role
+            ),
+            EngineError::AuctionClosed { auction_id } => {
+                write!(f, "bidding has closed on auction {}", auction_id)
+            }
+            EngineError::BidTooLow {
+                auction_id,
+                minimum,
+            } => write!(
+                f,
+                "bid on auction {} must be at least {}",
+                auction_id, minimum
+            ),
+            EngineError::PromotionBlocked {
+                holder_id,
+                role,
+                missing,
+            } => {
+                write!(f, "{} is not eligible for {}: ", holder_id, role)?;
+                for (i, gap) in missing.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", gap)?;
+                }
+                Ok(())
+            }
+            EngineError::DelegationCycle { chain } => {
+                write!(f, "delegation cycle: {}", chain.join(" -> "))
+            }
+            EngineError::GuardrailViolation { task_id, guardrail } => {
+                write!(f, "task {}: {}", task_id, guardrail)
+            }
+            EngineError::InvalidInput { reason } => write!(f, "invalid input: {}", reason),
+            EngineError::Overflow => write!(f, "amount overflow"),
+        }
+    }
+}
+
+impl Error for EngineError {}
+
+#[derive(Debug)]
+pub enum StoreError {
+    Io(io::Error),
+    Sqlite(rusqlite::Error),
+    Serde(serde_json::Error),
+    InvalidSpec(String),
+    Locked {
+        path: PathBuf,
+    },
+    Engine(EngineError),
+    /// The state a command produced failed verification and was not saved.
+    Invariant(Vec<Violation>),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Io(e) => write!(f, "storage io error: {}", e),
+            StoreError::Sqlite(e) => write!(f, "sqlite error: {}", e),
+            StoreError::Serde(e) => write!(f, "state encoding error: {}", e),
+            StoreError::InvalidSpec(spec) => write!(
+                f,
+                "invalid store spec {:?} (expected json:<path> or sqlite:<path>)",
+                spec
+            ),
+            StoreError::Locked { path } => write!(
+                f,
+                "state at {} is locked by another bnet process; retry once it finishes",
+                path.display()
+            ),
+            StoreError::Engine(e) => write!(f, "{}", e),
+            StoreError::Invariant(violations) => {
+                write!(f, "refusing to save a state with broken invariants: ")?;
+                for (i, v) in violations.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for StoreError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StoreError::Io(e) => Some(e),
+            StoreError::Sqlite(e) => Some(e),
+            StoreError::Serde(e) => Some(e),
+            StoreError::InvalidSpec(_) | StoreError::Locked { .. } | StoreError::Invariant(_) => {
+                None
+            }
+            StoreError::Engine(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for StoreError {
+    fn from(e: io::Error) -> Self {
+        StoreError::Io(e)
+    }
+}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(e: rusqlite::Error) -> Self {
+        StoreError::Sqlite(e)
+    }
+}
+
+impl From<serde_json::Error> for StoreError {
+    fn from(e: serde_json::Error) -> Self {
+        StoreError::Serde(e)
+    }
+}
+
+impl From<EngineError> for StoreError {
+    fn from(e: EngineError) -> Self {
+        StoreError::Engine(e)
+    }
+}
+
+/// Why a price feed could not produce its next tick.
+#[derive(Debug)]
+pub enum FeedError {
+    Io(io::Error),
+    InvalidSource(String),
+    /// A tick that does not parse; `line` is set for line-based sources.
+    InvalidTick {
+        line: Option<usize>,
+        reason: String,
+    },
+    /// A polled endpoint failed this time round; the next poll may succeed.
+    Unavailable(String),
+}
+
+impl FeedError {
+    /// Whether the feed may be polled again after this error.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, FeedError::Unavailable(_))
+    }
+}
+
+impl fmt::Display for FeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeedError::Io(e) => write!(f, "price feed io error: {}", e),
+            FeedError::InvalidSource(spec) => write!(
+                f,
+                "invalid price source {:?} (expected file:<path>, stdin or http://<host>[:<port>]/<path>)",
+                spec
+            ),
+            FeedError::InvalidTick {
+                line: Some(line),
+                reason,
+            } => write!(f, "invalid tick on line {}: {}", line, reason),
+            FeedError::InvalidTick { line: None, reason } => {
+                write!(f, "invalid tick: {}", reason)
+            }
+            FeedError::Unavailable(reason) => write!(f, "price source unavailable: {}", reason),
+        }
+    }
+}
+
+impl Error for FeedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FeedError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FeedError {
+    fn from(e: io::Error) -> Self {
+        FeedError::Io(e)
+    }
+}
@@ -0,0 +1,528 @@
+use crate::amount::Amount;
+use crate::engine::*;
+use crate::error::EngineError;
+use crate::ledger::{backfill_token_receipts, open_balances};
+use crate::model::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Every accepted mutation of `CompanyState`. Replaying the log through
+/// [`apply_event`] in order reproduces the state exactly, so anything that
+/// depends on time reads it from the event rather than the clock.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CompanyEvent {
+    Initialized {
+        employees: usize,
+    },
+    /// Baseline for states that predate the event log.
+    Imported {
+        state: Box<CompanyState>,
+    },
+    HolderAdded {
+        id: String,
+        name: String,
+        cash: Amount,
+    },
+    OnboardingPolicyUpdated {
+        early_joiner_limit: Option<usize>,
+        early_joiner_reward: Option<Amount>,
+        #[serde(default)]
+        early_joiner_vesting: Option<VestingTerms>,
+    },
+    HolderOnboarded {
+        id: String,
+        name: String,
+        cash: Amount,
+    },
+    RevenueIngested {
+        revenue: RevenueEvent,
+    },
+    /// Emission for a hand-entered revenue total, as logged before revenue
+    /// events were kept; replay only.
+    WeeklyEmissionRun {
+        week_revenue: Amount,
+        as_of: DateTime<Utc>,
+    },
+    /// Emission for the ISO week containing `as_of`, which might not have
+    /// ended yet; replay only.
+    WeekEmitted {
+        as_of: DateTime<Utc>,
+    },
+    CompletedWeekEmitted {
+        week: String,
+    },
+    PayoutRunCompleted {
+        week: String,
+        source: PayoutSource,
+    },
+    PayoutRunReversed {
+        week: String,
+    },
+    TokensDistributed {
+        allocations: Vec<WorkAllocation>,
+        total_tokens: Amount,
+    },
+    TokensGranted {
+        holder_id: String,
+        amount: Amount,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        vesting: Option<VestingTerms>,
+    },
+    TokensTransferred {
+        from: String,
+        to: String,
+        amount: Amount,
+        memo: Option<String>,
+    },
+    TransferPolicySet {
+        policy: TransferPolicy,
+    },
+    RoleVestingSet {
+        role: RoleTier,
+        terms: Option<VestingTerms>,
+    },
+    VestingAccrued {
+        as_of: DateTime<Utc>,
+    },
+    PromotionBidPlaced {
+        bid: PromotionBid,
+    },
+    PositionListed {
+        listing_id: String,
+        seller_id: String,
+        role: RoleTier,
+        price: Amount,
+    },
+    ListingBought {
+        listing_id: String,
+        buyer_id: String,
+    },
+    AuctionOpened {
+        auction_id: String,
+        role: RoleTier,
+        format: AuctionFormat,
+        pricing: AuctionPricing,
+        reserve_price: Amount,
+        ends_at: DateTime<Utc>,
+    },
+    AuctionBidPlaced {
+        auction_id: String,
+        bidder_id: String,
+        amount: Amount,
+    },
+    AuctionSettled {
+        auction_id: String,
+    },
+    VoteCreated {
+        vote_id: String,
+        target_role: RoleTier,
+        target_holder: String,
+        reason: String,
+        /// Absent in logs written before votes had deadlines.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        terms: Option<VoteTerms>,
+    },
+    ProposalCreated {
+        vote_id: String,
+        proposal: Proposal,
+        reason: String,
+        terms: VoteTerms,
+    },
+    /// A ballot with a caller-supplied weight, as logged before weights
+    /// were computed; replay only.
+    VoteCast {
+        vote_id: String,
+        vote: Vote,
+    },
+    BallotCast {
+        vote_id: String,
+        voter_id: String,
+        approve: bool,
+    },
+    TokensStaked {
+        holder_id: String,
+        amount: Amount,
+    },
+    TokensUnstaked {
+        holder_id: String,
+        amount: Amount,
+    },
+    UnbondedClaimed {
+        holder_id: String,
+    },
+    StakingPolicySet {
+        policy: StakingPolicy,
+    },
+    PromotionPolicySet {
+        policy: PromotionPolicy,
+    },
+    VotingPowerPolicySet {
+        policy: VotingPowerPolicy,
+    },
+    GovernancePolicySet {
+        policy: GovernancePolicy,
+    },
+    VotesDelegated {
+        delegator_id: String,
+        delegate_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        kind: Option<ProposalKind>,
+    },
+    DelegationRevoked {
+        delegator_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        kind: Option<ProposalKind>,
+    },
+    VoteResolutionChecked {
+        vote_id: String,
+    },
+    ExpiredVotesClosed,
+    TokenPriceRecorded {
+        price: f64,
+        /// When a feed observed the price, if not when it was recorded.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        observed_at: Option<DateTime<Utc>>,
+    },
+    /// A value-drop vote against a hand-picked holder, as logged before
+    /// drops were monitored; replay only.
+    ValueDropChecked {
+        vote_id: String,
+        target_role: RoleTier,
+        target_holder: String,
+        current_price: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        terms: Option<VoteTerms>,
+    },
+    ValueDropMonitored {
+        current_price: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        observed_at: Option<DateTime<Utc>>,
+    },
+    TaskCreated {
+        id: String,
+        title: String,
+        summary: String,
+        definition_of_done: Vec<String>,
+        deliverables: Vec<Deliverable>,
+        max_total_loc: usize,
+    },
+    TaskReady {
+        id: String,
+    },
+    TaskAssigned {
+        id: String,
+        assignee_id: String,
+        role: String,
+    },
+    TaskSubmittedForReview {
+        id: String,
+    },
+    TaskFinalized {
+        id: String,
+        loc_changed: usize,
+        tests_run: bool,
+    },
+    RolesSeeded {
+        holder_id: String,
+        name: String,
+    },
+    TokenomicsSet {
+        total_supply_cap: Amount,
+        minted_supply: Amount,
+        allocations: Vec<TokenAllocation>,
+    },
+}
+
+impl CompanyEvent {
+    /// Events that create a state from nothing rather than modifying one.
+    pub fn starts_history(&self) -> bool {
+        matches!(
+            self,
+            CompanyEvent::Initialized { .. } | CompanyEvent::Imported { .. }
+        )
+    }
+
+    /// The serialized `type` tag, e.g. `vote_cast`.
+    pub fn kind(&self) -> String {
+        serde_json::to_value(self)
+            .ok()
+            .and_then(|v| v.get("type").and_then(|t| t.as_str()).map(str::to_string))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub seq: u64,
+    pub at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    pub event: CompanyEvent,
+}
+
+pub fn apply_event(
+    state: &mut CompanyState,
+    event: &CompanyEvent,
+    at: DateTime<Utc>,
+) -> Result<(), EngineError> {
+    match event {
+        CompanyEvent::Initialized { employees } => {
+            *state = new_company(*employees, at);
+        }
+        CompanyEvent::Imported { state: snapshot } => {
+            *state = (**snapshot).clone();
+            open_balances(state, at);
+            backfill_employee_since(state);
+            backfill_token_receipts(state);
+        }
+        CompanyEvent::HolderAdded { id, name, cash } => {
+            onboard_holder(state, id, name, *cash, at)?;
+            ensure_positions(state);
+        }
+        CompanyEvent::OnboardingPolicyUpdated {
+            early_joiner_limit,
+            early_joiner_reward,
+            early_joiner_vesting,
+        } => set_onboarding_policy(
+            state,
+            *early_joiner_limit,
+            *early_joiner_reward,
+            *early_joiner_vesting,
+        )?,
+        CompanyEvent::HolderOnboarded { id, name, cash } => {
+            auto_onboard(state, id, name, *cash, at)?;
+        }
+        CompanyEvent::RevenueIngested { revenue } => {
+            ingest_revenue(state, revenue)?;
+        }
+        CompanyEvent::WeeklyEmissionRun {
+            week_revenue,
+            as_of,
+        } => {
+            run_weekly_emission(state, *week_revenue, *as_of)?;
+        }
+        CompanyEvent::WeekEmitted { as_of } => {
+            emit_week(state, *as_of)?;
+        }
+        CompanyEvent::CompletedWeekEmitted { week } => {
+            emit_completed_week(state, week, at)?;
+        }
+        CompanyEvent::PayoutRunCompleted { week, source } => {
+            run_payout(state, week, source, at)?;
+        }
+        CompanyEvent::PayoutRunReversed { week } => reverse_payout(state, week, at)?,
+        CompanyEvent::TokensDistributed {
+            allocations,
+            total_tokens,
+        } => {
+            distribute_tokens(state, allocations, *total_tokens, at)?;
+        }
+        CompanyEvent::TokensGranted {
+            holder_id,
+            amount,
+            vesting,
+        } => grant_tokens(state, holder_id, *amount, *vesting, at)?,
+        CompanyEvent::TokensTransferred {
+            from,
+            to,
+            amount,
+            memo,
+        } => transfer_tokens(state, from, to, *amount, memo.as_deref(), at)?,
+        CompanyEvent::TransferPolicySet { policy } => set_transfer_policy(state, policy.clone())?,
+        CompanyEvent::RoleVestingSet { role, terms } => set_role_vesting(state, *role, *terms)?,
+        CompanyEvent::VestingAccrued { as_of } => {
+            accrue_vesting(state, *as_of)?;
+        }
+        CompanyEvent::PromotionBidPlaced { bid } => apply_promotion_bid(state, bid.clone())?,
+        CompanyEvent::PositionListed {
+            listing_id,
+            seller_id,
+            role,
+            price,
+        } => create_listing(state, listing_id, seller_id, *role, *price, at)?,
+        CompanyEvent::ListingBought {
+            listing_id,
+            buyer_id,
+        } => buy_listing(state, listing_id, buyer_id, at)?,
+        CompanyEvent::AuctionOpened {
+            auction_id,
+            role,
+            format,
+            pricing,
+            reserve_price,
+            ends_at,
+        } => open_auction(
+            state,
+            auction_id,
+            *role,
+            *format,
+            *pricing,
+            *reserve_price,
+            *ends_at,
+            at,
+        )?,
+        CompanyEvent::AuctionBidPlaced {
+            auction_id,
+            bidder_id,
+            amount,
+        } => place_auction_bid(state, auction_id, bidder_id, *amount, at)?,
+        CompanyEvent::AuctionSettled { auction_id } => {
+            settle_auction(state, auction_id, at)?;
+        }
+        CompanyEvent::VoteCreated {
+            vote_id,
+            target_role,
+            target_holder,
+            reason,
+            terms,
+        } => {
+            let proposal = Proposal::RemoveFromRole {
+                target_role: *target_role,
+                target_holder: target_holder.clone(),
+            };
+            create_vote(state, vote_id, proposal, reason, *terms, at)
+        }
+        CompanyEvent::ProposalCreated {
+            vote_id,
+            proposal,
+            reason,
+            terms,
+        } => propose(state, vote_id, proposal.clone(), reason, *terms, at)?,
+        CompanyEvent::VoteCast { vote_id, vote } => {
+            cast_weighted_vote(state, vote_id, vote.clone(), at)?;
+        }
+        CompanyEvent::BallotCast {
+            vote_id,
+            voter_id,
+            approve,
+        } => {
+            cast_vote(state, vote_id, voter_id, *approve, at)?;
+        }
+        CompanyEvent::TokensStaked { holder_id, amount } => {
+            stake_tokens(state, holder_id, *amount, at)?
+        }
+        CompanyEvent::TokensUnstaked { holder_id, amount } => {
+            unstake_tokens(state, holder_id, *amount, at)?;
+        }
+        CompanyEvent::UnbondedClaimed { holder_id } => {
+            claim_unbonded(state, holder_id, at)?;
+        }
+        CompanyEvent::StakingPolicySet { policy } => set_staking_policy(state, policy.clone())?,
+        CompanyEvent::PromotionPolicySet { policy } => set_promotion_policy(state, policy.clone())?,
+        CompanyEvent::VotingPowerPolicySet { policy } => {
+            set_voting_power_policy(state, policy.clone())?
+        }
+        CompanyEvent::GovernancePolicySet { policy } => {
+            set_governance_policy(state, policy.clone())?
+        }
+        CompanyEvent::VotesDelegated {
+            delegator_id,
+            delegate_id,
+            kind,
+        } => delegate_votes(state, delegator_id, delegate_id, *kind, at)?,
+        CompanyEvent::DelegationRevoked { delegator_id, kind } => {
+            revoke_delegation(state, delegator_id, *kind)?
+        }
+        CompanyEvent::VoteResolutionChecked { vote_id } => {
+            resolve_vote_if_passed(state, vote_id, at)?;
+        }
+        CompanyEvent::ExpiredVotesClosed => {
+            close_expired_votes(state, at)?;
+        }
+        CompanyEvent::TokenPriceRecorded { price, observed_at } => {
+            record_token_price(state, *price, observed_at.unwrap_or(at))
+        }
+        CompanyEvent::ValueDropChecked {
+            vote_id,
+            target_role,
+            target_holder,
+            current_price,
+            terms,
+        } => {
+            auto_trigger_value_drop_vote(
+                state,
+                *current_price,
+                at,
+                vote_id,
+                *target_role,
+                target_holder,
+                *terms,
+            );
+        }
+        CompanyEvent::ValueDropMonitored {
+            current_price,
+            observed_at,
+        } => {
+            monitor_value_drop(state, *current_price, observed_at.unwrap_or(at), at);
+        }
+        CompanyEvent::TaskCreated {
+            id,
+            title,
+            summary,
+            definition_of_done,
+            deliverables,
+            max_total_loc,
+        } => pm_create_task(
+            state,
+            id,
+            title,
+            summary,
+            definition_of_done.clone(),
+            deliverables.clone(),
+            *max_total_loc,
+            at,
+        ),
+        CompanyEvent::TaskReady { id } => pm_ready_task(state, id, at)?,
+        CompanyEvent::TaskAssigned {
+            id,
+            assignee_id,
+            role,
+        } => pm_assign_task(state, id, assignee_id, role, at)?,
+        CompanyEvent::TaskSubmittedForReview { id } => pm_submit_for_review(state, id, at)?,
+        CompanyEvent::TaskFinalized {
+            id,
+            loc_changed,
+            tests_run,
+        } => pm_finalize_task(state, id, *loc_changed, *tests_run, at)?,
+        CompanyEvent::RolesSeeded { holder_id, name } => seed_roles(state, holder_id, name, at)?,
+        CompanyEvent::TokenomicsSet {
+            total_supply_cap,
+            minted_supply,
+            allocations,
+        } => set_tokenomics(
+            state,
+            *total_supply_cap,
+            *minted_supply,
+            allocations.clone(),
+        ),
+    }
+    Ok(())
+}
+
+/// Applies a logged event and advances `state.event_seq` past it.
+pub fn apply_recorded(
+    state: &mut CompanyState,
+    recorded: &RecordedEvent,
+) -> Result<(), EngineError> {
+    apply_event(state, &recorded.event, recorded.at)?;
+    state.event_seq = recorded.seq;
+    Ok(())
+}
+
+/// Rebuilds a state by folding `events` from the beginning of history.
+pub fn replay(events: &[RecordedEvent]) -> Result<CompanyState, EngineError> {
+    let first = events
+        .first()
+        .ok_or_else(|| EngineError::invalid("event log is empty"))?;
+    if !first.event.starts_history() {
+        return Err(EngineError::invalid(
+            "event log must start with an initialized or imported event",
+        ));
+    }
+    let mut state = new_company(0, first.at);
+    for recorded in events {
+        apply_recorded(&mut state, recorded)?;
+    }
+    Ok(state)
+}
@@ -0,0 +1,168 @@
+use crate::amount::Amount;
+use crate::error::EngineError;
+use crate::events::{RecordedEvent, apply_recorded, replay};
+use crate::model::{CompanyState, Holder, RoleTier};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Folds the events recorded at or before `until`; `None` if the company
+/// did not exist yet at that moment.
+pub fn state_at(
+    events: &[RecordedEvent],
+    until: DateTime<Utc>,
+) -> Result<Option<CompanyState>, EngineError> {
+    let upto = events.iter().take_while(|e| e.at <= until).count();
+    if upto == 0 {
+        return Ok(None);
+    }
+    replay(&events[..upto]).map(Some)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HolderSnapshot {
+    pub tokens: Amount,
+    pub cash: Amount,
+    pub positions: Vec<RoleTier>,
+}
+
+impl From<&Holder> for HolderSnapshot {
+    fn from(holder: &Holder) -> Self {
+        Self {
+            tokens: holder.tokens,
+            cash: holder.cash,
+            positions: holder.positions.clone(),
+        }
+    }
+}
+
+/// One event that changed a holder's balances or roles. `snapshot` is the
+/// holder after the event, or `None` if the event removed them.
+#[derive(Debug, Clone, Serialize)]
+pub struct HolderChange {
+    pub seq: u64,
+    pub at: DateTime<Utc>,
+    pub actor: Option<String>,
+    pub event: String,
+    pub snapshot: Option<HolderSnapshot>,
+}
+
+/// Every change to `holder_id` recorded at or before `until`, oldest first.
+pub fn holder_history(
+    events: &[RecordedEvent],
+    holder_id: &str,
+    until: DateTime<Utc>,
+) -> Result<Vec<HolderChange>, EngineError> {
+    let mut changes = vec![];
+    let Some(first) = events.first() else {
+        return Ok(changes);
+    };
+    let mut state = replay(std::slice::from_ref(first))?;
+    let mut last = state.holders.get(holder_id).map(HolderSnapshot::from);
+    if last.is_some() && first.at <= until {
+        changes.push(change(first, last.clone()));
+    }
+    for recorded in events.iter().skip(1).take_while(|e| e.at <= until) {
+        apply_recorded(&mut state, recorded)?;
+        let current = state.holders.get(holder_id).map(HolderSnapshot::from);
+        if current != last {
+            changes.push(change(recorded, current.clone()));
+            last = current;
+        }
+    }
+    Ok(changes)
+}
+
+fn change(recorded: &RecordedEvent, snapshot: Option<HolderSnapshot>) -> HolderChange {
+    HolderChange {
+        seq: recorded.seq,
+        at: recorded.at,
+        actor: recorded.actor.clone(),
+        event: recorded.event.kind(),
+        snapshot,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::CompanyEvent;
+    use chrono::TimeZone;
+
+    fn day(d: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, d, 12, 0, 0).unwrap()
+    }
+
+    fn events() -> Vec<RecordedEvent> {
+        let grant = |amount| CompanyEvent::TokensGranted {
+            holder_id: "alice".into(),
+            amount: Amount::whole(amount),
+            vesting: None,
+        };
+        let steps = vec![
+            (day(1), CompanyEvent::Initialized { employees: 0 }),
+            (
+                day(2),
+                CompanyEvent::HolderAdded {
+                    id: "alice".into(),
+                    name: "Alice".into(),
+                    cash: Amount::whole(100),
+                },
+            ),
+            (day(4), grant(50)),
+            (day(6), grant(25)),
+        ];
+        steps
+            .into_iter()
+            .enumerate()
+            .map(|(i, (at, event))| RecordedEvent {
+                seq: i as u64 + 1,
+                at,
+                actor: None,
+                event,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn state_at_folds_only_events_up_to_the_moment() {
+        let events = events();
+        assert!(
+            state_at(&events, day(1) - chrono::Duration::hours(1))
+                .unwrap()
+                .is_none()
+        );
+
+        let tokens = |until| state_at(&events, until).unwrap().unwrap().holders["alice"].tokens;
+        let onboarded = tokens(day(2));
+        assert_eq!(tokens(day(3)), onboarded);
+        assert_eq!(tokens(day(5)), onboarded + Amount::whole(50));
+        assert_eq!(tokens(day(7)), onboarded + Amount::whole(75));
+        assert_eq!(state_at(&events, day(5)).unwrap().unwrap().event_seq, 3);
+    }
+
+    #[test]
+    fn holder_history_stops_at_the_moment() {
+        let events = events();
+        let seqs = |until| -> Vec<u64> {
+            holder_history(&events, "alice", until)
+                .unwrap()
+                .iter()
+                .map(|c| c.seq)
+                .collect()
+        };
+        assert!(seqs(day(1)).is_empty());
+        assert_eq!(seqs(day(3)), vec![2]);
+        assert_eq!(seqs(day(5)), vec![2, 3]);
+
+        let changes = holder_history(&events, "alice", day(5)).unwrap();
+        let last = changes.last().unwrap();
+        assert_eq!(last.event, events[2].event.kind());
+        let snapshot = last.snapshot.as_ref().unwrap();
+        assert_eq!(snapshot.cash, Amount::whole(100));
+        assert!(
+            holder_history(&events, "nobody", day(7))
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,432 @@
+use crate::amount::Amount;
+use crate::error::{EngineError, EntityKind};
+use crate::model::*;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+fn cached_balance<'a>(
+    state: &'a mut CompanyState,
+    account: &Account,
+    asset: Asset,
+) -> Option<&'a mut Amount> {
+    match (account, asset) {
+        (Account::Treasury, Asset::Tokens) => Some(&mut state.treasury_tokens),
+        (Account::Treasury, Asset::Cash) => Some(&mut state.treasury_cash),
+        (Account::Holder(id), Asset::Tokens) => state.holders.get_mut(id).map(|h| &mut h.tokens),
+        (Account::Holder(id), Asset::Cash) => state.holders.get_mut(id).map(|h| &mut h.cash),
+        _ => None,
+    }
+}
+
+/// Records a balanced entry and applies it to the cached balances. Treasury
+/// and holder accounts may not go negative; the rest are unbounded sources
+/// and sinks.
+pub fn post(
+    state: &mut CompanyState,
+    at: DateTime<Utc>,
+    asset: Asset,
+    memo: &str,
+    postings: Vec<Posting>,
+) -> Result<(), EngineError> {
+    if postings.is_empty() {
+        return Err(EngineError::invalid("journal entry has no postings"));
+    }
+    let total = postings
+        .iter()
+        .try_fold(Amount::ZERO, |acc, p| acc.checked_add(p.amount))
+        .ok_or(EngineError::Overflow)?;
+    if !total.is_zero() {
+        return Err(EngineError::invalid(format!(
+            "journal entry does not balance: off by {}",
+            total
+        )));
+    }
+
+    let mut net: BTreeMap<&Account, Amount> = BTreeMap::new();
+    for p in &postings {
+        let entry = net.entry(&p.account).or_default();
+        *entry = entry.checked_add(p.amount).ok_or(EngineError::Overflow)?;
+    }
+    let mut updates = vec![];
+    for (account, delta) in &net {
+        if let Account::Holder(id) = account
+            && !state.holders.contains_key(id)
+        {
+            return Err(EngineError::not_found(EntityKind::Holder, id));
+        }
+        let Some(balance) = cached_balance(state, account, asset) else {
+            continue;
+        };
+        let updated = balance.checked_add(*delta).ok_or(EngineError::Overflow)?;
+        if updated.is_negative() {
+            return Err(EngineError::InsufficientFunds {
+                account: account.to_string(),
+                asset,
+                needed: -*delta,
+                available: *balance,
+            });
+        }
+        updates.push((*account, updated));
+    }
+    for (account, updated) in updates {
+        if let Some(balance) = cached_balance(state, account, asset) {
+            *balance = updated;
+        }
+    }
+
+    if asset == Asset::Tokens {
+        for (holder_id, amount) in lockable_receipts(&postings) {
+            let receipt = TokenReceipt { at, amount };
+            state
+                .token_receipts
+                .entry(holder_id.to_string())
+                .or_default()
+                .push(receipt);
+        }
+    }
+    let id = state.journal.len() as u64 + 1;
+    state.journal.push(JournalEntry {
+        id,
+        at,
+        asset,
+        memo: memo.to_string(),
+        postings,
+    });
+    Ok(())
+}
+
+/// Holders the token `postings` credit with tokens sent by another holder,
+/// the treasury or the mint. Tokens back from staking or out of vesting
+/// were the holder's already and are not receipts.
+fn lockable_receipts(postings: &[Posting]) -> Vec<(&str, Amount)> {
+    let mut receipts = vec![];
+    for p in postings.iter().filter(|p| p.amount > Amount::ZERO) {
+        let Account::Holder(id) = &p.account else {
+            continue;
+        };
+        let sent_by_another = postings.iter().any(|q| {
+            q.amount.is_negative()
+                && match &q.account {
+                    Account::Treasury | Account::Mint => true,
+                    Account::Holder(from) => from != id,
+                    _ => false,
+                }
+        });
+        if sent_by_another {
+            receipts.push((id.as_str(), p.amount));
+        }
+    }
+    receipts
+}
+
+/// Indexes the receipts in the journal of a state that predates the
+/// receipt index. A state whose journal has receipts but whose index is
+/// empty can only be one of those.
+pub fn backfill_token_receipts(state: &mut CompanyState) {
+    if !state.token_receipts.is_empty() {
+        return;
+    }
+    let mut index: HashMap<String, Vec<TokenReceipt>> = HashMap::new();
+    for entry in state.journal.iter().filter(|e| e.asset == Asset::Tokens) {
+        for (holder_id, amount) in lockable_receipts(&entry.postings) {
+            let receipt = TokenReceipt {
+                at: entry.at,
+                amount,
+            };
+            index
+                .entry(holder_id.to_string())
+                .or_default()
+                .push(receipt);
+        }
+    }
+    state.token_receipts = index;
+}
+
+/// Moves `amount` of `asset` from one account to another.
+pub fn transfer(
+    state: &mut CompanyState,
+    at: DateTime<Utc>,
+    asset: Asset,
+    memo: &str,
+    from: Account,
+    to: Account,
+    amount: Amount,
+) -> Result<(), EngineError> {
+    if amount.is_negative() {
+        return Err(EngineError::invalid("transfer amount must be >= 0"));
+    }
+    if amount.is_zero() {
+        return Ok(());
+    }
+    post(
+        state,
+        at,
+        asset,
+        memo,
+        vec![
+            Posting {
+                account: from,
+                amount: -amount,
+            },
+            Posting {
+                account: to,
+                amount,
+            },
+        ],
+    )
+}
+
+/// Seeds the journal from cached balances for states that predate it, so
+/// existing balances reconcile instead of appearing from nowhere.
+pub fn open_balances(state: &mut CompanyState, at: DateTime<Utc>) {
+    if !state.journal.is_empty() {
+        return;
+    }
+    let mut opening: Vec<(Account, Asset, Amount)> = vec![
+        (Account::Treasury, Asset::Tokens, state.treasury_tokens),
+        (Account::Treasury, Asset::Cash, state.treasury_cash),
+    ];
+    let mut holders: Vec<&Holder> = state.holders.values().collect();
+    holders.sort_by(|a, b| a.id.cmp(&b.id));
+    for h in holders {
+        opening.push((Account::Holder(h.id.clone()), Asset::Tokens, h.tokens));
+        opening.push((Account::Holder(h.id.clone()), Asset::Cash, h.cash));
+    }
+    for (account, asset, amount) in opening {
+        if amount.is_zero() {
+            continue;
+        }
+        let id = state.journal.len() as u64 + 1;
+        state.journal.push(JournalEntry {
+            id,
+            at,
+            asset,
+            memo: "opening balance".into(),
+            postings: vec![
+                Posting {
+                    account: Account::External,
+                    amount: -amount,
+                },
+                Posting { account, amount },
+            ],
+        });
+    }
+}
+
+/// Balance of `account` derived from the journal alone.
+pub fn balance(state: &CompanyState, account: &Account, asset: Asset) -> Amount {
+    state
+        .journal
+        .iter()
+        .filter(|e| e.asset == asset)
+        .flat_map(|e| &e.postings)
+        .filter(|p| &p.account == account)
+        .map(|p| p.amount)
+        .sum()
+}
+
+/// Derived balance of every account that appears in the journal.
+pub fn trial_balance(state: &CompanyState) -> BTreeMap<(Account, Asset), Amount> {
+    let mut balances = BTreeMap::new();
+    for entry in &state.journal {
+        for p in &entry.postings {
+            let balance = balances
+                .entry((p.account.clone(), entry.asset))
+                .or_insert(Amount::ZERO);
+            *balance = *balance + p.amount;
+        }
+    }
+    balances
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatementLine {
+    pub entry_id: u64,
+    pub at: DateTime<Utc>,
+    pub asset: Asset,
+    pub memo: String,
+    pub amount: Amount,
+    pub balance: Amount,
+}
+
+/// Every movement touching `account`, with running balances per asset.
+pub fn statement(state: &CompanyState, account: &Account) -> Vec<StatementLine> {
+    let mut running: BTreeMap<Asset, Amount> = BTreeMap::new();
+    let mut lines = vec![];
+    for entry in &state.journal {
+        if !entry.postings.iter().any(|p| &p.account == account) {
+            continue;
+        }
+        let amount: Amount = entry
+            .postings
+            .iter()
+            .filter(|p| &p.account == account)
+            .map(|p| p.amount)
+            .sum();
+        let balance = running.entry(entry.asset).or_insert(Amount::ZERO);
+        *balance = *balance + amount;
+        lines.push(StatementLine {
+            entry_id: entry.id,
+            at: entry.at,
+            asset: entry.asset,
+            memo: entry.memo.clone(),
+            amount,
+            balance: *balance,
+        });
+    }
+    lines
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Discrepancy {
+    pub account: Account,
+    pub asset: Asset,
+    pub cached: Amount,
+    pub derived: Amount,
+}
+
+/// Cached balances that disagree with the journal.
+pub fn reconcile(state: &CompanyState) -> Vec<Discrepancy> {
+    let derived = trial_balance(state);
+    let mut cached: Vec<(Account, Asset, Amount)> = vec![
+        (Account::Treasury, Asset::Tokens, state.treasury_tokens),
+        (Account::Treasury, Asset::Cash, state.treasury_cash),
+    ];
+    for h in state.holders.values() {
+        cached.push((Account::Holder(h.id.clone()), Asset::Tokens, h.tokens));
+        cached.push((Account::Holder(h.id.clone()), Asset::Cash, h.cash));
+    }
+    let mut out: Vec<Discrepancy> = cached
+        .into_iter()
+        .filter_map(|(account, asset, cached)| {
+            let derived = derived
+                .get(&(account.clone(), asset))
+                .copied()
+                .unwrap_or(Amount::ZERO);
+            (cached != derived).then_some(Discrepancy {
+                account,
+                asset,
+                cached,
+                derived,
+            })
+        })
+        .collect();
+    out.sort_by(|a, b| (&a.account, a.asset).cmp(&(&b.account, b.asset)));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{new_company, onboard_holder};
+    use crate::error::EngineError;
+    use chrono::TimeZone;
+
+    fn at() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 6, 12, 0, 0).unwrap()
+    }
+
+    fn company() -> CompanyState {
+        let mut state = new_company(0, at());
+        onboard_holder(&mut state, "alice", "Alice", Amount::whole(10), at()).unwrap();
+        state
+    }
+
+    fn posting(account: Account, amount: i64) -> Posting {
+        Posting {
+            account,
+            amount: Amount::whole(amount),
+        }
+    }
+
+    #[test]
+    fn post_rejects_unbalanced_entries() {
+        let mut state = company();
+        let entries = state.journal.len();
+        let err = post(
+            &mut state,
+            at(),
+            Asset::Cash,
+            "lopsided",
+            vec![
+                posting(Account::Holder("alice".into()), -3),
+                posting(Account::Treasury, 2),
+            ],
+        )
+        .unwrap_err();
+        assert!(matches!(err, EngineError::InvalidInput { .. }), "{err:?}");
+        assert_eq!(state.journal.len(), entries);
+        assert_eq!(state.holders["alice"].cash, Amount::whole(10));
+
+        let err = post(&mut state, at(), Asset::Cash, "empty", vec![]).unwrap_err();
+        assert!(matches!(err, EngineError::InvalidInput { .. }), "{err:?}");
+    }
+
+    #[test]
+    fn post_rejects_negative_holder_balances() {
+        let mut state = company();
+        let entries = state.journal.len();
+        let err = transfer(
+            &mut state,
+            at(),
+            Asset::Cash,
+            "overdraft",
+            Account::Holder("alice".into()),
+            Account::Treasury,
+            Amount::whole(11),
+        )
+        .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                EngineError::InsufficientFunds { needed, available, .. }
+                    if needed == Amount::whole(11) && available == Amount::whole(10)
+            ),
+            "{err:?}"
+        );
+        assert_eq!(state.journal.len(), entries);
+        assert_eq!(state.holders["alice"].cash, Amount::whole(10));
+        assert!(state.treasury_cash.is_zero());
+    }
+
+    #[test]
+    fn post_rejects_unknown_holders() {
+        let mut state = company();
+        let err = transfer(
+            &mut state,
+            at(),
+            Asset::Tokens,
+            "nobody",
+            Account::Mint,
+            Account::Holder("bob".into()),
+            Amount::whole(1),
+        )
+        .unwrap_err();
+        assert!(matches!(err, EngineError::NotFound { .. }), "{err:?}");
+    }
+
+    #[test]
+    fn balanced_posts_update_cache_and_reconcile() {
+        let mut state = company();
+        post(
+            &mut state,
+            at(),
+            Asset::Cash,
+            "split",
+            vec![
+                posting(Account::Holder("alice".into()), -4),
+                posting(Account::Treasury, 3),
+                posting(Account::Burn, 1),
+            ],
+        )
+        .unwrap();
+        assert_eq!(state.holders["alice"].cash, Amount::whole(6));
+        assert_eq!(state.treasury_cash, Amount::whole(3));
+        assert_eq!(
+            balance(&state, &Account::Burn, Asset::Cash),
+            Amount::whole(1)
+        );
+        assert!(reconcile(&state).is_empty());
+    }
+}
@@ -1,3 +1,9 @@
+pub mod amount;
 pub mod engine;
+pub mod error;
+pub mod events;
+pub mod history;
+pub mod ledger;
 pub mod model;
+pub mod price_feed;
 pub mod storage;
@@ -1,10 +1,17 @@
+use bnet::amount::Amount;
 use bnet::engine::*;
+use bnet::error::{EngineError, EntityKind, StoreError};
+use bnet::events::CompanyEvent;
+use bnet::history::{holder_history, state_at};
+use bnet::ledger::{reconcile, statement, trial_balance};
 use bnet::model::*;
+use bnet::price_feed::{SourceSpec, open_source};
 use bnet::storage::*;
 use chrono::{DateTime, Utc};
-use clap::{Parser, Subcommand};
-use std::collections::HashMap;
+use clap::{Args, Parser, Subcommand};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "bnet")]
@@ -13,6 +20,18 @@ struct Cli {
     #[arg(long, default_value = "state.json")]
     state: PathBuf,
 
+    /// Storage backend, e.g. `json:state.json` or `sqlite:bnet.db` (overrides --state).
+    #[arg(long)]
+    store: Option<String>,
+
+    /// Who is issuing the command; recorded on every logged event.
+    #[arg(long, env = "BNET_ACTOR")]
+    actor: Option<String>,
+
+    /// Check invariants before every save and refuse to write a broken state.
+    #[arg(long, env = "BNET_VERIFY")]
+    verify: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -28,26 +47,51 @@ enum Commands {
         id: String,
         #[arg(long)]
         name: String,
-        #[arg(long, default_value_t = 0.0)]
-        cash: f64,
+        #[arg(long, default_value = "0")]
+        cash: Amount,
     },
     IngestRevenue {
         #[arg(long)]
-        gross: f64,
-        #[arg(long, default_value_t = 0.0)]
-        refund: f64,
+        gross: Amount,
+        #[arg(long, default_value = "0")]
+        refund: Amount,
         #[arg(long)]
-        timestamp: Option<String>,
+        timestamp: Option<DateTime<Utc>>,
     },
+    /// Emit for the last ISO week over by --timestamp (default: now), or for
+    /// --week, from its ingested revenue.
     EmitWeekly {
+        #[arg(long, conflicts_with = "week")]
+        timestamp: Option<DateTime<Utc>>,
+        /// ISO week, e.g. 2026-W07; it must have ended.
+        #[arg(long)]
+        week: Option<String>,
+    },
+    /// List the weeks emitted so far.
+    ListEmissions,
+    /// Emit for an ISO week (if not yet emitted) and distribute it; a no-op
+    /// if the week already has a payout run.
+    PayoutRun {
+        /// ISO week, e.g. 2026-W07.
+        #[arg(long)]
+        week: String,
+        /// Allocate by this template instead of the week's completed tasks.
         #[arg(long)]
-        revenue_total: f64,
+        template: Option<String>,
+    },
+    /// Undo a week's payout run so it can be run again.
+    PayoutReverse {
+        #[arg(long)]
+        week: String,
+    },
+    /// List payout runs, or show one week's shares.
+    PayoutRuns {
         #[arg(long)]
-        timestamp: Option<String>,
+        week: Option<String>,
     },
     Distribute {
         #[arg(long)]
-        total_tokens: f64,
+        total_tokens: Amount,
         #[arg(long)]
         allocations: String, // format: id:weight,id:weight
     },
@@ -57,7 +101,7 @@ enum Commands {
         #[arg(long)]
         role: RoleTier,
         #[arg(long)]
-        amount: f64,
+        amount: Amount,
     },
     ListPosition {
         #[arg(long)]
@@ -67,7 +111,7 @@ enum Commands {
         #[arg(long)]
         role: RoleTier,
         #[arg(long)]
-        price: f64,
+        price: Amount,
     },
     BuyPosition {
         #[arg(long)]
@@ -75,6 +119,40 @@ enum Commands {
         #[arg(long)]
         buyer: String,
     },
+    /// Put a vacant role position up for auction.
+    OpenAuction {
+        #[arg(long)]
+        auction_id: String,
+        #[arg(long)]
+        role: RoleTier,
+        /// `sealed` or `ascending`.
+        #[arg(long, default_value = "ascending")]
+        format: AuctionFormat,
+        /// `first_price`, or `second_price` to charge the winner the
+        /// runner-up's bid.
+        #[arg(long, default_value = "first_price")]
+        pricing: AuctionPricing,
+        #[arg(long, default_value = "0")]
+        reserve_price: Amount,
+        #[arg(long)]
+        ends_at: DateTime<Utc>,
+    },
+    /// Bid cash on an auction, or raise a standing bid; the cash is held in
+    /// escrow until the auction settles.
+    AuctionBid {
+        #[arg(long)]
+        auction_id: String,
+        #[arg(long)]
+        bidder: String,
+        #[arg(long)]
+        amount: Amount,
+    },
+    /// Award an ended auction to its winner and refund everyone else.
+    SettleAuction {
+        #[arg(long)]
+        auction_id: String,
+    },
+    ListAuctions,
     CreateVote {
         #[arg(long)]
         vote_id: String,
@@ -84,6 +162,10 @@ enum Commands {
         target_holder: String,
         #[arg(long, default_value = "performance")]
         reason: String,
+        /// Stop taking ballots at this time (default: after the policy's
+        /// voting period).
+        #[arg(long)]
+        closes_at: Option<DateTime<Utc>>,
     },
     CastVote {
         #[arg(long)]
@@ -91,21 +173,38 @@ enum Commands {
         #[arg(long)]
         voter: String,
         #[arg(long)]
-        weight: f64,
-        #[arg(long)]
         approve: bool,
     },
     ResolveVote {
         #[arg(long)]
         vote_id: String,
     },
-    AutoValueDropVote {
+    /// Decide every open vote whose closing time has passed.
+    CloseExpiredVotes,
+    /// Let another holder vote with your voting power until you vote yourself.
+    Delegate {
         #[arg(long)]
-        vote_id: String,
+        from: String,
         #[arg(long)]
-        target_role: RoleTier,
+        to: String,
+        /// Only for this kind of proposal, e.g. spend_treasury (default: all).
         #[arg(long)]
-        target_holder: String,
+        kind: Option<ProposalKind>,
+    },
+    RevokeDelegation {
+        #[arg(long)]
+        from: String,
+        #[arg(long)]
+        kind: Option<ProposalKind>,
+    },
+    /// List standing vote delegations.
+    Delegations {
+        #[arg(long)]
+        holder: Option<String>,
+    },
+    /// Record a price and open removal votes against the accountable roles
+    /// if it is a value drop.
+    AutoValueDropVote {
         #[arg(long)]
         current_price: f64,
     },
@@ -117,16 +216,20 @@ enum Commands {
         #[arg(long)]
         voter: String,
         #[arg(long)]
-        weight: f64,
-        #[arg(long)]
         approve: bool,
         #[arg(long, default_value = "performance")]
         reason: String,
     },
+    /// Record a token price, checking it for a value drop.
     SetPrice {
         #[arg(long)]
         price: f64,
     },
+    /// Record token prices from an external feed.
+    PriceFeed {
+        #[command(subcommand)]
+        command: PriceFeedCommand,
+    },
     ListHolders,
     StateReport,
     ListMarketplace,
@@ -182,9 +285,9 @@ enum Commands {
     },
     TokenomicsSet {
         #[arg(long)]
-        total_supply_cap: f64,
+        total_supply_cap: Amount,
         #[arg(long)]
-        minted_supply: f64,
+        minted_supply: Amount,
         #[arg(long)]
         allocations: String,
     },
@@ -193,25 +296,481 @@ enum Commands {
         #[arg(long)]
         holder_id: String,
         #[arg(long)]
-        amount: f64,
+        amount: Amount,
+        /// Vest the grant over this many days instead of paying it at once.
+        #[arg(long)]
+        vest_days: Option<i64>,
+        #[arg(long, requires = "vest_days", default_value_t = 0)]
+        vest_cliff_days: i64,
     },
     Onboard {
         #[arg(long)]
         id: String,
         #[arg(long)]
         name: String,
-        #[arg(long, default_value_t = 0.0)]
-        cash: f64,
+        #[arg(long, default_value = "0")]
+        cash: Amount,
         #[arg(long, default_value_t = 0)]
         early_limit: usize,
-        #[arg(long, default_value_t = 0.0)]
-        early_reward: f64,
+        #[arg(long, default_value = "0")]
+        early_reward: Amount,
+        /// Vest early-joiner rewards over this many days.
+        #[arg(long)]
+        early_vest_days: Option<i64>,
+        #[arg(long, requires = "early_vest_days", default_value_t = 0)]
+        early_vest_cliff_days: i64,
+    },
+    /// Send tokens to another holder.
+    Transfer {
+        #[arg(long)]
+        from: String,
+        #[arg(long)]
+        to: String,
+        #[arg(long)]
+        amount: Amount,
+        #[arg(long)]
+        memo: Option<String>,
+    },
+    /// Show or change the holder-to-holder transfer restrictions.
+    TransferPolicy {
+        #[arg(long)]
+        lockup_days: Option<i64>,
+        #[arg(long, conflicts_with = "no_daily_limit")]
+        max_per_day: Option<Amount>,
+        #[arg(long)]
+        no_daily_limit: bool,
+        #[arg(long)]
+        block_during_removal_vote: Option<bool>,
+    },
+    /// Stake tokens for voting power.
+    Stake {
+        #[arg(long)]
+        holder: String,
+        #[arg(long)]
+        amount: Amount,
+    },
+    /// Start unbonding staked tokens.
+    Unstake {
+        #[arg(long)]
+        holder: String,
+        #[arg(long)]
+        amount: Amount,
+    },
+    /// Return tokens that have finished unbonding to the holder.
+    ClaimUnbonded {
+        #[arg(long)]
+        holder: String,
+    },
+    /// Show or change the unbonding period and role multipliers.
+    StakingPolicy {
+        #[arg(long)]
+        unbonding_days: Option<i64>,
+        /// Voting power multiplier under the stake policy, e.g. ceo:3
+        /// (repeatable; 1 removes it).
+        #[arg(long = "role-multiplier", value_parser = parse_role_multiplier)]
+        role_multipliers: Vec<(RoleTier, f64)>,
+    },
+    /// List stakes and each holder's voting power.
+    ListStakes,
+    /// Show the promotion ladder, or change the rung for --role.
+    PromotionPolicy {
+        #[arg(long)]
+        role: Option<RoleTier>,
+        /// Role that must be held first (a new rung defaults to the tier
+        /// below).
+        #[arg(long, requires = "role", conflicts_with = "no_prerequisite")]
+        requires: Option<RoleTier>,
+        #[arg(long, requires = "role")]
+        no_prerequisite: bool,
+        #[arg(long, requires = "role")]
+        min_days_in_role: Option<i64>,
+        #[arg(long, requires = "role")]
+        min_completed_tasks: Option<usize>,
+        #[arg(long, requires = "role")]
+        min_stake: Option<Amount>,
+        /// Drop the rung, opening the role to anyone.
+        #[arg(long, requires = "role")]
+        remove: bool,
+    },
+    /// Explain what a holder still needs for each role on the ladder.
+    Eligibility {
+        #[arg(long)]
+        holder: String,
+        #[arg(long)]
+        role: Option<RoleTier>,
+    },
+    /// Show or change how ballot weight is computed.
+    VotingPower {
+        /// token, stake, one_holder, role_tier or quadratic.
+        #[arg(long)]
+        policy: Option<VotingPowerPolicy>,
+        /// Role weight for role_tier, e.g. ceo:10 (repeatable).
+        #[arg(long = "role-weight", value_parser = parse_role_multiplier)]
+        role_weights: Vec<(RoleTier, f64)>,
+    },
+    /// Show or change the governance policy.
+    GovernancePolicy {
+        #[command(flatten)]
+        changes: GovernancePolicyArgs,
+    },
+    /// Open a vote on a proposal that takes effect if the vote passes.
+    Propose {
+        #[arg(long)]
+        vote_id: String,
+        #[arg(long, default_value = "")]
+        reason: String,
+        /// Stop taking ballots at this time (default: after the policy's
+        /// voting period).
+        #[arg(long)]
+        closes_at: Option<DateTime<Utc>>,
+        #[command(subcommand)]
+        proposal: ProposalCommand,
+    },
+    /// Release every vesting schedule's tokens vested by --now.
+    Vest {
+        /// Accrue up to this time (default: the current time).
+        #[arg(
+            long,
+            value_name = "TIMESTAMP",
+            num_args = 0..=1,
+            default_missing_value = "now",
+            value_parser = parse_moment
+        )]
+        now: Option<DateTime<Utc>>,
+    },
+    /// Vest work distributions to holders of a role.
+    VestingSetRole {
+        #[arg(long)]
+        role: RoleTier,
+        #[arg(long, required_unless_present = "clear")]
+        days: Option<i64>,
+        #[arg(long, default_value_t = 0)]
+        cliff_days: i64,
+        /// Stop vesting distributions for this role.
+        #[arg(long, conflicts_with = "days")]
+        clear: bool,
+    },
+    /// List vesting schedules.
+    Vesting {
+        #[arg(long)]
+        holder: Option<String>,
+    },
+    /// Check the state's cross-references and accounting invariants.
+    Verify,
+    /// Print the event log (the audit trail of every mutation).
+    Events {
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Refold the state snapshot from the event log.
+    Rebuild,
+    /// Show the company as it was at a past moment.
+    StateAt {
+        #[arg(long)]
+        timestamp: DateTime<Utc>,
+    },
+    /// Print an account statement, or the trial balance when no account is given.
+    Ledger {
+        /// e.g. `treasury`, `revenue`, `mint` or `holder:<id>`.
+        #[arg(long)]
+        account: Option<Account>,
+    },
+    /// Show how one holder's tokens, cash and roles changed over time.
+    History {
+        #[arg(long)]
+        holder: String,
+        /// Only consider events up to this moment (defaults to now).
+        #[arg(long)]
+        timestamp: Option<DateTime<Utc>>,
     },
 }
 
-fn parse_timestamp(opt: Option<String>) -> DateTime<Utc> {
-    opt.and_then(|s| s.parse::<DateTime<Utc>>().ok())
-        .unwrap_or_else(Utc::now)
+#[derive(Args)]
+struct GovernancePolicyArgs {
+    /// Share of cast voting power needed to pass, e.g. 0.6667.
+    #[arg(long)]
+    vote_threshold: Option<f64>,
+    #[arg(long)]
+    value_drop_trigger: Option<f64>,
+    #[arg(long)]
+    value_window_days: Option<i64>,
+    /// Whether voters may replace their ballot on an open vote.
+    #[arg(long)]
+    allow_vote_changes: Option<bool>,
+    /// Share of eligible voting power that must vote, e.g. 0.5.
+    #[arg(long)]
+    quorum: Option<f64>,
+    #[arg(long)]
+    voting_period_days: Option<i64>,
+    /// Who may vote on a kind of proposal, e.g. `remove_from_role:ceo=board_seat`
+    /// or `spend_treasury=director` (repeatable).
+    #[arg(long = "eligibility", value_parser = parse_eligibility_rule)]
+    eligibility: Vec<EligibilityRule>,
+    /// Drop every eligibility rule before applying --eligibility.
+    #[arg(long)]
+    clear_eligibility: bool,
+    /// Roles that face removal votes on a value drop (repeatable; replaces
+    /// the current list).
+    #[arg(long = "accountable-role", conflicts_with = "clear_accountable_roles")]
+    accountable_roles: Vec<RoleTier>,
+    /// Stop opening removal votes on value drops.
+    #[arg(long)]
+    clear_accountable_roles: bool,
+    #[arg(long)]
+    value_drop_cooldown_days: Option<i64>,
+    /// How prices are averaged over the value window: `mean`, `twap`,
+    /// `median` or `ema[:<half-life days>]`.
+    #[arg(long)]
+    price_average: Option<PriceAverage>,
+}
+
+impl GovernancePolicyArgs {
+    fn into_patch(self) -> GovernancePolicyPatch {
+        GovernancePolicyPatch {
+            vote_threshold: self.vote_threshold,
+            value_drop_trigger: self.value_drop_trigger,
+            value_window_days: self.value_window_days,
+            allow_vote_changes: self.allow_vote_changes,
+            quorum: self.quorum,
+            voting_period_days: self.voting_period_days,
+            clear_eligibility: self.clear_eligibility,
+            eligibility: self.eligibility,
+            accountable_roles: (self.clear_accountable_roles || !self.accountable_roles.is_empty())
+                .then_some(self.accountable_roles),
+            value_drop_cooldown_days: self.value_drop_cooldown_days,
+            price_average: self.price_average,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum PriceFeedCommand {
+    /// Record every tick from a source, checking each for a value drop.
+    /// Timestamped ticks no newer than the latest recorded price are
+    /// skipped, so a file can be fed in again after it grows.
+    Run {
+        /// `file:<path>` (CSV or JSON lines), `stdin`, or an `http://` URL
+        /// to poll.
+        #[arg(long)]
+        source: String,
+        /// Seconds between polls of an HTTP source.
+        #[arg(long, default_value_t = 60)]
+        interval_secs: u64,
+        /// Stop after recording this many ticks.
+        #[arg(long)]
+        max_ticks: Option<usize>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProposalCommand {
+    /// Remove a holder from a role.
+    RemoveFromRole {
+        #[arg(long)]
+        role: RoleTier,
+        #[arg(long)]
+        holder: String,
+    },
+    /// Appoint a holder to a role.
+    AppointToRole {
+        #[arg(long)]
+        role: RoleTier,
+        #[arg(long)]
+        holder: String,
+    },
+    /// Change governance policy fields; the rest keep whatever value they
+    /// have when the vote passes.
+    Governance {
+        #[command(flatten)]
+        changes: GovernancePolicyArgs,
+    },
+    /// Change emission policy fields; the rest keep whatever value they
+    /// have when the vote passes.
+    Emission {
+        /// Share of weekly net revenue emitted as tokens, e.g. 0.2.
+        #[arg(long)]
+        weekly_payout_percent: Option<f64>,
+        #[arg(long)]
+        halving_interval_days: Option<i64>,
+    },
+    /// Pay from the treasury to `holder:<id>` or `external`.
+    Spend {
+        #[arg(long)]
+        to: Account,
+        #[arg(long, default_value = "cash")]
+        asset: Asset,
+        #[arg(long)]
+        amount: Amount,
+    },
+    /// Mint tokens into the treasury.
+    Mint {
+        #[arg(long)]
+        amount: Amount,
+    },
+    /// Burn tokens from the treasury.
+    Burn {
+        #[arg(long)]
+        amount: Amount,
+    },
+    /// Replace the tokenomics allocations, e.g. `team:20|community:50`.
+    Allocations {
+        #[arg(long)]
+        allocations: String,
+    },
+}
+
+impl ProposalCommand {
+    fn into_proposal(self) -> Proposal {
+        match self {
+            ProposalCommand::RemoveFromRole { role, holder } => Proposal::RemoveFromRole {
+                target_role: role,
+                target_holder: holder,
+            },
+            ProposalCommand::AppointToRole { role, holder } => Proposal::AppointToRole {
+                role,
+                holder_id: holder,
+            },
+            ProposalCommand::Governance { changes } => Proposal::PatchGovernancePolicy {
+                changes: changes.into_patch(),
+            },
+            ProposalCommand::Emission {
+                weekly_payout_percent,
+                halving_interval_days,
+            } => Proposal::PatchEmissionPolicy {
+                changes: EmissionPolicyPatch {
+                    weekly_payout_percent,
+                    halving_interval_days,
+                },
+            },
+            ProposalCommand::Spend { to, asset, amount } => {
+                Proposal::SpendTreasury { to, asset, amount }
+            }
+            ProposalCommand::Mint { amount } => Proposal::MintTokens { amount },
+            ProposalCommand::Burn { amount } => Proposal::BurnTokens { amount },
+            ProposalCommand::Allocations { allocations } => Proposal::AmendAllocations {
+                allocations: parse_allocations_config(&allocations),
+            },
+        }
+    }
+}
+
+trait OrExit<T> {
+    fn or_exit(self, context: &str) -> T;
+}
+
+impl<T, E: Into<StoreError>> OrExit<T> for Result<T, E> {
+    /// Prints the error (human line, then JSON for engine errors) and exits non-zero.
+    fn or_exit(self, context: &str) -> T {
+        match self.map_err(Into::into) {
+            Ok(value) => value,
+            Err(err) => {
+                eprintln!("{}: {}", context, err);
+                if let StoreError::Engine(engine_err) = &err
+                    && let Ok(json) = serde_json::to_string(engine_err)
+                {
+                    eprintln!("{}", json);
+                }
+                if let StoreError::Invariant(violations) = &err {
+                    for v in violations {
+                        eprintln!("{}", serde_json::to_string(v).unwrap());
+                    }
+                }
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// What recording one price found.
+struct PriceCheck {
+    average: Option<f64>,
+    triggered: bool,
+    vote_ids: Vec<String>,
+}
+
+/// Records `price` and monitors it for a value drop, as observed at
+/// `observed_at` or else now.
+fn record_price(
+    tx: &mut Transaction,
+    price: f64,
+    observed_at: Option<DateTime<Utc>>,
+) -> Result<PriceCheck, EngineError> {
+    tx.record(CompanyEvent::TokenPriceRecorded { price, observed_at })?;
+    let state = tx.state();
+    let at = observed_at.or_else(|| state.token_price_history.last().map(|(at, _)| *at));
+    let average = at.and_then(|at| rolling_average_price(state, at));
+    let triggered = at.is_some_and(|at| value_drop_triggered(state, price, at));
+    let drops = state.value_drops.len();
+    tx.record(CompanyEvent::ValueDropMonitored {
+        current_price: price,
+        observed_at,
+    })?;
+    let vote_ids = match tx.state().value_drops.get(drops) {
+        Some(drop) => drop.vote_ids.clone(),
+        None => vec![],
+    };
+    Ok(PriceCheck {
+        average,
+        triggered,
+        vote_ids,
+    })
+}
+
+fn print_vote_outcome(record: &VoteRecord) {
+    let outcome = if record.passed() {
+        VoteOutcome::Passed
+    } else {
+        VoteOutcome::Failed
+    };
+    println!("Resolved vote {}: {}", record.id, outcome);
+    if let Some(err) = &record.effect_error {
+        println!("  could not {}: {}", record.proposal, err);
+    }
+}
+
+/// A timestamp, or `now` for the current time.
+fn parse_moment(raw: &str) -> Result<DateTime<Utc>, String> {
+    if raw == "now" {
+        return Ok(Utc::now());
+    }
+    raw.parse().map_err(|_| {
+        format!(
+            "invalid timestamp {:?}, expected e.g. 2026-02-16T00:00:00Z",
+            raw
+        )
+    })
+}
+
+fn parse_role_multiplier(raw: &str) -> Result<(RoleTier, f64), String> {
+    let (role, multiplier) = raw
+        .split_once(':')
+        .ok_or_else(|| format!("expected role:multiplier, got {}", raw))?;
+    let multiplier = multiplier
+        .parse::<f64>()
+        .map_err(|_| format!("invalid multiplier: {}", multiplier))?;
+    Ok((role.parse()?, multiplier))
+}
+
+fn parse_eligibility_rule(raw: &str) -> Result<EligibilityRule, String> {
+    let (scope, min_role) = raw
+        .split_once('=')
+        .ok_or_else(|| format!("expected kind[:role]=min_role, got {}", raw))?;
+    let (kind, target_role) = match scope.split_once(':') {
+        Some((kind, role)) => (kind, Some(role.parse()?)),
+        None => (scope, None),
+    };
+    Ok(EligibilityRule {
+        kind: kind.parse()?,
+        target_role,
+        min_role: min_role.parse()?,
+    })
+}
+
+fn vesting_terms(days: Option<i64>, cliff_days: i64) -> Option<VestingTerms> {
+    days.map(|duration_days| VestingTerms {
+        cliff_days,
+        duration_days,
+    })
 }
 
 fn parse_allocations(raw: &str) -> Vec<WorkAllocation> {
@@ -261,54 +820,36 @@ fn parse_allocations_config(raw: &str) -> Vec<TokenAllocation> {
         .collect()
 }
 
-fn default_state() -> CompanyState {
-    let now = Utc::now();
-    CompanyState {
-        holders: HashMap::new(),
-        treasury_tokens: 0.0,
-        treasury_cash: 0.0,
-        positions: vec![],
-        employee_count: 0,
-        token_price_history: vec![],
-        emission_policy: EmissionPolicy {
-            weekly_payout_percent: 0.20,
-            halving_interval_days: 365,
-            genesis: now,
-        },
-        governance_policy: GovernancePolicy {
-            vote_threshold: 2.0 / 3.0,
-            value_drop_trigger: 0.20,
-            value_window_days: 30,
-        },
-        votes: HashMap::new(),
-        marketplace: vec![],
-        tasks: HashMap::new(),
-        tokenomics: None,
-        onboarding_policy: OnboardingPolicy {
-            early_joiner_limit: 0,
-            early_joiner_reward: 0.0,
-        },
-        onboarding_count: 0,
-    }
-}
-
 fn main() {
     let cli = Cli::parse();
-    let path = cli.state;
+    let spec = match cli.store {
+        Some(raw) => raw.parse::<StoreSpec>().or_exit("parse store"),
+        None => StoreSpec::Json(cli.state),
+    };
+    let mut store = open_store(&spec).or_exit("open store");
+    if cli.verify {
+        store = Box::new(VerifiedStore::new(store));
+    }
+    let store = store.as_mut();
+    let actor = cli.actor.as_deref();
 
     match cli.command {
         Commands::Init { employees } => {
-            let mut state = default_state();
-            state.employee_count = employees;
-            ensure_positions(&mut state);
-            save_state(&path, &state).expect("save state");
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::Initialized { employees })
+            })
+            .or_exit("init");
             println!("Initialized state with {} employees", employees);
         }
         Commands::AddHolder { id, name, cash } => {
-            let mut state = load_state(&path).expect("load state");
-            onboard_holder(&mut state, &id, &name, cash).expect("add holder");
-            ensure_positions(&mut state);
-            save_state(&path, &state).expect("save state");
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::HolderAdded {
+                    id: id.clone(),
+                    name,
+                    cash,
+                })
+            })
+            .or_exit("add holder");
             println!("Added holder {}", id);
         }
         Commands::IngestRevenue {
@@ -316,132 +857,340 @@ fn main() {
             refund,
             timestamp,
         } => {
-            let mut state = load_state(&path).expect("load state");
-            let event = RevenueEvent {
-                timestamp: parse_timestamp(timestamp),
+            let revenue = RevenueEvent {
+                timestamp: timestamp.unwrap_or_else(Utc::now),
                 gross_revenue: gross,
                 refund_amount: refund,
             };
-            let net = net_revenue(&event);
-            state.treasury_cash += net;
-            save_state(&path, &state).expect("save state");
+            let net = net_revenue(&revenue);
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::RevenueIngested { revenue })
+            })
+            .or_exit("ingest revenue");
             println!("Ingested net revenue: {}", net);
         }
-        Commands::EmitWeekly {
-            revenue_total,
-            timestamp,
-        } => {
-            let mut state = load_state(&path).expect("load state");
-            let minted = run_weekly_emission(&mut state, revenue_total, parse_timestamp(timestamp));
-            save_state(&path, &state).expect("save state");
-            println!("Minted weekly tokens: {}", minted);
+        Commands::EmitWeekly { timestamp, week } => {
+            let week = match week {
+                Some(week) => canonical_iso_week(&week).or_exit("emit weekly"),
+                None => last_completed_week(timestamp.unwrap_or_else(Utc::now)),
+            };
+            let emission = update(store, actor, |tx| {
+                tx.record(CompanyEvent::CompletedWeekEmitted { week })?;
+                Ok(tx.state().weekly_emissions.last().cloned())
+            })
+            .or_exit("emit weekly")
+            .expect("emission recorded");
+            println!(
+                "Week {}: net revenue {}, minted weekly tokens: {}",
+                emission.week, emission.net_revenue, emission.minted
+            );
+            if emission.withheld > Amount::ZERO {
+                println!("Withheld by supply cap: {}", emission.withheld);
+            }
+        }
+        Commands::PayoutRun { week, template } => {
+            let source = match template {
+                Some(name) => PayoutSource::Template { name },
+                None => PayoutSource::Tasks,
+            };
+            let week = canonical_iso_week(&week).or_exit("payout run");
+            let (run, fresh) = update(store, actor, |tx| {
+                if let Some(existing) = payout_run(tx.state(), &week) {
+                    return Ok((existing.clone(), false));
+                }
+                tx.record(CompanyEvent::PayoutRunCompleted {
+                    week: week.clone(),
+                    source,
+                })?;
+                let run = payout_run(tx.state(), &week).expect("payout run recorded");
+                Ok((run.clone(), true))
+            })
+            .or_exit("payout run");
+            if fresh {
+                println!(
+                    "Payout run {}: minted {}, distributed {} to {} holders",
+                    run.week,
+                    run.minted,
+                    run.distributed,
+                    run.shares.len()
+                );
+            } else {
+                println!(
+                    "Payout run {} already done at {}; nothing to do",
+                    run.week,
+                    run.run_at.to_rfc3339()
+                );
+            }
+        }
+        Commands::PayoutReverse { week } => {
+            let week = canonical_iso_week(&week).or_exit("payout reverse");
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::PayoutRunReversed { week: week.clone() })
+            })
+            .or_exit("payout reverse");
+            println!("Reversed payout run {}", week);
+        }
+        Commands::PayoutRuns { week: None } => {
+            let state = current_state(store).or_exit("load state");
+            for r in &state.payout_runs {
+                let status = match r.reversed_at {
+                    Some(at) => format!("reversed {}", at.to_rfc3339()),
+                    None => "live".to_string(),
+                };
+                println!(
+                    "{} | run {} | minted: {} | distributed: {} | {}",
+                    r.week,
+                    r.run_at.to_rfc3339(),
+                    r.minted,
+                    r.distributed,
+                    status
+                );
+            }
+        }
+        Commands::PayoutRuns { week: Some(week) } => {
+            let state = current_state(store).or_exit("load state");
+            let runs: Vec<&PayoutRun> = state
+                .payout_runs
+                .iter()
+                .filter(|r| same_week(&r.week, &week))
+                .collect();
+            if runs.is_empty() {
+                println!("No payout runs for {}", week);
+            }
+            for r in runs {
+                println!("{}", serde_json::to_string_pretty(r).unwrap());
+            }
+        }
+        Commands::ListEmissions => {
+            let state = current_state(store).or_exit("load state");
+            for e in &state.weekly_emissions {
+                println!(
+                    "{} | as of {} | net revenue: {} | minted: {} | withheld: {}",
+                    e.week,
+                    e.as_of.to_rfc3339(),
+                    e.net_revenue,
+                    e.minted,
+                    e.withheld
+                );
+            }
         }
         Commands::Distribute {
             total_tokens,
             allocations,
         } => {
-            let mut state = load_state(&path).expect("load state");
             let parsed = parse_allocations(&allocations);
-            distribute_tokens(&mut state, &parsed, total_tokens).expect("distribute");
-            save_state(&path, &state).expect("save state");
-            println!("Distributed {} tokens", total_tokens);
+            let distributed = update(store, actor, |tx| {
+                let before = tx.state().treasury_tokens;
+                tx.record(CompanyEvent::TokensDistributed {
+                    allocations: parsed,
+                    total_tokens,
+                })?;
+                Ok(before - tx.state().treasury_tokens)
+            })
+            .or_exit("distribute");
+            println!(
+                "Distributed {} tokens ({} left in treasury as rounding remainder)",
+                distributed,
+                total_tokens - distributed
+            );
         }
         Commands::Bid {
             bidder,
             role,
             amount,
         } => {
-            let mut state = load_state(&path).expect("load state");
             let bid = PromotionBid {
                 bidder_id: bidder,
                 target_role: role,
                 bid_amount: amount,
                 timestamp: Utc::now(),
             };
-            apply_promotion_bid(&mut state, bid).expect("apply bid");
-            save_state(&path, &state).expect("save state");
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::PromotionBidPlaced { bid })
+            })
+            .or_exit("apply bid");
             println!("Bid accepted");
         }
         Commands::Vote {
             target_role,
             target_holder,
             voter,
-            weight,
             approve,
             reason,
         } => {
-            let mut state = load_state(&path).expect("load state");
             let vote_id = format!("vote-{}", Utc::now().timestamp());
-            create_vote(
-                &mut state,
-                &vote_id,
-                target_role,
-                &target_holder,
-                &reason,
-                Utc::now(),
-            );
-            cast_vote(
-                &mut state,
-                &vote_id,
-                Vote {
+            let passed = update(store, actor, |tx| {
+                let terms = vote_terms(tx.state(), Utc::now());
+                tx.record(CompanyEvent::VoteCreated {
+                    vote_id: vote_id.clone(),
+                    target_role,
+                    target_holder,
+                    reason,
+                    terms: Some(terms),
+                })?;
+                tx.record(CompanyEvent::BallotCast {
+                    vote_id: vote_id.clone(),
                     voter_id: voter,
-                    weight,
                     approve,
-                },
-            )
-            .expect("cast vote");
+                })?;
+                tx.record(CompanyEvent::VoteResolutionChecked {
+                    vote_id: vote_id.clone(),
+                })?;
+                Ok(tx.state().votes[&vote_id].passed())
+            })
+            .or_exit("vote");
 
-            if resolve_vote_if_passed(&mut state, &vote_id).expect("resolve vote") {
+            if passed {
                 println!("Vote passed: removed holder from role");
             } else {
                 println!("Vote recorded (not yet passed)");
             }
-
-            save_state(&path, &state).expect("save state");
         }
         Commands::CreateVote {
             vote_id,
             target_role,
             target_holder,
             reason,
+            closes_at,
         } => {
-            let mut state = load_state(&path).expect("load state");
-            create_vote(
-                &mut state,
-                &vote_id,
-                target_role,
-                &target_holder,
-                &reason,
-                Utc::now(),
+            let terms = update(store, actor, |tx| {
+                let mut terms = vote_terms(tx.state(), Utc::now());
+                if let Some(closes_at) = closes_at {
+                    if closes_at <= terms.opens_at {
+                        return Err(EngineError::invalid("vote must close in the future"));
+                    }
+                    terms.closes_at = closes_at;
+                }
+                tx.record(CompanyEvent::VoteCreated {
+                    vote_id: vote_id.clone(),
+                    target_role,
+                    target_holder,
+                    reason,
+                    terms: Some(terms),
+                })?;
+                Ok(terms)
+            })
+            .or_exit("create vote");
+            println!(
+                "Created vote {} (closes {}, quorum {})",
+                vote_id,
+                terms.closes_at.to_rfc3339(),
+                terms.quorum
             );
-            save_state(&path, &state).expect("save state");
-            println!("Created vote {}", vote_id);
         }
         Commands::CastVote {
             vote_id,
             voter,
-            weight,
             approve,
         } => {
-            let mut state = load_state(&path).expect("load state");
-            cast_vote(
-                &mut state,
-                &vote_id,
-                Vote {
-                    voter_id: voter,
-                    weight,
+            let (weight, delegated) = update(store, actor, |tx| {
+                tx.record(CompanyEvent::BallotCast {
+                    vote_id: vote_id.clone(),
+                    voter_id: voter.clone(),
                     approve,
-                },
-            )
-            .expect("cast vote");
-            save_state(&path, &state).expect("save state");
-            println!("Cast vote {}", vote_id);
+                })?;
+                let record = &tx.state().votes[&vote_id];
+                Ok((
+                    record.votes[&voter].weight,
+                    delegated_power(tx.state(), record, &voter),
+                ))
+            })
+            .or_exit("cast vote");
+            if delegated > 0.0 {
+                println!(
+                    "Cast vote {} with weight {} (+{} delegated)",
+                    vote_id, weight, delegated
+                );
+            } else {
+                println!("Cast vote {} with weight {}", vote_id, weight);
+            }
         }
         Commands::ResolveVote { vote_id } => {
-            let mut state = load_state(&path).expect("load state");
-            let passed = resolve_vote_if_passed(&mut state, &vote_id).expect("resolve vote");
-            save_state(&path, &state).expect("save state");
-            println!("Resolved vote {}: passed={}", vote_id, passed);
+            let record = update(store, actor, |tx| {
+                tx.record(CompanyEvent::VoteResolutionChecked {
+                    vote_id: vote_id.clone(),
+                })?;
+                Ok(tx.state().votes[&vote_id].clone())
+            })
+            .or_exit("resolve vote");
+            if record.resolved {
+                print_vote_outcome(&record);
+            } else {
+                println!("Vote {} is still undecided", vote_id);
+            }
+        }
+        Commands::CloseExpiredVotes => {
+            let closed = update(store, actor, |tx| {
+                let mut open: Vec<String> = tx
+                    .state()
+                    .votes
+                    .values()
+                    .filter(|v| !v.resolved)
+                    .map(|v| v.id.clone())
+                    .collect();
+                open.sort();
+                tx.record(CompanyEvent::ExpiredVotesClosed)?;
+                let votes = &tx.state().votes;
+                Ok(open
+                    .into_iter()
+                    .map(|id| votes[&id].clone())
+                    .filter(|v| v.resolved)
+                    .collect::<Vec<_>>())
+            })
+            .or_exit("close expired votes");
+            if closed.is_empty() {
+                println!("No expired votes");
+            }
+            for record in &closed {
+                print_vote_outcome(record);
+            }
+        }
+        Commands::Delegate { from, to, kind } => {
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::VotesDelegated {
+                    delegator_id: from.clone(),
+                    delegate_id: to.clone(),
+                    kind,
+                })
+            })
+            .or_exit("delegate");
+            match kind {
+                Some(kind) => println!("{} delegates {} votes to {}", from, kind, to),
+                None => println!("{} delegates votes to {}", from, to),
+            }
+        }
+        Commands::RevokeDelegation { from, kind } => {
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::DelegationRevoked {
+                    delegator_id: from.clone(),
+                    kind,
+                })
+            })
+            .or_exit("revoke delegation");
+            println!("Revoked delegation for {}", from);
+        }
+        Commands::Delegations { holder } => {
+            let state = current_state(store).or_exit("load state");
+            let mut delegations: Vec<&Delegation> = state
+                .delegations
+                .iter()
+                .filter(|d| {
+                    holder
+                        .as_ref()
+                        .is_none_or(|h| &d.delegator_id == h || &d.delegate_id == h)
+                })
+                .collect();
+            delegations.sort_by(|a, b| (&a.delegator_id, a.kind).cmp(&(&b.delegator_id, b.kind)));
+            for d in delegations {
+                println!(
+                    "{} -> {} | {} | since {}",
+                    d.delegator_id,
+                    d.delegate_id,
+                    d.kind
+                        .map_or("all proposals".to_string(), |k| k.to_string()),
+                    d.since.to_rfc3339()
+                );
+            }
         }
         Commands::ListPosition {
             listing_id,
@@ -449,54 +1198,114 @@ fn main() {
             role,
             price,
         } => {
-            let mut state = load_state(&path).expect("load state");
-            create_listing(&mut state, &listing_id, &seller, role, price, Utc::now())
-                .expect("create listing");
-            save_state(&path, &state).expect("save state");
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::PositionListed {
+                    listing_id: listing_id.clone(),
+                    seller_id: seller,
+                    role,
+                    price,
+                })
+            })
+            .or_exit("create listing");
             println!("Listed position {}", listing_id);
         }
         Commands::BuyPosition { listing_id, buyer } => {
-            let mut state = load_state(&path).expect("load state");
-            buy_listing(&mut state, &listing_id, &buyer).expect("buy listing");
-            save_state(&path, &state).expect("save state");
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::ListingBought {
+                    listing_id: listing_id.clone(),
+                    buyer_id: buyer,
+                })
+            })
+            .or_exit("buy listing");
             println!("Bought position {}", listing_id);
         }
         Commands::AutoValueDropVote {
-            vote_id,
-            target_role,
-            target_holder,
-            current_price,
-        } => {
-            let mut state = load_state(&path).expect("load state");
-            record_token_price(&mut state, current_price, Utc::now());
-            let triggered = auto_trigger_value_drop_vote(
-                &mut state,
-                current_price,
-                Utc::now(),
-                &vote_id,
-                target_role,
-                &target_holder,
-            );
-            save_state(&path, &state).expect("save state");
-            println!("Value-drop trigger: {}", triggered);
+            current_price: price,
         }
-        Commands::SetPrice { price } => {
-            let mut state = load_state(&path).expect("load state");
-            record_token_price(&mut state, price, Utc::now());
-            save_state(&path, &state).expect("save state");
+        | Commands::SetPrice { price } => {
+            let check =
+                update(store, actor, |tx| record_price(tx, price, None)).or_exit("set price");
             println!("Price recorded: {}", price);
+            if let Some(average) = check.average {
+                println!("Window average: {:.6}", average);
+            }
+            println!("Value-drop trigger: {}", check.triggered);
+            for vote_id in check.vote_ids {
+                println!("Opened vote {}", vote_id);
+            }
+        }
+        Commands::PriceFeed {
+            command:
+                PriceFeedCommand::Run {
+                    source,
+                    interval_secs,
+                    max_ticks,
+                },
+        } => {
+            let mut source = source
+                .parse::<SourceSpec>()
+                .and_then(|spec| open_source(&spec, Duration::from_secs(interval_secs)))
+                .unwrap_or_else(|e| {
+                    eprintln!("price feed: {}", e);
+                    std::process::exit(1);
+                });
+            let (mut recorded, mut skipped) = (0, 0);
+            while max_ticks.is_none_or(|max| recorded < max) {
+                let tick = match source.next_tick() {
+                    Ok(Some(tick)) => tick,
+                    Ok(None) => break,
+                    Err(e) => {
+                        eprintln!("price feed: {}", e);
+                        if e.is_transient() {
+                            continue;
+                        }
+                        std::process::exit(1);
+                    }
+                };
+                let check = update(store, actor, |tx| {
+                    if tick.is_stale(&tx.state().token_price_history) {
+                        return Ok(None);
+                    }
+                    record_price(tx, tick.price, tick.at).map(Some)
+                })
+                .or_exit("record tick");
+                let Some(check) = check else {
+                    skipped += 1;
+                    continue;
+                };
+                recorded += 1;
+                let at = tick
+                    .at
+                    .map_or_else(|| "now".to_string(), |at| at.to_rfc3339());
+                match check.average {
+                    Some(average) => println!(
+                        "{} | price: {} | average: {:.6} | value drop: {}",
+                        at, tick.price, average, check.triggered
+                    ),
+                    None => println!("{} | price: {}", at, tick.price),
+                }
+                for vote_id in check.vote_ids {
+                    println!("Opened vote {}", vote_id);
+                }
+            }
+            println!("Recorded {} ticks, skipped {} stale", recorded, skipped);
         }
         Commands::ListHolders => {
-            let state = load_state(&path).expect("load state");
+            let state = current_state(store).or_exit("load state");
             for holder in state.holders.values() {
                 println!(
-                    "{} | {} | tokens: {} | cash: {} | roles: {:?}",
-                    holder.id, holder.display_name, holder.tokens, holder.cash, holder.positions
+                    "{} | {} | tokens: {} | unvested: {} | cash: {} | roles: {:?}",
+                    holder.id,
+                    holder.display_name,
+                    holder.tokens,
+                    unvested_tokens(&state, &holder.id),
+                    holder.cash,
+                    holder.positions
                 );
             }
         }
         Commands::StateReport => {
-            let state = load_state(&path).expect("load state");
+            let state = current_state(store).or_exit("load state");
             println!("Employees: {}", state.employee_count);
             println!("Treasury tokens: {}", state.treasury_tokens);
             println!("Treasury cash: {}", state.treasury_cash);
@@ -504,8 +1313,97 @@ fn main() {
             println!("Marketplace listings: {}", state.marketplace.len());
             println!("Votes: {}", state.votes.len());
         }
+        Commands::OpenAuction {
+            auction_id,
+            role,
+            format,
+            pricing,
+            reserve_price,
+            ends_at,
+        } => {
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::AuctionOpened {
+                    auction_id: auction_id.clone(),
+                    role,
+                    format,
+                    pricing,
+                    reserve_price,
+                    ends_at,
+                })
+            })
+            .or_exit("open auction");
+            println!(
+                "Opened {} {} auction {} for {} until {}",
+                format, pricing, auction_id, role, ends_at
+            );
+        }
+        Commands::AuctionBid {
+            auction_id,
+            bidder,
+            amount,
+        } => {
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::AuctionBidPlaced {
+                    auction_id: auction_id.clone(),
+                    bidder_id: bidder.clone(),
+                    amount,
+                })
+            })
+            .or_exit("bid");
+            println!("{} bid {} on auction {}", bidder, amount, auction_id);
+        }
+        Commands::SettleAuction { auction_id } => {
+            let settlement = update(store, actor, |tx| {
+                tx.record(CompanyEvent::AuctionSettled {
+                    auction_id: auction_id.clone(),
+                })?;
+                Ok(tx.state().auctions[&auction_id].settlement.clone())
+            })
+            .or_exit("settle auction");
+            match settlement.and_then(|s| s.winner_id.map(|w| (w, s.price))) {
+                Some((winner, price)) => {
+                    println!("Auction {} won by {} for {}", auction_id, winner, price)
+                }
+                None => println!("Auction {} closed without a winner", auction_id),
+            }
+        }
+        Commands::ListAuctions => {
+            let state = current_state(store).or_exit("load state");
+            let now = Utc::now();
+            let mut auctions: Vec<&Auction> = state.auctions.values().collect();
+            auctions.sort_by(|a, b| (a.ends_at, &a.id).cmp(&(b.ends_at, &b.id)));
+            for a in auctions {
+                // Sealed bids stay hidden until the auction settles.
+                let leading = match (a.format, &a.settlement) {
+                    (AuctionFormat::Ascending, None) => a
+                        .leader()
+                        .map(|(id, bid)| format!(" | leading: {} at {}", id, bid.amount)),
+                    _ => None,
+                };
+                let status = match &a.settlement {
+                    Some(s) => match &s.winner_id {
+                        Some(winner) => format!("sold to {} for {}", winner, s.price),
+                        None => "unsold".to_string(),
+                    },
+                    None if a.ends_at <= now => "ended".to_string(),
+                    None => "open".to_string(),
+                };
+                println!(
+                    "{} | role: {} | {} {} | reserve: {} | ends: {} | bids: {}{} | {}",
+                    a.id,
+                    a.role,
+                    a.format,
+                    a.pricing,
+                    a.reserve_price,
+                    a.ends_at,
+                    a.bids.len(),
+                    leading.unwrap_or_default(),
+                    status
+                );
+            }
+        }
         Commands::ListMarketplace => {
-            let state = load_state(&path).expect("load state");
+            let state = current_state(store).or_exit("load state");
             for l in state.marketplace.iter().filter(|l| l.active) {
                 println!(
                     "{} | role: {} | seller: {} | price: {} | active: {}",
@@ -514,15 +1412,28 @@ fn main() {
             }
         }
         Commands::ListVotes => {
-            let state = load_state(&path).expect("load state");
+            let state = current_state(store).or_exit("load state");
+            let now = Utc::now();
             for v in state.votes.values() {
+                let status = if v.resolved {
+                    if v.passed() { "passed" } else { "failed" }
+                } else if v.terms.is_some_and(|t| t.closes_at <= now) {
+                    "expired"
+                } else {
+                    "open"
+                };
+                let tally = tally_vote(&state, v);
                 println!(
-                    "{} | role: {} | holder: {} | votes: {} | resolved: {}",
+                    "{} | {} | votes: {} | cast: {}/{} | closes: {} | {}",
                     v.id,
-                    v.target_role,
-                    v.target_holder,
+                    v.proposal,
                     v.votes.len(),
-                    v.resolved
+                    tally.cast,
+                    tally.eligible,
+                    v.terms
+                        .map(|t| t.closes_at.to_rfc3339())
+                        .unwrap_or_else(|| "-".into()),
+                    status
                 );
             }
         }
@@ -543,29 +1454,42 @@ fn main() {
             deliverables,
             max_loc,
         } => {
-            let mut state = load_state(&path).expect("load state");
-            let dod_list = parse_dod(&dod);
-            let deliv = parse_deliverables(&deliverables);
-            pm_create_task(&mut state, &id, &title, &summary, dod_list, deliv, max_loc);
-            save_state(&path, &state).expect("save state");
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::TaskCreated {
+                    id: id.clone(),
+                    title,
+                    summary,
+                    definition_of_done: parse_dod(&dod),
+                    deliverables: parse_deliverables(&deliverables),
+                    max_total_loc: max_loc,
+                })
+            })
+            .or_exit("create task");
             println!("Created task {}", id);
         }
         Commands::PmReadyTask { id } => {
-            let mut state = load_state(&path).expect("load state");
-            pm_ready_task(&mut state, &id).expect("ready task");
-            save_state(&path, &state).expect("save state");
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::TaskReady { id: id.clone() })
+            })
+            .or_exit("ready task");
             println!("Task ready {}", id);
         }
         Commands::PmAssignTask { id, assignee, role } => {
-            let mut state = load_state(&path).expect("load state");
-            pm_assign_task(&mut state, &id, &assignee, &role).expect("assign task");
-            save_state(&path, &state).expect("save state");
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::TaskAssigned {
+                    id: id.clone(),
+                    assignee_id: assignee,
+                    role,
+                })
+            })
+            .or_exit("assign task");
             println!("Assigned task {}", id);
         }
         Commands::PmSubmitReview { id } => {
-            let mut state = load_state(&path).expect("load state");
-            pm_submit_for_review(&mut state, &id).expect("submit review");
-            save_state(&path, &state).expect("save state");
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::TaskSubmittedForReview { id: id.clone() })
+            })
+            .or_exit("submit review");
             println!("Task in review {}", id);
         }
         Commands::PmFinalizeTask {
@@ -573,13 +1497,18 @@ fn main() {
             loc_changed,
             tests_run,
         } => {
-            let mut state = load_state(&path).expect("load state");
-            pm_finalize_task(&mut state, &id, loc_changed, tests_run).expect("finalize task");
-            save_state(&path, &state).expect("save state");
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::TaskFinalized {
+                    id: id.clone(),
+                    loc_changed,
+                    tests_run,
+                })
+            })
+            .or_exit("finalize task");
             println!("Task finalized {}", id);
         }
         Commands::ListTasks => {
-            let state = load_state(&path).expect("load state");
+            let state = current_state(store).or_exit("load state");
             for t in state.tasks.values() {
                 println!(
                     "{} | {} | status: {:?} | deliverables: {}",
@@ -591,9 +1520,13 @@ fn main() {
             }
         }
         Commands::SeedRoles { holder_id, name } => {
-            let mut state = load_state(&path).expect("load state");
-            seed_roles(&mut state, &holder_id, &name).expect("seed roles");
-            save_state(&path, &state).expect("save state");
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::RolesSeeded {
+                    holder_id: holder_id.clone(),
+                    name,
+                })
+            })
+            .or_exit("seed roles");
             println!("Seeded roles with {} as CEO + Board seat", holder_id);
         }
         Commands::TokenomicsSet {
@@ -601,25 +1534,46 @@ fn main() {
             minted_supply,
             allocations,
         } => {
-            let mut state = load_state(&path).expect("load state");
-            let allocs = parse_allocations_config(&allocations);
-            set_tokenomics(&mut state, total_supply_cap, minted_supply, allocs);
-            save_state(&path, &state).expect("save state");
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::TokenomicsSet {
+                    total_supply_cap,
+                    minted_supply,
+                    allocations: parse_allocations_config(&allocations),
+                })
+            })
+            .or_exit("set tokenomics");
             println!("Tokenomics saved");
         }
         Commands::TokenomicsReport => {
-            let state = load_state(&path).expect("load state");
+            let state = current_state(store).or_exit("load state");
             if let Some(report) = tokenomics_report(&state) {
                 println!("{}", serde_json::to_string_pretty(&report).unwrap());
             } else {
                 println!("No tokenomics configured");
             }
         }
-        Commands::GrantTokens { holder_id, amount } => {
-            let mut state = load_state(&path).expect("load state");
-            grant_tokens(&mut state, &holder_id, amount).expect("grant tokens");
-            save_state(&path, &state).expect("save state");
-            println!("Granted {} tokens to {}", amount, holder_id);
+        Commands::GrantTokens {
+            holder_id,
+            amount,
+            vest_days,
+            vest_cliff_days,
+        } => {
+            let vesting = vesting_terms(vest_days, vest_cliff_days);
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::TokensGranted {
+                    holder_id: holder_id.clone(),
+                    amount,
+                    vesting,
+                })
+            })
+            .or_exit("grant tokens");
+            match vesting {
+                Some(t) => println!(
+                    "Granted {} tokens to {}, vesting over {} days ({}-day cliff)",
+                    amount, holder_id, t.duration_days, t.cliff_days
+                ),
+                None => println!("Granted {} tokens to {}", amount, holder_id),
+            }
         }
         Commands::Onboard {
             id,
@@ -627,17 +1581,508 @@ fn main() {
             cash,
             early_limit,
             early_reward,
+            early_vest_days,
+            early_vest_cliff_days,
         } => {
-            let mut state = load_state(&path).expect("load state");
-            if early_limit > 0 {
-                state.onboarding_policy.early_joiner_limit = early_limit;
+            let early_vesting = vesting_terms(early_vest_days, early_vest_cliff_days);
+            let rewarded = update(store, actor, |tx| {
+                if early_limit > 0 || early_reward > Amount::ZERO || early_vesting.is_some() {
+                    tx.record(CompanyEvent::OnboardingPolicyUpdated {
+                        early_joiner_limit: (early_limit > 0).then_some(early_limit),
+                        early_joiner_reward: (early_reward > Amount::ZERO).then_some(early_reward),
+                        early_joiner_vesting: early_vesting,
+                    })?;
+                }
+                tx.record(CompanyEvent::HolderOnboarded {
+                    id: id.clone(),
+                    name,
+                    cash,
+                })?;
+                // New holders start with no tokens, so any balance is the reward.
+                let state = tx.state();
+                Ok(state.holders[&id].tokens > Amount::ZERO
+                    || unvested_tokens(state, &id) > Amount::ZERO)
+            })
+            .or_exit("onboard");
+            println!("Onboarded {} (rewarded: {})", id, rewarded);
+        }
+        Commands::Transfer {
+            from,
+            to,
+            amount,
+            memo,
+        } => {
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::TokensTransferred {
+                    from: from.clone(),
+                    to: to.clone(),
+                    amount,
+                    memo,
+                })
+            })
+            .or_exit("transfer");
+            println!("Transferred {} tokens from {} to {}", amount, from, to);
+        }
+        Commands::TransferPolicy {
+            lockup_days,
+            max_per_day,
+            no_daily_limit,
+            block_during_removal_vote,
+        } => {
+            let changed = lockup_days.is_some()
+                || max_per_day.is_some()
+                || no_daily_limit
+                || block_during_removal_vote.is_some();
+            let policy = if changed {
+                update(store, actor, |tx| {
+                    let mut policy = tx.state().transfer_policy.clone();
+                    if let Some(days) = lockup_days {
+                        policy.lockup_days = days;
+                    }
+                    if max_per_day.is_some() || no_daily_limit {
+                        policy.max_per_day = max_per_day;
+                    }
+                    if let Some(block) = block_during_removal_vote {
+                        policy.block_during_removal_vote = block;
+                    }
+                    tx.record(CompanyEvent::TransferPolicySet {
+                        policy: policy.clone(),
+                    })?;
+                    Ok(policy)
+                })
+                .or_exit("transfer policy")
+            } else {
+                current_state(store).or_exit("load state").transfer_policy
+            };
+            println!("{}", serde_json::to_string_pretty(&policy).unwrap());
+        }
+        Commands::Stake { holder, amount } => {
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::TokensStaked {
+                    holder_id: holder.clone(),
+                    amount,
+                })
+            })
+            .or_exit("stake");
+            println!("Staked {} tokens for {}", amount, holder);
+        }
+        Commands::Unstake { holder, amount } => {
+            let release_at = update(store, actor, |tx| {
+                tx.record(CompanyEvent::TokensUnstaked {
+                    holder_id: holder.clone(),
+                    amount,
+                })?;
+                let stake = &tx.state().stakes[&holder];
+                Ok(stake.unbonding.last().map(|u| u.release_at))
+            })
+            .or_exit("unstake");
+            if let Some(at) = release_at {
+                println!(
+                    "Unbonding {} tokens for {} until {}",
+                    amount,
+                    holder,
+                    at.to_rfc3339()
+                );
+            }
+        }
+        Commands::ClaimUnbonded { holder } => {
+            let claimed = update(store, actor, |tx| {
+                let before = tx.state().holders.get(&holder).map(|h| h.tokens);
+                tx.record(CompanyEvent::UnbondedClaimed {
+                    holder_id: holder.clone(),
+                })?;
+                Ok(tx.state().holders[&holder].tokens - before.unwrap_or_default())
+            })
+            .or_exit("claim unbonded");
+            println!("Returned {} unbonded tokens to {}", claimed, holder);
+        }
+        Commands::StakingPolicy {
+            unbonding_days,
+            role_multipliers,
+        } => {
+            let policy = if unbonding_days.is_some() || !role_multipliers.is_empty() {
+                update(store, actor, |tx| {
+                    let mut policy = tx.state().staking_policy.clone();
+                    if let Some(days) = unbonding_days {
+                        policy.unbonding_days = days;
+                    }
+                    for (role, multiplier) in role_multipliers {
+                        if multiplier == 1.0 {
+                            policy.role_multipliers.remove(&role);
+                        } else {
+                            policy.role_multipliers.insert(role, multiplier);
+                        }
+                    }
+                    tx.record(CompanyEvent::StakingPolicySet {
+                        policy: policy.clone(),
+                    })?;
+                    Ok(policy)
+                })
+                .or_exit("staking policy")
+            } else {
+                current_state(store).or_exit("load state").staking_policy
+            };
+            println!("{}", serde_json::to_string_pretty(&policy).unwrap());
+        }
+        Commands::PromotionPolicy {
+            role,
+            requires,
+            no_prerequisite,
+            min_days_in_role,
+            min_completed_tasks,
+            min_stake,
+            remove,
+        } => {
+            let policy = match role {
+                Some(role) => update(store, actor, |tx| {
+                    let mut policy = tx.state().promotion_policy.clone();
+                    if remove {
+                        policy.rungs.remove(&role);
+                    } else {
+                        let rung = policy.rungs.entry(role).or_insert_with(|| PromotionRung {
+                            requires: role.below(),
+                            ..Default::default()
+                        });
+                        if requires.is_some() || no_prerequisite {
+                            rung.requires = requires;
+                        }
+                        if let Some(days) = min_days_in_role {
+                            rung.min_days_in_role = days;
+                        }
+                        if let Some(tasks) = min_completed_tasks {
+                            rung.min_completed_tasks = tasks;
+                        }
+                        if let Some(stake) = min_stake {
+                            rung.min_stake = stake;
+                        }
+                    }
+                    tx.record(CompanyEvent::PromotionPolicySet {
+                        policy: policy.clone(),
+                    })?;
+                    Ok(policy)
+                })
+                .or_exit("promotion policy"),
+                None => current_state(store).or_exit("load state").promotion_policy,
+            };
+            println!("{}", serde_json::to_string_pretty(&policy).unwrap());
+        }
+        Commands::Eligibility { holder, role } => {
+            let state = current_state(store).or_exit("load state");
+            let now = Utc::now();
+            let roles: Vec<RoleTier> = match role {
+                Some(role) => vec![role],
+                None => state.promotion_policy.rungs.keys().copied().collect(),
+            };
+            let h = state
+                .holders
+                .get(&holder)
+                .ok_or_else(|| EngineError::not_found(EntityKind::Holder, &holder))
+                .or_exit("eligibility");
+            let staked = state
+                .stakes
+                .get(&holder)
+                .map(|s| s.staked)
+                .unwrap_or_default();
+            println!(
+                "{} | roles: {:?} | completed tasks: {} | staked: {}",
+                holder,
+                h.positions,
+                completed_tasks(&state, &holder),
+                staked
+            );
+            if roles.is_empty() {
+                println!("No promotion rules; every role is open");
             }
-            if early_reward > 0.0 {
-                state.onboarding_policy.early_joiner_reward = early_reward;
+            for role in roles {
+                if h.positions.contains(&role) {
+                    println!("{}: held", role);
+                    continue;
+                }
+                let gaps = promotion_gaps(&state, &holder, role, now).or_exit("eligibility");
+                if gaps.is_empty() {
+                    println!("{}: eligible", role);
+                    continue;
+                }
+                println!("{}: not eligible", role);
+                for gap in gaps {
+                    println!("  - {}", gap);
+                }
+            }
+        }
+        Commands::VotingPower {
+            policy,
+            role_weights,
+        } => {
+            let policy = if policy.is_some() || !role_weights.is_empty() {
+                update(store, actor, |tx| {
+                    let mut policy =
+                        policy.unwrap_or_else(|| tx.state().governance_policy.voting_power.clone());
+                    if !role_weights.is_empty() {
+                        let VotingPowerPolicy::RoleTierWeighted { weights } = &mut policy else {
+                            return Err(EngineError::invalid(
+                                "role weights only apply to the role_tier policy",
+                            ));
+                        };
+                        weights.extend(role_weights);
+                    }
+                    tx.record(CompanyEvent::VotingPowerPolicySet {
+                        policy: policy.clone(),
+                    })?;
+                    Ok(policy)
+                })
+                .or_exit("voting power")
+            } else {
+                current_state(store)
+                    .or_exit("load state")
+                    .governance_policy
+                    .voting_power
+            };
+            println!("{}", serde_json::to_string_pretty(&policy).unwrap());
+        }
+        Commands::GovernancePolicy { changes } => {
+            let changes = changes.into_patch();
+            let policy = if !changes.is_empty() {
+                update(store, actor, |tx| {
+                    let policy = changes.apply(tx.state().governance_policy.clone());
+                    tx.record(CompanyEvent::GovernancePolicySet {
+                        policy: policy.clone(),
+                    })?;
+                    Ok(policy)
+                })
+                .or_exit("governance policy")
+            } else {
+                current_state(store).or_exit("load state").governance_policy
+            };
+            println!("{}", serde_json::to_string_pretty(&policy).unwrap());
+        }
+        Commands::Propose {
+            vote_id,
+            reason,
+            closes_at,
+            proposal,
+        } => {
+            let (proposal, terms) = update(store, actor, |tx| {
+                let proposal = proposal.into_proposal();
+                let mut terms = vote_terms(tx.state(), Utc::now());
+                if let Some(closes_at) = closes_at {
+                    terms.closes_at = closes_at;
+                }
+                tx.record(CompanyEvent::ProposalCreated {
+                    vote_id: vote_id.clone(),
+                    proposal: proposal.clone(),
+                    reason,
+                    terms,
+                })?;
+                Ok((proposal, terms))
+            })
+            .or_exit("propose");
+            println!(
+                "Created vote {} to {} (closes {}, quorum {})",
+                vote_id,
+                proposal,
+                terms.closes_at.to_rfc3339(),
+                terms.quorum
+            );
+        }
+        Commands::ListStakes => {
+            let state = current_state(store).or_exit("load state");
+            let mut stakes: Vec<&Stake> = state.stakes.values().collect();
+            stakes.sort_by(|a, b| a.holder_id.cmp(&b.holder_id));
+            for stake in stakes {
+                let unbonding: Amount = stake.unbonding.iter().map(|u| u.amount).sum();
+                println!(
+                    "{} | staked: {} | unbonding: {} | voting power: {}",
+                    stake.holder_id,
+                    stake.staked,
+                    unbonding,
+                    voting_power(&state, &stake.holder_id)
+                );
+            }
+        }
+        Commands::Vest { now } => {
+            let as_of = now.unwrap_or_else(Utc::now);
+            let released = update(store, actor, |tx| {
+                let before: Amount = tx.state().holders.values().map(|h| h.tokens).sum();
+                tx.record(CompanyEvent::VestingAccrued { as_of })?;
+                let after: Amount = tx.state().holders.values().map(|h| h.tokens).sum();
+                Ok(after - before)
+            })
+            .or_exit("vest");
+            println!(
+                "Released {} vested tokens as of {}",
+                released,
+                as_of.to_rfc3339()
+            );
+        }
+        Commands::VestingSetRole {
+            role,
+            days,
+            cliff_days,
+            clear: _,
+        } => {
+            let terms = vesting_terms(days, cliff_days);
+            update(store, actor, |tx| {
+                tx.record(CompanyEvent::RoleVestingSet { role, terms })
+            })
+            .or_exit("set role vesting");
+            match terms {
+                Some(t) => println!(
+                    "Distributions to {} now vest over {} days ({}-day cliff)",
+                    role, t.duration_days, t.cliff_days
+                ),
+                None => println!("Distributions to {} no longer vest", role),
+            }
+        }
+        Commands::Vesting { holder } => {
+            let state = current_state(store).or_exit("load state");
+            for s in state
+                .vesting_schedules
+                .iter()
+                .filter(|s| holder.as_ref().is_none_or(|h| *h == s.holder_id))
+            {
+                println!(
+                    "#{} | {} | started {} | cliff {}d / {}d | total: {} | released: {} | forfeited: {} | unvested: {}",
+                    s.id,
+                    s.holder_id,
+                    s.start.to_rfc3339(),
+                    s.terms.cliff_days,
+                    s.terms.duration_days,
+                    s.total,
+                    s.released,
+                    s.forfeited,
+                    s.unvested()
+                );
+            }
+        }
+        Commands::Events { limit } => {
+            let events = store.load_events(0).or_exit("load events");
+            let skip = limit.map_or(0, |n| events.len().saturating_sub(n));
+            for recorded in &events[skip..] {
+                println!(
+                    "#{} | {} | actor: {} | {}",
+                    recorded.seq,
+                    recorded.at.to_rfc3339(),
+                    recorded.actor.as_deref().unwrap_or("-"),
+                    serde_json::to_string(&recorded.event).unwrap()
+                );
+            }
+        }
+        Commands::Verify => {
+            let state = current_state(store).or_exit("load state");
+            let violations = verify(&state);
+            if violations.is_empty() {
+                println!("State OK");
+            } else {
+                for v in &violations {
+                    println!("{}", v);
+                    eprintln!("{}", serde_json::to_string(v).unwrap());
+                }
+                eprintln!("verify: {} violation(s)", violations.len());
+                std::process::exit(1);
+            }
+        }
+        Commands::Rebuild => {
+            let state = rebuild(store).or_exit("rebuild");
+            println!("Rebuilt state from {} events", state.event_seq);
+        }
+        Commands::Ledger {
+            account: Some(account),
+        } => {
+            let state = current_state(store).or_exit("load state");
+            let lines = statement(&state, &account);
+            if lines.is_empty() {
+                println!("No entries for {}", account);
+            }
+            for l in lines {
+                println!(
+                    "#{} | {} | {} | {} | {:+} | balance: {}",
+                    l.entry_id,
+                    l.at.to_rfc3339(),
+                    l.asset,
+                    l.memo,
+                    l.amount,
+                    l.balance
+                );
+            }
+        }
+        Commands::Ledger { account: None } => {
+            let state = current_state(store).or_exit("load state");
+            let mut totals: BTreeMap<Asset, Amount> = BTreeMap::new();
+            for ((account, asset), balance) in trial_balance(&state) {
+                let total = totals.entry(asset).or_default();
+                *total = *total + balance;
+                println!("{} | {} | {}", account, asset, balance);
+            }
+            for (asset, total) in totals {
+                println!("Total {}: {}", asset, total);
+            }
+            for d in reconcile(&state) {
+                println!(
+                    "MISMATCH {} | {} | cached: {} | journal: {}",
+                    d.account, d.asset, d.cached, d.derived
+                );
+            }
+        }
+        Commands::StateAt { timestamp } => {
+            let events = store.load_events(0).or_exit("load events");
+            let Some(state) = state_at(&events, timestamp).or_exit("replay") else {
+                println!("No company state as of {}", timestamp.to_rfc3339());
+                return;
+            };
+            println!(
+                "As of {} (event #{})",
+                timestamp.to_rfc3339(),
+                state.event_seq
+            );
+            println!("Employees: {}", state.employee_count);
+            println!("Treasury tokens: {}", state.treasury_tokens);
+            println!("Treasury cash: {}", state.treasury_cash);
+            for p in &state.positions {
+                println!(
+                    "position {} | holder: {} | acquired: {}",
+                    p.tier,
+                    p.holder_id.as_deref().unwrap_or("-"),
+                    p.acquired_at.map_or("-".into(), |t| t.to_rfc3339())
+                );
+            }
+            for holder in state.holders.values() {
+                println!(
+                    "{} | {} | tokens: {} | cash: {} | roles: {:?}",
+                    holder.id, holder.display_name, holder.tokens, holder.cash, holder.positions
+                );
+            }
+        }
+        Commands::History { holder, timestamp } => {
+            let events = store.load_events(0).or_exit("load events");
+            let until = timestamp.unwrap_or_else(Utc::now);
+            let changes = holder_history(&events, &holder, until).or_exit("replay");
+            for c in &changes {
+                let after = match &c.snapshot {
+                    Some(s) => format!(
+                        "tokens: {} | cash: {} | roles: {:?}",
+                        s.tokens, s.cash, s.positions
+                    ),
+                    None => "removed".into(),
+                };
+                println!(
+                    "#{} | {} | actor: {} | {} | {}",
+                    c.seq,
+                    c.at.to_rfc3339(),
+                    c.actor.as_deref().unwrap_or("-"),
+                    c.event,
+                    after
+                );
+            }
+            match changes.last().and_then(|c| c.snapshot.as_ref()) {
+                Some(s) => println!(
+                    "As of {}: tokens: {} | cash: {} | roles: {:?}",
+                    until.to_rfc3339(),
+                    s.tokens,
+                    s.cash,
+                    s.positions
+                ),
+                None => println!("{} was not a holder as of {}", holder, until.to_rfc3339()),
             }
-            let rewarded = auto_onboard(&mut state, &id, &name, cash).expect("onboard");
-            save_state(&path, &state).expect("save state");
-            println!("Onboarded {} (rewarded: {})", id, rewarded);
         }
     }
 }
@@ -1,10 +1,11 @@
+use crate::amount::Amount;
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum RoleTier {
     Employee,
     Manager,
@@ -34,6 +35,24 @@ impl fmt::Display for RoleTier {
     }
 }
 
+impl RoleTier {
+    /// The tier directly below this one, if any.
+    pub fn below(self) -> Option<RoleTier> {
+        use RoleTier::*;
+        match self {
+            Employee => None,
+            Manager => Some(Employee),
+            SeniorManager => Some(Manager),
+            Director => Some(SeniorManager),
+            CSuite => Some(Director),
+            President => Some(CSuite),
+            CoPresident => Some(President),
+            CEO => Some(CoPresident),
+            BoardSeat => Some(CEO),
+        }
+    }
+}
+
 impl FromStr for RoleTier {
     type Err = String;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -52,49 +71,506 @@ impl FromStr for RoleTier {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Asset {
+    Cash,
+    Tokens,
+}
+
+impl fmt::Display for Asset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Asset::Cash => "cash",
+            Asset::Tokens => "tokens",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for Asset {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "cash" => Ok(Asset::Cash),
+            "tokens" | "token" => Ok(Asset::Tokens),
+            _ => Err(format!("Unknown asset: {}", s)),
+        }
+    }
+}
+
+/// A ledger account. Holder and treasury accounts carry real balances;
+/// the others are the counterparties money and tokens flow in from or out to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum Account {
+    Treasury,
+    Holder(String),
+    Revenue,
+    Refunds,
+    Mint,
+    Burn,
+    External,
+    /// Granted tokens still waiting on a vesting schedule.
+    Vesting,
+    /// Tokens staked for voting power, including those unbonding.
+    Staking,
+    /// Cash bid in auctions that are still running.
+    Escrow,
+}
+
+impl fmt::Display for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Account::Treasury => write!(f, "treasury"),
+            Account::Holder(id) => write!(f, "holder:{}", id),
+            Account::Revenue => write!(f, "revenue"),
+            Account::Refunds => write!(f, "refunds"),
+            Account::Mint => write!(f, "mint"),
+            Account::Burn => write!(f, "burn"),
+            Account::External => write!(f, "external"),
+            Account::Vesting => write!(f, "vesting"),
+            Account::Staking => write!(f, "staking"),
+            Account::Escrow => write!(f, "escrow"),
+        }
+    }
+}
+
+impl FromStr for Account {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "treasury" => Ok(Account::Treasury),
+            "revenue" => Ok(Account::Revenue),
+            "refunds" => Ok(Account::Refunds),
+            "mint" => Ok(Account::Mint),
+            "burn" => Ok(Account::Burn),
+            "external" => Ok(Account::External),
+            "vesting" => Ok(Account::Vesting),
+            "staking" => Ok(Account::Staking),
+            "escrow" => Ok(Account::Escrow),
+            _ => match s.strip_prefix("holder:") {
+                Some(id) if !id.is_empty() => Ok(Account::Holder(id.to_string())),
+                _ => Err(format!("Unknown account: {}", s)),
+            },
+        }
+    }
+}
+
+impl From<Account> for String {
+    fn from(account: Account) -> Self {
+        account.to_string()
+    }
+}
+
+impl TryFrom<String> for Account {
+    type Error = String;
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// One leg of a journal entry; positive amounts increase the account.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Posting {
+    pub account: Account,
+    pub amount: Amount,
+}
+
+/// A balanced movement of one asset: its postings always sum to zero.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: u64,
+    pub at: DateTime<Utc>,
+    pub asset: Asset,
+    pub memo: String,
+    pub postings: Vec<Posting>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RolePosition {
     pub tier: RoleTier,
     pub holder_id: Option<String>,
     pub acquired_at: Option<DateTime<Utc>>,
-    pub price_paid: Option<f64>,
+    pub price_paid: Option<Amount>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Holder {
     pub id: String,
     pub display_name: String,
-    pub tokens: f64,
-    pub cash: f64,
+    pub tokens: Amount,
+    pub cash: Amount,
     pub positions: Vec<RoleTier>,
+    /// When the holder became an employee. States from before this was
+    /// tracked are backfilled from the journal when loaded; holders with no
+    /// entries stay unknown.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub employee_since: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RevenueEvent {
     pub timestamp: DateTime<Utc>,
-    pub gross_revenue: f64,
-    pub refund_amount: f64,
+    pub gross_revenue: Amount,
+    pub refund_amount: Amount,
 }
 
+/// The emission for one ISO week, kept so the week is never emitted twice.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeeklyEmission {
+    /// ISO week id, e.g. `2026-W07`.
+    pub week: String,
+    pub as_of: DateTime<Utc>,
+    pub net_revenue: Amount,
+    pub minted: Amount,
+    pub withheld: Amount,
+}
+
+/// Where a payout run takes its allocation weights from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PayoutSource {
+    /// Tasks finalized during the week.
+    Tasks,
+    /// A named [`crate::engine::allocation_template`].
+    Template { name: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayoutShare {
+    pub holder_id: String,
+    pub weight: f64,
+    pub tokens: Amount,
+    /// Set when the share was paid into a vesting schedule.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vesting_schedule: Option<u64>,
+}
+
+/// One week's emission-and-distribution, keyed by ISO week.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayoutRun {
+    pub week: String,
+    pub run_at: DateTime<Utc>,
+    pub source: PayoutSource,
+    /// Whether this run minted the week's emission, rather than reusing an
+    /// earlier `emit-weekly`.
+    pub emitted: bool,
+    pub minted: Amount,
+    pub distributed: Amount,
+    pub shares: Vec<PayoutShare>,
+    pub reversed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EmissionPolicy {
     pub weekly_payout_percent: f64, // 20% of revenue
     pub halving_interval_days: i64, // every year
     pub genesis: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Emission policy fields to change; the rest keep whatever value is
+/// current when the patch is applied.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EmissionPolicyPatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weekly_payout_percent: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub halving_interval_days: Option<i64>,
+}
+
+impl EmissionPolicyPatch {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// `policy` with the patched fields changed.
+    pub fn apply(&self, mut policy: EmissionPolicy) -> EmissionPolicy {
+        if let Some(percent) = self.weekly_payout_percent {
+            policy.weekly_payout_percent = percent;
+        }
+        if let Some(days) = self.halving_interval_days {
+            policy.halving_interval_days = days;
+        }
+        policy
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GovernancePolicy {
     pub vote_threshold: f64,     // 2/3
     pub value_drop_trigger: f64, // 20% drop
     pub value_window_days: i64,  // 30-day average
+    #[serde(default)]
+    pub voting_power: VotingPowerPolicy,
+    /// Whether a voter may re-cast their ballot while the vote is open.
+    #[serde(default = "allow_vote_changes_default")]
+    pub allow_vote_changes: bool,
+    /// Share of all eligible voting power that must cast a ballot.
+    #[serde(default = "quorum_default")]
+    pub quorum: f64,
+    /// How long a new vote stays open for ballots.
+    #[serde(default = "voting_period_days_default")]
+    pub voting_period_days: i64,
+    /// Who may vote on which proposals; anyone may vote where no rule applies.
+    #[serde(default)]
+    pub eligibility: Vec<EligibilityRule>,
+    /// Roles whose holders face a removal vote when the token value drops.
+    #[serde(default = "accountable_roles_default")]
+    pub accountable_roles: Vec<RoleTier>,
+    /// Minimum time between value drops that open votes.
+    #[serde(default = "value_drop_cooldown_days_default")]
+    pub value_drop_cooldown_days: i64,
+    /// How the token price is averaged over the value window.
+    #[serde(default)]
+    pub price_average: PriceAverage,
+}
+
+/// Governance policy fields to change; the rest keep whatever value is
+/// current when the patch is applied.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GovernancePolicyPatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vote_threshold: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_drop_trigger: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_window_days: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_vote_changes: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quorum: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voting_period_days: Option<i64>,
+    /// Drop every eligibility rule before adding `eligibility`.
+    #[serde(default)]
+    pub clear_eligibility: bool,
+    /// Rules to add, each replacing any rule for the same kind and role.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub eligibility: Vec<EligibilityRule>,
+    /// Replaces the whole list when set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accountable_roles: Option<Vec<RoleTier>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_drop_cooldown_days: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_average: Option<PriceAverage>,
+}
+
+impl GovernancePolicyPatch {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// `policy` with the patched fields changed.
+    pub fn apply(&self, mut policy: GovernancePolicy) -> GovernancePolicy {
+        if let Some(threshold) = self.vote_threshold {
+            policy.vote_threshold = threshold;
+        }
+        if let Some(trigger) = self.value_drop_trigger {
+            policy.value_drop_trigger = trigger;
+        }
+        if let Some(days) = self.value_window_days {
+            policy.value_window_days = days;
+        }
+        if let Some(allow) = self.allow_vote_changes {
+            policy.allow_vote_changes = allow;
+        }
+        if let Some(quorum) = self.quorum {
+            policy.quorum = quorum;
+        }
+        if let Some(days) = self.voting_period_days {
+            policy.voting_period_days = days;
+        }
+        if self.clear_eligibility {
+            policy.eligibility.clear();
+        }
+        for rule in &self.eligibility {
+            policy
+                .eligibility
+                .retain(|r| (r.kind, r.target_role) != (rule.kind, rule.target_role));
+            policy.eligibility.push(rule.clone());
+        }
+        if let Some(roles) = &self.accountable_roles {
+            policy.accountable_roles = roles.clone();
+        }
+        if let Some(days) = self.value_drop_cooldown_days {
+            policy.value_drop_cooldown_days = days;
+        }
+        if let Some(average) = self.price_average {
+            policy.price_average = average;
+        }
+        policy
+    }
+}
+
+/// How recorded token prices are averaged over the value window. The plain
+/// mean counts every sample equally, so a burst of samples outweighs a
+/// long stretch of quiet; the other methods weigh samples by time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PriceAverage {
+    #[default]
+    Mean,
+    /// Each price weighted by how long it stood before the next sample.
+    TimeWeighted,
+    Median,
+    /// Time-decayed moving average; a sample's influence halves every
+    /// `half_life_days`, however many samples follow it.
+    Exponential {
+        half_life_days: f64,
+    },
+}
+
+impl PriceAverage {
+    pub const DEFAULT_HALF_LIFE_DAYS: f64 = 7.0;
+}
+
+impl fmt::Display for PriceAverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PriceAverage::Mean => write!(f, "mean"),
+            PriceAverage::TimeWeighted => write!(f, "twap"),
+            PriceAverage::Median => write!(f, "median"),
+            PriceAverage::Exponential { half_life_days } => {
+                write!(f, "ema:{}", half_life_days)
+            }
+        }
+    }
+}
+
+impl FromStr for PriceAverage {
+    type Err = String;
+    /// Accepts `mean`, `twap`, `median`, `ema` or `ema:<half-life days>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_lowercase();
+        let (name, arg) = match lower.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (lower.as_str(), None),
+        };
+        let average = match name {
+            "mean" => PriceAverage::Mean,
+            "twap" | "time_weighted" => PriceAverage::TimeWeighted,
+            "median" => PriceAverage::Median,
+            "ema" | "exponential" => {
+                let half_life_days = match arg {
+                    Some(days) => days
+                        .parse()
+                        .map_err(|_| format!("Invalid half-life: {}", days))?,
+                    None => PriceAverage::DEFAULT_HALF_LIFE_DAYS,
+                };
+                return Ok(PriceAverage::Exponential { half_life_days });
+            }
+            _ => return Err(format!("Unknown price average: {}", s)),
+        };
+        match arg {
+            Some(_) => Err(format!("{} takes no parameter", name)),
+            None => Ok(average),
+        }
+    }
+}
+
+fn accountable_roles_default() -> Vec<RoleTier> {
+    vec![RoleTier::CEO, RoleTier::CSuite]
+}
+
+fn value_drop_cooldown_days_default() -> i64 {
+    7
+}
+
+/// Restricts voting on a kind of proposal to holders of `min_role` or a
+/// higher role. A rule naming a `target_role` applies only to proposals
+/// about that role and takes precedence over one that does not.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EligibilityRule {
+    pub kind: ProposalKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_role: Option<RoleTier>,
+    pub min_role: RoleTier,
+}
+
+fn allow_vote_changes_default() -> bool {
+    true
+}
+
+fn quorum_default() -> f64 {
+    0.5
+}
+
+fn voting_period_days_default() -> i64 {
+    7
+}
+
+/// How the engine turns a holder into a ballot weight.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum VotingPowerPolicy {
+    /// Tokens held.
+    #[default]
+    TokenWeighted,
+    /// Staked tokens times the staking role multiplier.
+    StakeWeighted,
+    OneHolderOneVote,
+    /// The weight of the highest-weighted role the holder has.
+    RoleTierWeighted {
+        weights: BTreeMap<RoleTier, f64>,
+    },
+    /// Square root of the tokens held.
+    Quadratic,
+}
+
+impl VotingPowerPolicy {
+    /// Role-tier weighting with the default ladder, one step per tier.
+    pub fn role_tier_default() -> Self {
+        let weights = [
+            (RoleTier::Employee, 1.0),
+            (RoleTier::Manager, 2.0),
+            (RoleTier::SeniorManager, 3.0),
+            (RoleTier::Director, 4.0),
+            (RoleTier::CSuite, 5.0),
+            (RoleTier::President, 6.0),
+            (RoleTier::CoPresident, 6.0),
+            (RoleTier::CEO, 7.0),
+            (RoleTier::BoardSeat, 7.0),
+        ];
+        VotingPowerPolicy::RoleTierWeighted {
+            weights: weights.into_iter().collect(),
+        }
+    }
+}
+
+impl fmt::Display for VotingPowerPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            VotingPowerPolicy::TokenWeighted => "token",
+            VotingPowerPolicy::StakeWeighted => "stake",
+            VotingPowerPolicy::OneHolderOneVote => "one_holder",
+            VotingPowerPolicy::RoleTierWeighted { .. } => "role_tier",
+            VotingPowerPolicy::Quadratic => "quadratic",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for VotingPowerPolicy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "token" | "token_weighted" => Ok(VotingPowerPolicy::TokenWeighted),
+            "stake" | "stake_weighted" => Ok(VotingPowerPolicy::StakeWeighted),
+            "one_holder" | "one_holder_one_vote" => Ok(VotingPowerPolicy::OneHolderOneVote),
+            "role_tier" | "role_tier_weighted" => Ok(VotingPowerPolicy::role_tier_default()),
+            "quadratic" => Ok(VotingPowerPolicy::Quadratic),
+            _ => Err(format!("Unknown voting power policy: {}", s)),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompanyState {
     pub holders: HashMap<String, Holder>,
-    pub treasury_tokens: f64,
-    pub treasury_cash: f64,
+    pub treasury_tokens: Amount,
+    pub treasury_cash: Amount,
     pub positions: Vec<RolePosition>,
     pub employee_count: usize,
     pub token_price_history: Vec<(DateTime<Utc>, f64)>,
@@ -106,13 +582,64 @@ pub struct CompanyState {
     pub tokenomics: Option<Tokenomics>,
     pub onboarding_policy: OnboardingPolicy,
     pub onboarding_count: usize,
+    /// Sequence number of the last event folded into this state.
+    #[serde(default)]
+    pub event_seq: u64,
+    /// Every token and cash movement; the balance fields above are caches of it.
+    #[serde(default)]
+    pub journal: Vec<JournalEntry>,
+    /// Every ingested revenue event, in ingestion order.
+    #[serde(default)]
+    pub revenue_events: Vec<RevenueEvent>,
+    /// Weeks already emitted for, oldest first.
+    #[serde(default)]
+    pub weekly_emissions: Vec<WeeklyEmission>,
+    /// Weekly payout runs, including reversed ones, oldest first.
+    #[serde(default)]
+    pub payout_runs: Vec<PayoutRun>,
+    #[serde(default)]
+    pub vesting_schedules: Vec<VestingSchedule>,
+    #[serde(default)]
+    pub vesting_policy: VestingPolicy,
+    #[serde(default)]
+    pub transfer_policy: TransferPolicy,
+    /// Holder-to-holder token transfers, oldest first.
+    #[serde(default)]
+    pub transfers: Vec<TokenTransfer>,
+    #[serde(default)]
+    pub stakes: HashMap<String, Stake>,
+    #[serde(default)]
+    pub staking_policy: StakingPolicy,
+    /// Standing vote delegations, at most one per holder and scope.
+    #[serde(default)]
+    pub delegations: Vec<Delegation>,
+    /// Value drops that tripped the safeguard, oldest first.
+    #[serde(default)]
+    pub value_drops: Vec<ValueDrop>,
+    /// Auctions for vacant role positions, running and settled.
+    #[serde(default)]
+    pub auctions: HashMap<String, Auction>,
+    #[serde(default)]
+    pub promotion_policy: PromotionPolicy,
+    /// Tokens each holder was sent by another holder, the treasury or the
+    /// mint, oldest first; what the transfer lock-up holds back.
+    #[serde(default)]
+    pub token_receipts: HashMap<String, Vec<TokenReceipt>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValueDrop {
+    pub at: DateTime<Utc>,
+    pub price: f64,
+    pub average: f64,
+    pub vote_ids: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PromotionBid {
     pub bidder_id: String,
     pub target_role: RoleTier,
-    pub bid_amount: f64,
+    pub bid_amount: Amount,
     pub timestamp: DateTime<Utc>,
 }
 
@@ -132,12 +659,287 @@ pub struct Vote {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VoteRecord {
     pub id: String,
-    pub target_role: RoleTier,
-    pub target_holder: String,
+    /// What passing the vote does.
+    #[serde(flatten, deserialize_with = "proposal_or_removal")]
+    pub proposal: Proposal,
     pub reason: String,
     pub created_at: DateTime<Utc>,
-    pub votes: Vec<Vote>,
+    /// The standing ballot of each voter, keyed by voter id.
+    #[serde(deserialize_with = "ballots_by_voter")]
+    pub votes: BTreeMap<String, Vote>,
+    /// Set once the vote is decided either way.
     pub resolved: bool,
+    /// Ballots that were replaced by a later one from the same voter.
+    #[serde(default)]
+    pub ballot_history: Vec<SupersededBallot>,
+    /// Absent on votes created before votes had deadlines; those pass as
+    /// soon as the ballots cast meet the threshold.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub terms: Option<VoteTerms>,
+    /// How the vote was decided; absent on votes resolved before failed
+    /// votes were recorded, which all passed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<VoteOutcome>,
+    /// Why a passed proposal could not be carried out, if it could not.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effect_error: Option<String>,
+}
+
+/// A change the company votes on, applied by the engine when the vote
+/// passes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Proposal {
+    /// Oust a holder from a role. Field names match the votes stored before
+    /// proposals existed, which could only do this.
+    RemoveFromRole {
+        target_role: RoleTier,
+        target_holder: String,
+    },
+    AppointToRole {
+        role: RoleTier,
+        holder_id: String,
+    },
+    /// Replace the whole governance policy, as proposed before proposals
+    /// carried only the changed fields; replay only.
+    SetGovernancePolicy {
+        policy: GovernancePolicy,
+    },
+    /// Replace the whole emission policy; replay only, like
+    /// `SetGovernancePolicy`.
+    SetEmissionPolicy {
+        policy: EmissionPolicy,
+    },
+    /// Change some governance policy fields, laid over the policy in force
+    /// when the vote passes.
+    PatchGovernancePolicy {
+        changes: GovernancePolicyPatch,
+    },
+    /// Change some emission policy fields, laid over the policy in force
+    /// when the vote passes.
+    PatchEmissionPolicy {
+        changes: EmissionPolicyPatch,
+    },
+    /// Pay out of the treasury to a holder or an external party.
+    SpendTreasury {
+        to: Account,
+        asset: Asset,
+        amount: Amount,
+    },
+    /// Mint into the treasury, within the supply cap.
+    MintTokens {
+        amount: Amount,
+    },
+    /// Burn from the treasury. Burned tokens still count as minted against
+    /// the supply cap.
+    BurnTokens {
+        amount: Amount,
+    },
+    AmendAllocations {
+        allocations: Vec<TokenAllocation>,
+    },
+}
+
+impl Proposal {
+    pub fn kind(&self) -> ProposalKind {
+        match self {
+            Proposal::RemoveFromRole { .. } => ProposalKind::RemoveFromRole,
+            Proposal::AppointToRole { .. } => ProposalKind::AppointToRole,
+            Proposal::SetGovernancePolicy { .. } => ProposalKind::SetGovernancePolicy,
+            Proposal::SetEmissionPolicy { .. } => ProposalKind::SetEmissionPolicy,
+            Proposal::PatchGovernancePolicy { .. } => ProposalKind::SetGovernancePolicy,
+            Proposal::PatchEmissionPolicy { .. } => ProposalKind::SetEmissionPolicy,
+            Proposal::SpendTreasury { .. } => ProposalKind::SpendTreasury,
+            Proposal::MintTokens { .. } => ProposalKind::MintTokens,
+            Proposal::BurnTokens { .. } => ProposalKind::BurnTokens,
+            Proposal::AmendAllocations { .. } => ProposalKind::AmendAllocations,
+        }
+    }
+
+    /// The role a removal or appointment is about.
+    pub fn role(&self) -> Option<RoleTier> {
+        match self {
+            Proposal::RemoveFromRole { target_role, .. } => Some(*target_role),
+            Proposal::AppointToRole { role, .. } => Some(*role),
+            _ => None,
+        }
+    }
+
+    /// The holder a removal proposal would oust.
+    pub fn removal_target(&self) -> Option<&str> {
+        match self {
+            Proposal::RemoveFromRole { target_holder, .. } => Some(target_holder),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Proposal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Proposal::RemoveFromRole {
+                target_role,
+                target_holder,
+            } => write!(f, "remove {} from {}", target_holder, target_role),
+            Proposal::AppointToRole { role, holder_id } => {
+                write!(f, "appoint {} to {}", holder_id, role)
+            }
+            Proposal::SetGovernancePolicy { .. } | Proposal::PatchGovernancePolicy { .. } => {
+                write!(f, "change governance policy")
+            }
+            Proposal::SetEmissionPolicy { .. } | Proposal::PatchEmissionPolicy { .. } => {
+                write!(f, "change emission policy")
+            }
+            Proposal::SpendTreasury { to, asset, amount } => {
+                write!(f, "spend {} {} to {}", amount, asset, to)
+            }
+            Proposal::MintTokens { amount } => write!(f, "mint {} tokens", amount),
+            Proposal::BurnTokens { amount } => write!(f, "burn {} tokens", amount),
+            Proposal::AmendAllocations { .. } => write!(f, "amend token allocations"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProposalKind {
+    RemoveFromRole,
+    AppointToRole,
+    SetGovernancePolicy,
+    SetEmissionPolicy,
+    SpendTreasury,
+    MintTokens,
+    BurnTokens,
+    AmendAllocations,
+}
+
+impl ProposalKind {
+    pub const ALL: [ProposalKind; 8] = [
+        ProposalKind::RemoveFromRole,
+        ProposalKind::AppointToRole,
+        ProposalKind::SetGovernancePolicy,
+        ProposalKind::SetEmissionPolicy,
+        ProposalKind::SpendTreasury,
+        ProposalKind::MintTokens,
+        ProposalKind::BurnTokens,
+        ProposalKind::AmendAllocations,
+    ];
+}
+
+impl fmt::Display for ProposalKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ProposalKind::RemoveFromRole => "remove_from_role",
+            ProposalKind::AppointToRole => "appoint_to_role",
+            ProposalKind::SetGovernancePolicy => "set_governance_policy",
+            ProposalKind::SetEmissionPolicy => "set_emission_policy",
+            ProposalKind::SpendTreasury => "spend_treasury",
+            ProposalKind::MintTokens => "mint_tokens",
+            ProposalKind::BurnTokens => "burn_tokens",
+            ProposalKind::AmendAllocations => "amend_allocations",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for ProposalKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ProposalKind::ALL
+            .into_iter()
+            .find(|kind| kind.to_string() == s.to_lowercase())
+            .ok_or_else(|| format!("Unknown proposal kind: {}", s))
+    }
+}
+
+/// A holder handing their voting power to another holder, for every kind
+/// of proposal or just one. A kind-specific delegation takes precedence
+/// over the holder's general one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Delegation {
+    pub delegator_id: String,
+    pub delegate_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ProposalKind>,
+    pub since: DateTime<Utc>,
+}
+
+/// Reads a vote's proposal, treating records without a `kind` as the role
+/// removals they were before other proposals existed.
+fn proposal_or_removal<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Proposal, D::Error> {
+    let mut value = serde_json::Value::deserialize(deserializer)?;
+    if let Some(fields) = value.as_object_mut() {
+        fields
+            .entry("kind")
+            .or_insert_with(|| "remove_from_role".into());
+    }
+    Proposal::deserialize(value).map_err(serde::de::Error::custom)
+}
+
+impl VoteRecord {
+    pub fn passed(&self) -> bool {
+        match self.outcome {
+            Some(outcome) => outcome == VoteOutcome::Passed,
+            None => self.resolved,
+        }
+    }
+}
+
+/// When a vote takes ballots and how much participation it needs.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct VoteTerms {
+    pub opens_at: DateTime<Utc>,
+    pub closes_at: DateTime<Utc>,
+    /// Share of all eligible voting power that must cast a ballot.
+    pub quorum: f64,
+    /// Share of cast voting power needed to pass, as the governance policy
+    /// stood when the vote opened. Votes opened before it was kept here
+    /// follow the current policy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f64>,
+    /// Whether voters may change their ballot, kept the same way.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_vote_changes: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteOutcome {
+    Passed,
+    Failed,
+}
+
+impl fmt::Display for VoteOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VoteOutcome::Passed => write!(f, "passed"),
+            VoteOutcome::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupersededBallot {
+    pub ballot: Vote,
+    pub superseded_at: DateTime<Utc>,
+}
+
+/// Reads ballots keyed by voter, or as the plain list states used to store;
+/// in a list, a voter's last ballot stands.
+fn ballots_by_voter<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<String, Vote>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Ballots {
+        Keyed(BTreeMap<String, Vote>),
+        Listed(Vec<Vote>),
+    }
+
+    Ok(match Ballots::deserialize(deserializer)? {
+        Ballots::Keyed(votes) => votes,
+        Ballots::Listed(votes) => votes.into_iter().map(|v| (v.voter_id.clone(), v)).collect(),
+    })
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -145,11 +947,138 @@ pub struct PositionListing {
     pub id: String,
     pub role: RoleTier,
     pub seller_id: String,
-    pub price: f64,
+    pub price: Amount,
     pub created_at: DateTime<Utc>,
     pub active: bool,
 }
 
+/// A vacant role position sold to the highest bidder once `ends_at`
+/// passes. While it runs, one vacancy of the role is held back from
+/// first-come bids and appointments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Auction {
+    pub id: String,
+    pub role: RoleTier,
+    pub format: AuctionFormat,
+    pub pricing: AuctionPricing,
+    pub reserve_price: Amount,
+    pub opened_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    /// Each bidder's standing bid.
+    pub bids: BTreeMap<String, AuctionBid>,
+    pub settlement: Option<AuctionSettlement>,
+}
+
+impl Auction {
+    /// The winning bid so far: highest amount, earliest on a tie.
+    pub fn leader(&self) -> Option<(&String, &AuctionBid)> {
+        self.leader_among(|_, _| true)
+    }
+
+    /// The best of the bids that pass `include`.
+    pub fn leader_among(
+        &self,
+        include: impl Fn(&str, &AuctionBid) -> bool,
+    ) -> Option<(&String, &AuctionBid)> {
+        self.bids
+            .iter()
+            .filter(|(id, b)| include(id, b))
+            .min_by(|(a_id, a), (b_id, b)| {
+                b.amount
+                    .cmp(&a.amount)
+                    .then(a.placed_at.cmp(&b.placed_at))
+                    .then(a_id.cmp(b_id))
+            })
+    }
+
+    /// Cash currently held in escrow for this auction's bids.
+    pub fn escrowed(&self) -> Amount {
+        self.bids
+            .values()
+            .filter(|b| b.escrowed)
+            .map(|b| b.amount)
+            .sum()
+    }
+}
+
+/// Sealed bids stay hidden until settlement and a bidder may only raise
+/// their own; ascending bids must beat the current leader, and outbid
+/// bidders get their cash back straight away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuctionFormat {
+    Sealed,
+    Ascending,
+}
+
+impl fmt::Display for AuctionFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            AuctionFormat::Sealed => "sealed",
+            AuctionFormat::Ascending => "ascending",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for AuctionFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sealed" => Ok(AuctionFormat::Sealed),
+            "ascending" | "english" => Ok(AuctionFormat::Ascending),
+            _ => Err(format!("Unknown auction format: {}", s)),
+        }
+    }
+}
+
+/// What the winner pays: their own bid, or the runner-up's (never less
+/// than the reserve).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuctionPricing {
+    FirstPrice,
+    SecondPrice,
+}
+
+impl fmt::Display for AuctionPricing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            AuctionPricing::FirstPrice => "first_price",
+            AuctionPricing::SecondPrice => "second_price",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for AuctionPricing {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "first_price" | "first" => Ok(AuctionPricing::FirstPrice),
+            "second_price" | "second" => Ok(AuctionPricing::SecondPrice),
+            _ => Err(format!("Unknown auction pricing: {}", s)),
+        }
+    }
+}
+
+/// A bidder's standing bid; `escrowed` until it is refunded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuctionBid {
+    pub amount: Amount,
+    pub placed_at: DateTime<Utc>,
+    pub escrowed: bool,
+}
+
+/// How an auction ended; no winner if nobody bid or the position was no
+/// longer vacant.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuctionSettlement {
+    pub winner_id: Option<String>,
+    pub price: Amount,
+    pub settled_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TaskStatus {
     Draft,
@@ -190,7 +1119,7 @@ pub struct Task {
     pub require_tests: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TokenAllocation {
     pub name: String,
     pub percent: f64,
@@ -198,13 +1127,154 @@ pub struct TokenAllocation {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tokenomics {
-    pub total_supply_cap: f64,
-    pub minted_supply: f64,
+    pub total_supply_cap: Amount,
+    pub minted_supply: Amount,
     pub allocations: Vec<TokenAllocation>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OnboardingPolicy {
     pub early_joiner_limit: usize,
-    pub early_joiner_reward: f64,
+    pub early_joiner_reward: Amount,
+    /// Vest early-joiner rewards instead of paying them out at once.
+    #[serde(default)]
+    pub early_joiner_vesting: Option<VestingTerms>,
+}
+
+/// Nothing vests before `cliff_days`; after that the grant vests linearly
+/// from its start until fully vested at `duration_days`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VestingTerms {
+    pub cliff_days: i64,
+    pub duration_days: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VestingSchedule {
+    pub id: u64,
+    pub holder_id: String,
+    pub total: Amount,
+    pub start: DateTime<Utc>,
+    pub terms: VestingTerms,
+    /// The role whose pay the grant came from. Removing the holder from
+    /// that role forfeits what has not vested; grants tied to no role are
+    /// left alone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<RoleTier>,
+    /// Already moved to the holder's balance.
+    pub released: Amount,
+    /// Taken back before vesting, e.g. when the holder was voted out.
+    pub forfeited: Amount,
+}
+
+impl VestingSchedule {
+    pub fn unvested(&self) -> Amount {
+        self.total - self.released - self.forfeited
+    }
+}
+
+/// A holder's tokens in the staking pool. Only `staked` carries voting power.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stake {
+    pub holder_id: String,
+    pub staked: Amount,
+    pub unbonding: Vec<Unbonding>,
+}
+
+impl Stake {
+    /// Everything this holder has in the pool, unbonding included.
+    pub fn total(&self) -> Amount {
+        self.staked + self.unbonding.iter().map(|u| u.amount).sum::<Amount>()
+    }
+}
+
+/// Unstaked tokens waiting out the unbonding period.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Unbonding {
+    pub amount: Amount,
+    pub release_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StakingPolicy {
+    pub unbonding_days: i64,
+    /// Voting power multiplier by role; a holder gets their highest one, or
+    /// 1.0 when none of their roles is listed.
+    pub role_multipliers: BTreeMap<RoleTier, f64>,
+}
+
+impl Default for StakingPolicy {
+    fn default() -> Self {
+        Self {
+            unbonding_days: 7,
+            role_multipliers: BTreeMap::new(),
+        }
+    }
+}
+
+/// What a holder needs before taking a role, by bid, auction, purchase or
+/// appointment. Roles without a rung are open to anyone.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromotionPolicy {
+    pub rungs: BTreeMap<RoleTier, PromotionRung>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromotionRung {
+    /// Role that must be held first, for at least `min_days_in_role`. A
+    /// start date lost to a state from before it was tracked does not count
+    /// against the holder.
+    pub requires: Option<RoleTier>,
+    pub min_days_in_role: i64,
+    /// Finished PM tasks the holder was assigned to.
+    pub min_completed_tasks: usize,
+    /// Tokens actively staked; unbonding tokens do not count.
+    pub min_stake: Amount,
+}
+
+/// Limits on holder-to-holder transfers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransferPolicy {
+    /// Tokens received within this many days cannot be sent on yet.
+    pub lockup_days: i64,
+    /// Most a holder may send per UTC day; unlimited when `None`.
+    pub max_per_day: Option<Amount>,
+    /// Freeze a holder's outgoing transfers while a vote to remove them is open.
+    pub block_during_removal_vote: bool,
+}
+
+impl Default for TransferPolicy {
+    fn default() -> Self {
+        Self {
+            lockup_days: 0,
+            max_per_day: None,
+            block_during_removal_vote: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenTransfer {
+    pub from: String,
+    pub to: String,
+    pub amount: Amount,
+    pub at: DateTime<Utc>,
+    pub memo: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenReceipt {
+    pub at: DateTime<Utc>,
+    pub amount: Amount,
+}
+
+/// Vesting applied to work distributions, by the recipient's highest role
+/// that has terms configured.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VestingPolicy {
+    pub roles: BTreeMap<RoleTier, VestingTerms>,
 }
@@ -0,0 +1,338 @@
+use crate::error::FeedError;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+/// One observed token price. Without `at` the price counts as observed
+/// when it is recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct PriceTick {
+    pub price: f64,
+    #[serde(default, alias = "timestamp")]
+    pub at: Option<DateTime<Utc>>,
+}
+
+impl PriceTick {
+    /// Whether a price at or after this tick's time is already in
+    /// `history`, as when a feed is replayed from its start. Ticks without
+    /// a time are never stale.
+    pub fn is_stale(&self, history: &[(DateTime<Utc>, f64)]) -> bool {
+        let latest = history.last().map(|(at, _)| *at);
+        self.at.zip(latest).is_some_and(|(at, latest)| at <= latest)
+    }
+}
+
+/// Somewhere token prices come from.
+pub trait PriceSource {
+    /// Blocks until the next tick is available; `None` once the source is
+    /// exhausted.
+    fn next_tick(&mut self) -> Result<Option<PriceTick>, FeedError>;
+}
+
+/// Ticks one per line, either CSV (`price` or `timestamp,price`) or JSON
+/// objects (`{"price": 1.25, "at": "2024-01-01T00:00:00Z"}`), detected per
+/// line. Blank lines, `#` comments and a CSV header are skipped.
+pub struct LineSource<R> {
+    reader: R,
+    line: usize,
+}
+
+impl<R: BufRead> LineSource<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader, line: 0 }
+    }
+}
+
+impl LineSource<BufReader<File>> {
+    pub fn open(path: &str) -> Result<Self, FeedError> {
+        Ok(Self::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl LineSource<io::StdinLock<'static>> {
+    pub fn stdin() -> Self {
+        Self::new(io::stdin().lock())
+    }
+}
+
+impl<R: BufRead> PriceSource for LineSource<R> {
+    fn next_tick(&mut self) -> Result<Option<PriceTick>, FeedError> {
+        let mut buf = String::new();
+        loop {
+            buf.clear();
+            if self.reader.read_line(&mut buf)? == 0 {
+                return Ok(None);
+            }
+            self.line += 1;
+            let line = buf.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if self.line == 1 && is_csv_header(line) {
+                continue;
+            }
+            return parse_tick(line)
+                .map(Some)
+                .map_err(|reason| FeedError::InvalidTick {
+                    line: Some(self.line),
+                    reason,
+                });
+        }
+    }
+}
+
+fn is_csv_header(line: &str) -> bool {
+    !line.starts_with('{') && line.split(',').any(|f| f.trim() == "price")
+}
+
+/// Parses a JSON tick, a bare price or a `timestamp,price` pair.
+pub fn parse_tick(raw: &str) -> Result<PriceTick, String> {
+    let raw = raw.trim();
+    let tick = if raw.starts_with('{') {
+        serde_json::from_str(raw).map_err(|e| e.to_string())?
+    } else {
+        match raw.split_once(',') {
+            Some((at, price)) => PriceTick {
+                price: parse_price(price)?,
+                at: Some(
+                    at.trim()
+                        .parse()
+                        .map_err(|_| format!("invalid timestamp {:?}", at.trim()))?,
+                ),
+            },
+            None => PriceTick {
+                price: parse_price(raw)?,
+                at: None,
+            },
+        }
+    };
+    if !(tick.price > 0.0 && tick.price.is_finite()) {
+        return Err(format!("price must be > 0, got {}", tick.price));
+    }
+    Ok(tick)
+}
+
+fn parse_price(raw: &str) -> Result<f64, String> {
+    raw.trim()
+        .parse()
+        .map_err(|_| format!("invalid price {:?}", raw.trim()))
+}
+
+/// Polls a plain-HTTP endpoint that answers with a tick (a JSON object or a
+/// bare price) every `interval`. Meant for a local sidecar, so there is no
+/// TLS and the response is read whole.
+pub struct HttpSource {
+    host: String,
+    port: u16,
+    path: String,
+    interval: Duration,
+    polled: bool,
+}
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl HttpSource {
+    pub fn new(url: &str, interval: Duration) -> Result<Self, FeedError> {
+        let invalid = || FeedError::InvalidSource(url.to_string());
+        let rest = url.strip_prefix("http://").ok_or_else(invalid)?;
+        let (authority, path) = match rest.find('/') {
+            Some(idx) => rest.split_at(idx),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid())?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+            interval,
+            polled: false,
+        })
+    }
+
+    fn fetch(&self) -> Result<String, io::Error> {
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port))?;
+        stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+        stream.set_write_timeout(Some(HTTP_TIMEOUT))?;
+        write!(
+            stream,
+            "GET {} HTTP/1.0\r\nHost: {}\r\nAccept: application/json\r\nConnection: close\r\n\r\n",
+            self.path, self.host
+        )?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        Ok(response)
+    }
+}
+
+impl PriceSource for HttpSource {
+    fn next_tick(&mut self) -> Result<Option<PriceTick>, FeedError> {
+        if self.polled {
+            thread::sleep(self.interval);
+        }
+        self.polled = true;
+        let response = self
+            .fetch()
+            .map_err(|e| FeedError::Unavailable(e.to_string()))?;
+        let (head, body) = response
+            .split_once("\r\n\r\n")
+            .ok_or_else(|| FeedError::Unavailable("malformed http response".into()))?;
+        let status = head.lines().next().unwrap_or_default();
+        if status.split_whitespace().nth(1) != Some("200") {
+            return Err(FeedError::Unavailable(format!(
+                "endpoint answered {:?}",
+                status
+            )));
+        }
+        parse_tick(body)
+            .map(Some)
+            .map_err(|reason| FeedError::InvalidTick { line: None, reason })
+    }
+}
+
+/// Where `bnet price-feed run` reads ticks from: `file:<path>`, `stdin`
+/// (or `-`), or an `http://` URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceSpec {
+    File(String),
+    Stdin,
+    Http(String),
+}
+
+impl FromStr for SourceSpec {
+    type Err = FeedError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stdin" | "-" => Ok(SourceSpec::Stdin),
+            _ if s.starts_with("http://") => Ok(SourceSpec::Http(s.to_string())),
+            _ => match s.split_once(':') {
+                Some(("file", path)) if !path.is_empty() => Ok(SourceSpec::File(path.into())),
+                _ => Err(FeedError::InvalidSource(s.to_string())),
+            },
+        }
+    }
+}
+
+/// `interval` only applies to polled sources.
+pub fn open_source(
+    spec: &SourceSpec,
+    interval: Duration,
+) -> Result<Box<dyn PriceSource>, FeedError> {
+    match spec {
+        SourceSpec::File(path) => Ok(Box::new(LineSource::open(path)?)),
+        SourceSpec::Stdin => Ok(Box::new(LineSource::stdin())),
+        SourceSpec::Http(url) => Ok(Box::new(HttpSource::new(url, interval)?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::io::Cursor;
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 6, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn parses_csv_and_json_ticks() {
+        let bare = PriceTick {
+            price: 1.25,
+            at: None,
+        };
+        let timed = PriceTick {
+            price: 1.25,
+            at: Some(at(9)),
+        };
+        assert_eq!(parse_tick(" 1.25 ").unwrap(), bare);
+        assert_eq!(parse_tick("2025-01-06T09:00:00Z, 1.25").unwrap(), timed);
+        assert_eq!(parse_tick(r#"{"price": 1.25}"#).unwrap(), bare);
+        assert_eq!(
+            parse_tick(r#"{"price": 1.25, "at": "2025-01-06T09:00:00Z"}"#).unwrap(),
+            timed
+        );
+        assert_eq!(
+            parse_tick(r#"{"price": 1.25, "timestamp": "2025-01-06T09:00:00Z"}"#).unwrap(),
+            timed
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_ticks() {
+        for raw in [
+            "abc",
+            "2025-01-06T09:00:00Z,abc",
+            "yesterday,1.25",
+            "0",
+            "-1.5",
+            "NaN",
+            r#"{"price": "1.25"}"#,
+            r#"{"at": "2025-01-06T09:00:00Z"}"#,
+            "{",
+        ] {
+            assert!(parse_tick(raw).is_err(), "{raw:?} parsed");
+        }
+    }
+
+    #[test]
+    fn line_source_skips_headers_comments_and_blanks() {
+        let input = "timestamp,price\n# backfill\n\n2025-01-06T09:00:00Z,1.5\n{\"price\": 2}\n";
+        let mut source = LineSource::new(Cursor::new(input));
+        assert_eq!(source.next_tick().unwrap().unwrap().at, Some(at(9)));
+        assert_eq!(source.next_tick().unwrap().unwrap().price, 2.0);
+        assert!(source.next_tick().unwrap().is_none());
+
+        let mut source = LineSource::new(Cursor::new("1.5\n\noops\n"));
+        source.next_tick().unwrap();
+        let err = source.next_tick().unwrap_err();
+        assert!(
+            matches!(err, FeedError::InvalidTick { line: Some(3), .. }),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn source_specs() {
+        assert_eq!("stdin".parse::<SourceSpec>().unwrap(), SourceSpec::Stdin);
+        assert_eq!("-".parse::<SourceSpec>().unwrap(), SourceSpec::Stdin);
+        assert_eq!(
+            "file:ticks.csv".parse::<SourceSpec>().unwrap(),
+            SourceSpec::File("ticks.csv".into())
+        );
+        assert_eq!(
+            "http://localhost:8080/price".parse::<SourceSpec>().unwrap(),
+            SourceSpec::Http("http://localhost:8080/price".into())
+        );
+        for bad in ["file:", "ticks.csv", "https://example.com", "ftp:x"] {
+            assert!(
+                matches!(bad.parse::<SourceSpec>(), Err(FeedError::InvalidSource(_))),
+                "{bad:?} parsed"
+            );
+        }
+    }
+
+    #[test]
+    fn ticks_no_newer_than_the_history_are_stale() {
+        let history = vec![(at(8), 1.0), (at(9), 1.1)];
+        let tick = |hour: Option<u32>| PriceTick {
+            price: 1.2,
+            at: hour.map(at),
+        };
+        assert!(tick(Some(8)).is_stale(&history));
+        assert!(tick(Some(9)).is_stale(&history));
+        assert!(!tick(Some(10)).is_stale(&history));
+        assert!(!tick(None).is_stale(&history));
+        assert!(!tick(Some(8)).is_stale(&[]));
+    }
+}
@@ -1,7 +1,19 @@
-use crate::model::CompanyState;
-use std::fs;
-use std::io;
-use std::path::Path;
+use crate::engine::{backfill_employee_since, new_company, verify};
+use crate::error::{EngineError, StoreError};
+use crate::events::{CompanyEvent, RecordedEvent, apply_recorded, replay};
+use crate::ledger::backfill_token_receipts;
+use crate::model::{CompanyState, JournalEntry};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::ser::{self, Impossible};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 
 pub fn load_state(path: &Path) -> io::Result<CompanyState> {
     let data = fs::read_to_string(path)?;
@@ -10,8 +22,1282 @@ pub fn load_state(path: &Path) -> io::Result<CompanyState> {
     Ok(state)
 }
 
+/// Writes to a sibling temp file, fsyncs it and renames it over `path`, so a
+/// crash leaves either the old or the new state on disk, never a torn one.
 pub fn save_state(path: &Path, state: &CompanyState) -> io::Result<()> {
     let data = serde_json::to_string_pretty(state)
         .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-    fs::write(path, data)
+    let tmp = sibling_path(path, &format!("tmp-{}", std::process::id()));
+    let result = (|| {
+        let mut file = File::create(&tmp)?;
+        file.write_all(data.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)?;
+        sync_parent_dir(path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Advisory exclusive lock on `<state>.lock`, released when dropped.
+pub struct StateLock {
+    _file: File,
+}
+
+impl StateLock {
+    pub fn acquire(state_path: &Path) -> Result<Self, StoreError> {
+        let lock_path = sibling_path(state_path, "lock");
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)?;
+        match file.try_lock() {
+            Ok(()) => Ok(Self { _file: file }),
+            Err(TryLockError::WouldBlock) => Err(StoreError::Locked {
+                path: state_path.to_path_buf(),
+            }),
+            Err(TryLockError::Error(e)) => Err(e.into()),
+        }
+    }
+}
+
+/// A place `CompanyState` lives between `bnet` invocations: an append-only
+/// event log plus a snapshot of the state folded up to `event_seq`.
+///
+/// `begin`/`commit`/`rollback` bracket a read-modify-write cycle; use
+/// [`update`] rather than calling them directly.
+pub trait StateStore {
+    /// Latest snapshot. It may lag the log after a crash; see [`current_state`].
+    fn load(&mut self) -> Result<CompanyState, StoreError>;
+    fn save(&mut self, state: &CompanyState) -> Result<(), StoreError>;
+    /// Logged events with `seq > after`, in order.
+    fn load_events(&mut self, after: u64) -> Result<Vec<RecordedEvent>, StoreError>;
+    /// Takes the write lock and returns the snapshot, if one exists yet.
+    fn begin(&mut self) -> Result<Option<CompanyState>, StoreError>;
+    /// Appends `events` to the log, then replaces the snapshot with `state`.
+    fn commit(&mut self, state: &CompanyState, events: &[RecordedEvent]) -> Result<(), StoreError>;
+    fn rollback(&mut self) -> Result<(), StoreError>;
+}
+
+/// Snapshot plus any logged events it has not folded in yet.
+pub fn current_state(store: &mut dyn StateStore) -> Result<CompanyState, StoreError> {
+    let mut state = store.load()?;
+    backfill_employee_since(&mut state);
+    backfill_token_receipts(&mut state);
+    for recorded in store.load_events(state.event_seq)? {
+        apply_recorded(&mut state, &recorded)?;
+    }
+    Ok(state)
+}
+
+/// Refolds the whole log and replaces the snapshot with the result.
+pub fn rebuild(store: &mut dyn StateStore) -> Result<CompanyState, StoreError> {
+    store.begin()?;
+    let state = store.load_events(0).and_then(|events| Ok(replay(&events)?));
+    match state {
+        Ok(state) => {
+            store.commit(&state, &[])?;
+            Ok(state)
+        }
+        Err(err) => {
+            store.rollback()?;
+            Err(err)
+        }
+    }
+}
+
+/// Working copy handed to [`update`] callbacks. Mutations go through
+/// [`Transaction::record`] so each one lands in the event log.
+pub struct Transaction {
+    state: CompanyState,
+    exists: bool,
+    needs_import: bool,
+    actor: Option<String>,
+    pending: Vec<RecordedEvent>,
+}
+
+impl Transaction {
+    pub fn state(&self) -> &CompanyState {
+        &self.state
+    }
+
+    pub fn record(&mut self, event: CompanyEvent) -> Result<(), EngineError> {
+        if !self.exists && !event.starts_history() {
+            return Err(EngineError::invalid(
+                "no company state; run `bnet init` first",
+            ));
+        }
+        if self.needs_import && !event.starts_history() {
+            let snapshot = Box::new(self.state.clone());
+            self.push(CompanyEvent::Imported { state: snapshot })?;
+        }
+        self.needs_import = false;
+        self.push(event)?;
+        self.exists = true;
+        Ok(())
+    }
+
+    fn push(&mut self, event: CompanyEvent) -> Result<(), EngineError> {
+        let recorded = RecordedEvent {
+            seq: self.state.event_seq + 1,
+            at: Utc::now(),
+            actor: self.actor.clone(),
+            event,
+        };
+        apply_recorded(&mut self.state, &recorded)?;
+        self.pending.push(recorded);
+        Ok(())
+    }
+}
+
+/// Runs `f` against the current state under the store's write lock and
+/// persists the events it records only if it succeeds.
+pub fn update<T>(
+    store: &mut dyn StateStore,
+    actor: Option<&str>,
+    f: impl FnOnce(&mut Transaction) -> Result<T, EngineError>,
+) -> Result<T, StoreError> {
+    let snapshot = store.begin()?;
+    let result = open_transaction(store, snapshot, actor).and_then(|mut tx| {
+        let value = f(&mut tx)?;
+        Ok((tx, value))
+    });
+    match result {
+        Ok((tx, value)) if !tx.pending.is_empty() => {
+            store.commit(&tx.state, &tx.pending)?;
+            Ok(value)
+        }
+        Ok((_, value)) => {
+            store.rollback()?;
+            Ok(value)
+        }
+        Err(err) => {
+            store.rollback()?;
+            Err(err)
+        }
+    }
+}
+
+fn open_transaction(
+    store: &mut dyn StateStore,
+    snapshot: Option<CompanyState>,
+    actor: Option<&str>,
+) -> Result<Transaction, StoreError> {
+    let had_snapshot = snapshot.is_some();
+    let (state, exists) = match snapshot {
+        Some(mut state) => {
+            backfill_employee_since(&mut state);
+            backfill_token_receipts(&mut state);
+            for recorded in store.load_events(state.event_seq)? {
+                apply_recorded(&mut state, &recorded)?;
+            }
+            (state, true)
+        }
+        None => {
+            let events = store.load_events(0)?;
+            if events.is_empty() {
+                (new_company(0, Utc::now()), false)
+            } else {
+                (replay(&events)?, true)
+            }
+        }
+    };
+    Ok(Transaction {
+        needs_import: had_snapshot && state.event_seq == 0,
+        state,
+        exists,
+        actor: actor.map(str::to_string),
+        pending: vec![],
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoreSpec {
+    Json(PathBuf),
+    Sqlite(PathBuf),
+}
+
+impl FromStr for StoreSpec {
+    type Err = StoreError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("json", path)) if !path.is_empty() => Ok(StoreSpec::Json(path.into())),
+            Some(("sqlite", path)) if !path.is_empty() => Ok(StoreSpec::Sqlite(path.into())),
+            _ => Err(StoreError::InvalidSpec(s.to_string())),
+        }
+    }
+}
+
+pub fn open_store(spec: &StoreSpec) -> Result<Box<dyn StateStore>, StoreError> {
+    match spec {
+        StoreSpec::Json(path) => Ok(Box::new(JsonFileStore::new(path))),
+        StoreSpec::Sqlite(path) => Ok(Box::new(SqliteStore::open(path)?)),
+    }
+}
+
+/// Wraps another store and runs [`verify`] on every state about to be
+/// committed, rolling back instead of saving one that breaks an invariant.
+pub struct VerifiedStore {
+    inner: Box<dyn StateStore>,
+}
+
+impl VerifiedStore {
+    pub fn new(inner: Box<dyn StateStore>) -> Self {
+        Self { inner }
+    }
+}
+
+impl StateStore for VerifiedStore {
+    fn load(&mut self) -> Result<CompanyState, StoreError> {
+        self.inner.load()
+    }
+
+    fn save(&mut self, state: &CompanyState) -> Result<(), StoreError> {
+        self.inner.save(state)
+    }
+
+    fn load_events(&mut self, after: u64) -> Result<Vec<RecordedEvent>, StoreError> {
+        self.inner.load_events(after)
+    }
+
+    fn begin(&mut self) -> Result<Option<CompanyState>, StoreError> {
+        self.inner.begin()
+    }
+
+    fn commit(&mut self, state: &CompanyState, events: &[RecordedEvent]) -> Result<(), StoreError> {
+        let violations = verify(state);
+        if !violations.is_empty() {
+            self.inner.rollback()?;
+            return Err(StoreError::Invariant(violations));
+        }
+        self.inner.commit(state, events)
+    }
+
+    fn rollback(&mut self) -> Result<(), StoreError> {
+        self.inner.rollback()
+    }
+}
+
+/// The state as one pretty-printed JSON document with its event log in
+/// `<path>.events.jsonl`, guarded by a `<path>.lock` file for the duration
+/// of each write cycle.
+pub struct JsonFileStore {
+    path: PathBuf,
+    lock: Option<StateLock>,
+}
+
+impl JsonFileStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: None,
+        }
+    }
+}
+
+impl JsonFileStore {
+    fn log_path(&self) -> PathBuf {
+        sibling_path(&self.path, "events.jsonl")
+    }
+
+    fn append_events(&self, events: &[RecordedEvent]) -> Result<(), StoreError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        let mut buf = String::new();
+        for recorded in events {
+            buf.push_str(&serde_json::to_string(recorded)?);
+            buf.push('\n');
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(self.log_path())?;
+        truncate_torn_tail(&mut file)?;
+        file.write_all(buf.as_bytes())?;
+        file.sync_all()?;
+        Ok(())
+    }
+}
+
+/// Cuts off a final line a crash left half-written, so the next append
+/// starts on a line of its own.
+fn truncate_torn_tail(file: &mut File) -> io::Result<()> {
+    let len = file.seek(SeekFrom::End(0))?;
+    let mut end = len;
+    let mut buf = [0u8; 4096];
+    while end > 0 {
+        let start = end.saturating_sub(buf.len() as u64);
+        let chunk = &mut buf[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(chunk)?;
+        if let Some(i) = chunk.iter().rposition(|&b| b == b'\n') {
+            end = start + i as u64 + 1;
+            break;
+        }
+        end = start;
+    }
+    if end < len {
+        file.set_len(end)?;
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct SeqOnly {
+    seq: u64,
+}
+
+impl StateStore for JsonFileStore {
+    fn load(&mut self) -> Result<CompanyState, StoreError> {
+        Ok(load_state(&self.path)?)
+    }
+
+    fn load_events(&mut self, after: u64) -> Result<Vec<RecordedEvent>, StoreError> {
+        let file = match File::open(self.log_path()) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let mut events = vec![];
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                break;
+            }
+            // Only the last line can lack its newline: an append a crash cut
+            // short. Its commit never finished, and the next append drops it.
+            if !line.ends_with('\n') {
+                break;
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            // Skip already-folded events without decoding their payloads.
+            if serde_json::from_str::<SeqOnly>(&line)?.seq > after {
+                events.push(serde_json::from_str(&line)?);
+            }
+        }
+        Ok(events)
+    }
+
+    fn save(&mut self, state: &CompanyState) -> Result<(), StoreError> {
+        let _lock = match self.lock {
+            Some(_) => None,
+            None => Some(StateLock::acquire(&self.path)?),
+        };
+        Ok(save_state(&self.path, state)?)
+    }
+
+    fn begin(&mut self) -> Result<Option<CompanyState>, StoreError> {
+        self.lock = Some(StateLock::acquire(&self.path)?);
+        match load_state(&self.path) {
+            Ok(state) => Ok(Some(state)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => {
+                self.lock = None;
+                Err(e.into())
+            }
+        }
+    }
+
+    fn commit(&mut self, state: &CompanyState, events: &[RecordedEvent]) -> Result<(), StoreError> {
+        // Log first: a crash before the snapshot lands is repaired by
+        // folding the tail on the next load.
+        let result = self.append_events(events).and_then(|_| self.save(state));
+        self.lock = None;
+        result
+    }
+
+    fn rollback(&mut self) -> Result<(), StoreError> {
+        self.lock = None;
+        Ok(())
+    }
+}
+
+const SQLITE_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS state_fields (
+    name TEXT PRIMARY KEY,
+    shape TEXT NOT NULL,
+    value TEXT
+);
+CREATE TABLE IF NOT EXISTS state_items (
+    field TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (field, key)
+);
+CREATE TABLE IF NOT EXISTS events (
+    seq INTEGER PRIMARY KEY,
+    at TEXT NOT NULL,
+    body TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS journal (
+    id INTEGER PRIMARY KEY,
+    at TEXT NOT NULL,
+    body TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS price_history (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    at TEXT NOT NULL,
+    price REAL NOT NULL
+);
+";
+
+/// State fields that only grow. They live in append-only tables of their
+/// own, so a commit writes just their new entries.
+const JOURNAL: &str = "journal";
+const PRICE_HISTORY: &str = "token_price_history";
+
+fn corrupt(reason: impl Into<String>) -> StoreError {
+    StoreError::Io(io::Error::new(io::ErrorKind::InvalidData, reason.into()))
+}
+
+/// Top-level `CompanyState` fields flattened into rows: maps and lists get
+/// one row per entry, everything else one row per field. A list entry keeps
+/// its key while it is unchanged and the list's own row holds the order of
+/// the keys, so adding or dropping an entry rewrites that entry and the
+/// order, not every entry after it.
+#[derive(Default, PartialEq)]
+struct Rows {
+    fields: BTreeMap<String, (String, Option<String>)>,
+    items: BTreeMap<(String, String), String>,
+}
+
+impl Rows {
+    /// Rows for `state`, reusing `prev`'s keys for list entries it still has.
+    fn from_state(state: &CompanyState, prev: &Rows) -> Result<Self, StoreError> {
+        let mut top = TopLevel::default();
+        state.serialize(&mut top)?;
+        let mut rows = Rows::default();
+        for (name, value) in top.0 {
+            match value {
+                Value::Object(map) => {
+                    rows.fields.insert(name.clone(), ("map".into(), None));
+                    for (key, item) in map {
+                        rows.items
+                            .insert((name.clone(), key), serde_json::to_string(&item)?);
+                    }
+                }
+                Value::Array(list) => {
+                    let list = list
+                        .iter()
+                        .map(serde_json::to_string)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let keys = prev.list_keys(&name, &list)?;
+                    let order = serde_json::to_string(&keys)?;
+                    rows.fields
+                        .insert(name.clone(), ("list".into(), Some(order)));
+                    for (key, item) in keys.into_iter().zip(list) {
+                        rows.items.insert((name.clone(), key), item);
+                    }
+                }
+                other => {
+                    rows.fields
+                        .insert(name, ("value".into(), Some(serde_json::to_string(&other)?)));
+                }
+            }
+        }
+        Ok(rows)
+    }
+
+    fn entries<'a>(&'a self, field: &'a str) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.items
+            .range((field.to_string(), String::new())..)
+            .take_while(move |((f, _), _)| f == field)
+            .map(|((_, key), value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Keys of `field`'s entries in list order. Lists written before entries
+    /// had lasting keys have no order row; their keys are zero-padded
+    /// indexes and sort into place.
+    fn order(&self, field: &str) -> Result<Vec<String>, StoreError> {
+        match self.fields.get(field) {
+            Some((_, Some(order))) => Ok(serde_json::from_str(order)?),
+            _ => Ok(self
+                .entries(field)
+                .map(|(key, _)| key.to_string())
+                .collect()),
+        }
+    }
+
+    /// A key for each entry of `list`: the key of an identical entry in
+    /// these rows while one is left, otherwise a fresh one.
+    fn list_keys(&self, field: &str, list: &[String]) -> Result<Vec<String>, StoreError> {
+        let mut unused: HashMap<&str, VecDeque<String>> = HashMap::new();
+        let mut next = 0;
+        for key in self.order(field)? {
+            next = key.parse::<u64>().map_or(next, |k| next.max(k + 1));
+            if let Some(item) = self.items.get(&(field.to_string(), key.clone())) {
+                unused.entry(item.as_str()).or_default().push_back(key);
+            }
+        }
+        Ok(list
+            .iter()
+            .map(|item| {
+                unused
+                    .get_mut(item.as_str())
+                    .and_then(VecDeque::pop_front)
+                    .unwrap_or_else(|| {
+                        next += 1;
+                        format!("{:010}", next - 1)
+                    })
+            })
+            .collect())
+    }
+
+    /// The top-level JSON object the rows describe.
+    fn to_object(&self) -> Result<Map<String, Value>, StoreError> {
+        let mut top = Map::new();
+        for (name, (shape, value)) in &self.fields {
+            let value = match shape.as_str() {
+                "map" => Value::Object(
+                    self.entries(name)
+                        .map(|(key, raw)| Ok((key.to_string(), serde_json::from_str(raw)?)))
+                        .collect::<Result<_, StoreError>>()?,
+                ),
+                "list" => Value::Array(
+                    self.order(name)?
+                        .into_iter()
+                        .map(|key| {
+                            let raw = self
+                                .items
+                                .get(&(name.clone(), key.clone()))
+                                .ok_or_else(|| corrupt(format!("{} has no entry {}", name, key)))?;
+                            Ok(serde_json::from_str(raw)?)
+                        })
+                        .collect::<Result<_, StoreError>>()?,
+                ),
+                _ => serde_json::from_str(value.as_deref().unwrap_or("null"))?,
+            };
+            top.insert(name.clone(), value);
+        }
+        Ok(top)
+    }
+}
+
+/// Collects the state's top-level fields one at a time, leaving out the
+/// append-only ones, so a commit never encodes the whole journal.
+#[derive(Default)]
+struct TopLevel(Map<String, Value>);
+
+fn not_a_struct() -> serde_json::Error {
+    ser::Error::custom("company state must serialize as a struct")
+}
+
+macro_rules! reject_non_struct {
+    ($($method:ident($($arg:ty),*);)*) => {
+        $(fn $method(self, $(_: $arg),*) -> Result<(), serde_json::Error> {
+            Err(not_a_struct())
+        })*
+    };
+}
+
+impl ser::Serializer for &mut TopLevel {
+    type Ok = ();
+    type Error = serde_json::Error;
+    type SerializeSeq = Impossible<(), serde_json::Error>;
+    type SerializeTuple = Impossible<(), serde_json::Error>;
+    type SerializeTupleStruct = Impossible<(), serde_json::Error>;
+    type SerializeTupleVariant = Impossible<(), serde_json::Error>;
+    type SerializeMap = Impossible<(), serde_json::Error>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), serde_json::Error>;
+
+    reject_non_struct! {
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_f32(f32);
+        serialize_f64(f64);
+        serialize_char(char);
+        serialize_str(&str);
+        serialize_bytes(&[u8]);
+        serialize_none();
+        serialize_unit();
+        serialize_unit_struct(&'static str);
+        serialize_unit_variant(&'static str, u32, &'static str);
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> Result<(), serde_json::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), serde_json::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), serde_json::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, serde_json::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, serde_json::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, serde_json::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, serde_json::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, serde_json::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, serde_json::Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, serde_json::Error> {
+        Err(not_a_struct())
+    }
+}
+
+impl ser::SerializeStruct for &mut TopLevel {
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        if key != JOURNAL && key != PRICE_HISTORY {
+            self.0.insert(key.to_string(), serde_json::to_value(value)?);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+}
+
+/// What the append-only tables held as of the last read or write.
+#[derive(Default)]
+struct Appended {
+    journal: usize,
+    prices: Vec<(DateTime<Utc>, f64)>,
+}
+
+/// The prices in `current` that `stored` lacks, or `None` if `current` no
+/// longer holds every stored price in order and the table must be redone.
+fn new_prices(
+    stored: &[(DateTime<Utc>, f64)],
+    current: &[(DateTime<Utc>, f64)],
+) -> Option<Vec<(DateTime<Utc>, f64)>> {
+    let mut matched = 0;
+    let mut new = vec![];
+    for sample in current {
+        if stored.get(matched) == Some(sample) {
+            matched += 1;
+        } else {
+            new.push(*sample);
+        }
+    }
+    (matched == stored.len()).then_some(new)
+}
+
+/// Embedded SQLite store that only rewrites the rows a command changed.
+pub struct SqliteStore {
+    conn: Connection,
+    path: PathBuf,
+    cached: Option<(Rows, Appended)>,
+}
+
+impl SqliteStore {
+    pub fn open(path: &Path) -> Result<Self, StoreError> {
+        let conn = Connection::open(path)?;
+        // Fail fast instead of queueing behind another writer.
+        conn.busy_timeout(Duration::ZERO)?;
+        conn.execute_batch(SQLITE_SCHEMA)?;
+        Ok(Self {
+            conn,
+            path: path.to_path_buf(),
+            cached: None,
+        })
+    }
+
+    /// `BEGIN IMMEDIATE` takes SQLite's write lock up front, so it is held
+    /// across the whole load→mutate→save cycle.
+    fn begin_immediate(&self) -> Result<(), StoreError> {
+        match self.conn.execute_batch("BEGIN IMMEDIATE") {
+            Err(rusqlite::Error::SqliteFailure(e, _))
+                if e.code == rusqlite::ErrorCode::DatabaseBusy =>
+            {
+                Err(StoreError::Locked {
+                    path: self.path.clone(),
+                })
+            }
+            other => Ok(other?),
+        }
+    }
+
+    fn read_rows(&self) -> Result<Rows, StoreError> {
+        let mut rows = Rows::default();
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, shape, value FROM state_fields")?;
+        let fields = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))?;
+        for field in fields {
+            let (name, shape, value): (String, String, Option<String>) = field?;
+            rows.fields.insert(name, (shape, value));
+        }
+        let mut stmt = self
+            .conn
+            .prepare("SELECT field, key, value FROM state_items")?;
+        let items = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))?;
+        for item in items {
+            let (field, key, value): (String, String, String) = item?;
+            rows.items.insert((field, key), value);
+        }
+        Ok(rows)
+    }
+
+    fn read_journal(&self) -> Result<Vec<JournalEntry>, StoreError> {
+        let mut stmt = self.conn.prepare("SELECT body FROM journal ORDER BY id")?;
+        let rows = stmt.query_map([], |r| r.get::<_, String>(0))?;
+        let mut journal = vec![];
+        for body in rows {
+            journal.push(serde_json::from_str(&body?)?);
+        }
+        Ok(journal)
+    }
+
+    /// Prices in the order the state keeps them: by time, and in the order
+    /// they were recorded among equal times.
+    fn read_prices(&self) -> Result<Vec<(DateTime<Utc>, f64)>, StoreError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT at, price FROM price_history ORDER BY seq")?;
+        let rows = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get(1)?)))?;
+        let mut prices = vec![];
+        for row in rows {
+            let (at, price) = row?;
+            let at = at
+                .parse::<DateTime<Utc>>()
+                .map_err(|e| corrupt(format!("bad price time {:?}: {}", at, e)))?;
+            prices.push((at, price));
+        }
+        prices.sort_by_key(|(at, _)| *at);
+        Ok(prices)
+    }
+
+    fn load_rows(&mut self) -> Result<Option<CompanyState>, StoreError> {
+        let initialized: Option<String> = self
+            .conn
+            .query_row("SELECT name FROM state_fields LIMIT 1", [], |r| r.get(0))
+            .optional()?;
+        if initialized.is_none() {
+            return Ok(None);
+        }
+        let rows = self.read_rows()?;
+        let mut top = rows.to_object()?;
+        let journal = self.read_journal()?;
+        let appended = Appended {
+            journal: journal.len(),
+            prices: self.read_prices()?,
+        };
+        // Stores written before the append-only tables keep these as rows
+        // until the next commit moves them.
+        if !top.contains_key(JOURNAL) {
+            top.insert(JOURNAL.into(), serde_json::to_value(journal)?);
+        }
+        if !top.contains_key(PRICE_HISTORY) {
+            top.insert(
+                PRICE_HISTORY.into(),
+                serde_json::to_value(&appended.prices)?,
+            );
+        }
+        let state = serde_json::from_value(Value::Object(top))?;
+        self.cached = Some((rows, appended));
+        Ok(Some(state))
+    }
+
+    fn append_events(&self, events: &[RecordedEvent]) -> Result<(), StoreError> {
+        for recorded in events {
+            self.conn.execute(
+                "INSERT INTO events (seq, at, body) VALUES (?1, ?2, ?3)",
+                params![
+                    recorded.seq as i64,
+                    recorded.at.to_rfc3339(),
+                    serde_json::to_string(recorded)?
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Writes the journal entries and prices the tables do not have yet. A
+    /// `full` write, or history that no longer extends what is stored,
+    /// replaces the tables instead.
+    fn append_history(
+        &self,
+        state: &CompanyState,
+        stored: Appended,
+        full: bool,
+    ) -> Result<Appended, StoreError> {
+        let mut journal = stored.journal;
+        if full || state.journal.len() < journal {
+            self.conn.execute("DELETE FROM journal", [])?;
+            journal = 0;
+        }
+        for entry in &state.journal[journal..] {
+            self.conn.execute(
+                "INSERT INTO journal (id, at, body) VALUES (?1, ?2, ?3)",
+                params![
+                    entry.id as i64,
+                    entry.at.to_rfc3339(),
+                    serde_json::to_string(entry)?
+                ],
+            )?;
+        }
+
+        let history = &state.token_price_history;
+        let new = match new_prices(&stored.prices, history) {
+            Some(new) if !full => new,
+            _ => {
+                self.conn.execute("DELETE FROM price_history", [])?;
+                history.clone()
+            }
+        };
+        for (at, price) in new {
+            self.conn.execute(
+                "INSERT INTO price_history (at, price) VALUES (?1, ?2)",
+                params![at.to_rfc3339_opts(SecondsFormat::AutoSi, true), price],
+            )?;
+        }
+        Ok(Appended {
+            journal: state.journal.len(),
+            prices: history.clone(),
+        })
+    }
+
+    fn write_rows(&mut self, state: &CompanyState, full: bool) -> Result<(), StoreError> {
+        let (prev, stored) = match self.cached.take() {
+            Some(cached) => cached,
+            None => {
+                let stored = Appended {
+                    journal: self.read_journal()?.len(),
+                    prices: self.read_prices()?,
+                };
+                (self.read_rows()?, stored)
+            }
+        };
+        let next = Rows::from_state(state, &prev)?;
+
+        for name in prev.fields.keys().filter(|n| !next.fields.contains_key(*n)) {
+            self.conn
+                .execute("DELETE FROM state_fields WHERE name = ?1", params![name])?;
+        }
+        for (name, (shape, value)) in &next.fields {
+            if prev.fields.get(name) != Some(&(shape.clone(), value.clone())) {
+                self.conn.execute(
+                    "INSERT OR REPLACE INTO state_fields (name, shape, value) VALUES (?1, ?2, ?3)",
+                    params![name, shape, value],
+                )?;
+            }
+        }
+        for (field, key) in prev.items.keys().filter(|k| !next.items.contains_key(*k)) {
+            self.conn.execute(
+                "DELETE FROM state_items WHERE field = ?1 AND key = ?2",
+                params![field, key],
+            )?;
+        }
+        for ((field, key), value) in &next.items {
+            if prev.items.get(&(field.clone(), key.clone())) != Some(value) {
+                self.conn.execute(
+                    "INSERT OR REPLACE INTO state_items (field, key, value) VALUES (?1, ?2, ?3)",
+                    params![field, key, value],
+                )?;
+            }
+        }
+        let appended = self.append_history(state, stored, full)?;
+        self.cached = Some((next, appended));
+        Ok(())
+    }
+}
+
+impl StateStore for SqliteStore {
+    fn load(&mut self) -> Result<CompanyState, StoreError> {
+        self.load_rows()?.ok_or_else(|| {
+            StoreError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                "sqlite store has no state; run `bnet init` first",
+            ))
+        })
+    }
+
+    fn load_events(&mut self, after: u64) -> Result<Vec<RecordedEvent>, StoreError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT body FROM events WHERE seq > ?1 ORDER BY seq")?;
+        let rows = stmt.query_map(params![after as i64], |r| r.get::<_, String>(0))?;
+        let mut events = vec![];
+        for body in rows {
+            events.push(serde_json::from_str(&body?)?);
+        }
+        Ok(events)
+    }
+
+    fn save(&mut self, state: &CompanyState) -> Result<(), StoreError> {
+        self.begin_immediate()?;
+        match self.write_rows(state, true) {
+            Ok(()) => Ok(self.conn.execute_batch("COMMIT")?),
+            Err(err) => {
+                self.cached = None;
+                self.conn.execute_batch("ROLLBACK")?;
+                Err(err)
+            }
+        }
+    }
+
+    fn begin(&mut self) -> Result<Option<CompanyState>, StoreError> {
+        self.begin_immediate()?;
+        match self.load_rows() {
+            Ok(state) => Ok(state),
+            Err(err) => {
+                self.conn.execute_batch("ROLLBACK")?;
+                Err(err)
+            }
+        }
+    }
+
+    fn commit(&mut self, state: &CompanyState, events: &[RecordedEvent]) -> Result<(), StoreError> {
+        // A rebuild commits no events, and an import starts history over;
+        // both rewrite the history tables rather than extend them.
+        let full = events.is_empty() || events.iter().any(|e| e.event.starts_history());
+        // Log rows and snapshot rows share one SQLite transaction.
+        match self
+            .append_events(events)
+            .and_then(|_| self.write_rows(state, full))
+        {
+            Ok(()) => Ok(self.conn.execute_batch("COMMIT")?),
+            Err(err) => {
+                self.rollback()?;
+                Err(err)
+            }
+        }
+    }
+
+    fn rollback(&mut self) -> Result<(), StoreError> {
+        self.cached = None;
+        self.conn.execute_batch("ROLLBACK")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::Amount;
+    use crate::model::{ProposalKind, RevenueEvent, VestingTerms};
+    use chrono::TimeZone;
+
+    /// A fresh path under the system temp dir, unique to this test run.
+    fn scratch_path(name: &str) -> PathBuf {
+        let nanos = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+        std::env::temp_dir().join(format!("bnet-{}-{}-{}", std::process::id(), nanos, name))
+    }
+
+    /// Writes a short company history in several commits so the snapshot
+    /// and the log both carry part of it.
+    fn write_history(store: &mut dyn StateStore) {
+        let steps: Vec<Vec<CompanyEvent>> = vec![
+            vec![CompanyEvent::Initialized { employees: 0 }],
+            vec![
+                CompanyEvent::HolderAdded {
+                    id: "alice".into(),
+                    name: "Alice".into(),
+                    cash: Amount::whole(100),
+                },
+                CompanyEvent::HolderAdded {
+                    id: "bob".into(),
+                    name: "Bob".into(),
+                    cash: Amount::ZERO,
+                },
+            ],
+            vec![
+                CompanyEvent::RevenueIngested {
+                    revenue: RevenueEvent {
+                        timestamp: Utc.with_ymd_and_hms(2025, 1, 8, 9, 0, 0).unwrap(),
+                        gross_revenue: Amount::whole(1_000),
+                        refund_amount: Amount::whole(100),
+                    },
+                },
+                CompanyEvent::CompletedWeekEmitted {
+                    week: "2025-W02".into(),
+                },
+            ],
+            vec![
+                CompanyEvent::TokensGranted {
+                    holder_id: "alice".into(),
+                    amount: Amount::whole(50),
+                    vesting: None,
+                },
+                CompanyEvent::TokensGranted {
+                    holder_id: "bob".into(),
+                    amount: Amount::whole(30),
+                    vesting: Some(VestingTerms {
+                        cliff_days: 0,
+                        duration_days: 30,
+                    }),
+                },
+                CompanyEvent::TokensStaked {
+                    holder_id: "alice".into(),
+                    amount: Amount::whole(20),
+                },
+                CompanyEvent::VotesDelegated {
+                    delegator_id: "bob".into(),
+                    delegate_id: "alice".into(),
+                    kind: Some(ProposalKind::MintTokens),
+                },
+                CompanyEvent::TokenPriceRecorded {
+                    price: 1.5,
+                    observed_at: None,
+                },
+            ],
+        ];
+        for events in steps {
+            update(store, Some("tester"), |tx| {
+                for event in events {
+                    tx.record(event)?;
+                }
+                Ok(())
+            })
+            .unwrap();
+        }
+    }
+
+    fn assert_replay_matches(store: &mut dyn StateStore) {
+        let committed = current_state(store).unwrap();
+        assert_eq!(committed.event_seq, 10);
+        let replayed = replay(&store.load_events(0).unwrap()).unwrap();
+        assert!(verify(&replayed).is_empty());
+        assert_eq!(
+            serde_json::to_value(&replayed).unwrap(),
+            serde_json::to_value(&committed).unwrap()
+        );
+        let rebuilt = rebuild(store).unwrap();
+        assert_eq!(
+            serde_json::to_value(&rebuilt).unwrap(),
+            serde_json::to_value(&committed).unwrap()
+        );
+    }
+
+    #[test]
+    fn json_store_replay_reproduces_committed_state() {
+        let path = scratch_path("state.json");
+        let mut store = JsonFileStore::new(&path);
+        write_history(&mut store);
+        assert_replay_matches(&mut store);
+        for suffix in ["events.jsonl", "lock"] {
+            let _ = fs::remove_file(sibling_path(&path, suffix));
+        }
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn sqlite_store_replay_reproduces_committed_state() {
+        let path = scratch_path("bnet.db");
+        let mut store = SqliteStore::open(&path).unwrap();
+        write_history(&mut store);
+        assert_replay_matches(&mut store);
+        drop(store);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn sqlite_list_rows_keep_their_keys_and_history_only_appends() {
+        let path = scratch_path("rows.db");
+        let mut store = SqliteStore::open(&path).unwrap();
+        write_history(&mut store);
+        let before = store.read_rows().unwrap().order("revenue_events").unwrap();
+
+        let mut state = current_state(&mut store).unwrap();
+        let mut earlier = state.revenue_events[0].clone();
+        earlier.timestamp = Utc.with_ymd_and_hms(2025, 1, 7, 9, 0, 0).unwrap();
+        state.revenue_events.insert(0, earlier);
+        store.save(&state).unwrap();
+        let after = store.read_rows().unwrap().order("revenue_events").unwrap();
+        assert_eq!(after.len(), 2);
+        assert_eq!(after[1..], before[..]);
+
+        update(&mut store, None, |tx| {
+            tx.record(CompanyEvent::TokenPriceRecorded {
+                price: 1.2,
+                observed_at: Some(Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap()),
+            })
+        })
+        .unwrap();
+        let count = |table: &str| -> i64 {
+            let sql = format!("SELECT COUNT(*) FROM {}", table);
+            store.conn.query_row(&sql, [], |r| r.get(0)).unwrap()
+        };
+        assert_eq!(count("price_history"), 2);
+        assert_eq!(count("journal") as usize, state.journal.len());
+        let snapshot_rows: i64 = store
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM state_items WHERE field IN ('journal', 'token_price_history')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(snapshot_rows, 0);
+
+        let committed = current_state(&mut store).unwrap();
+        assert_eq!(committed.token_price_history[0].1, 1.2);
+        let mut reopened = SqliteStore::open(&path).unwrap();
+        assert_eq!(
+            serde_json::to_value(reopened.load().unwrap()).unwrap(),
+            serde_json::to_value(&committed).unwrap()
+        );
+        drop((store, reopened));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn second_writer_is_turned_away_while_the_lock_is_held() {
+        let path = scratch_path("locked.json");
+        let held = StateLock::acquire(&path).unwrap();
+        assert!(matches!(
+            StateLock::acquire(&path),
+            Err(StoreError::Locked { path: p }) if p == path
+        ));
+        let mut store = JsonFileStore::new(&path);
+        assert!(matches!(store.begin(), Err(StoreError::Locked { .. })));
+        drop(held);
+        assert!(store.begin().is_ok());
+        store.rollback().unwrap();
+        let _ = fs::remove_file(sibling_path(&path, "lock"));
+
+        let path = scratch_path("locked.db");
+        let mut first = SqliteStore::open(&path).unwrap();
+        let mut second = SqliteStore::open(&path).unwrap();
+        first.begin().unwrap();
+        assert!(matches!(second.begin(), Err(StoreError::Locked { .. })));
+        first.rollback().unwrap();
+        assert!(second.begin().is_ok());
+        second.rollback().unwrap();
+        drop((first, second));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn save_state_replaces_the_file_without_leaving_a_temp_file() {
+        let path = scratch_path("saved.json");
+        let mut state = new_company(3, Utc::now());
+        save_state(&path, &state).unwrap();
+        state.employee_count = 7;
+        save_state(&path, &state).unwrap();
+        let saved: CompanyState =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved.employee_count, 7);
+        let tmp = sibling_path(&path, &format!("tmp-{}", std::process::id()));
+        assert!(!tmp.exists());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn json_store_drops_a_torn_final_line() {
+        let path = scratch_path("torn.json");
+        let mut store = JsonFileStore::new(&path);
+        write_history(&mut store);
+        let log = sibling_path(&path, "events.jsonl");
+        let mut file = OpenOptions::new().append(true).open(&log).unwrap();
+        file.write_all(br#"{"seq":11,"at":"2025-01-"#).unwrap();
+        drop(file);
+        assert_eq!(store.load_events(0).unwrap().len(), 10);
+        assert_replay_matches(&mut store);
+
+        update(&mut store, None, |tx| {
+            tx.record(CompanyEvent::TokenPriceRecorded {
+                price: 2.0,
+                observed_at: None,
+            })
+        })
+        .unwrap();
+        let events = store.load_events(0).unwrap();
+        assert_eq!(events.last().map(|e| e.seq), Some(11));
+
+        let mut file = OpenOptions::new().append(true).open(&log).unwrap();
+        file.write_all(b"not json\n").unwrap();
+        drop(file);
+        assert!(store.load_events(0).is_err());
+        for suffix in ["events.jsonl", "lock"] {
+            let _ = fs::remove_file(sibling_path(&path, suffix));
+        }
+        let _ = fs::remove_file(&path);
+    }
 }